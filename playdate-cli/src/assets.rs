@@ -0,0 +1,211 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use cargo_metadata::Package;
+use playdate_cli::sync;
+use serde::Deserialize;
+
+/// A folder of assets, as `"path"` or `{ src = "path", dst = "prefix" }`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum RootConfig {
+    Path(String),
+    Table {
+        src: String,
+        #[serde(default)]
+        dst: String,
+    },
+}
+
+/// Asset folders and filters. Used for the whole package, and for examples, features and profiles.
+#[derive(Deserialize, Debug, Clone, Default)]
+struct AssetSetConfig {
+    roots: Option<Vec<RootConfig>>,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+/// `[package.metadata.playdate.assets]`
+#[derive(Deserialize, Debug, Default)]
+struct AssetsConfig {
+    #[serde(flatten)]
+    package: AssetSetConfig,
+    /// Replaces the package set when building the example
+    #[serde(default)]
+    examples: HashMap<String, AssetSetConfig>,
+    /// Added to the package set when the feature is enabled
+    #[serde(default)]
+    features: HashMap<String, AssetSetConfig>,
+    /// Added to the package set when building with the profile
+    #[serde(default)]
+    profiles: HashMap<String, AssetSetConfig>,
+}
+
+/// A folder copied into the pdx
+#[derive(Debug, Clone)]
+pub struct AssetRoot {
+    /// Absolute path of the folder
+    pub src: PathBuf,
+    /// Folder inside the pdx, with `/` separators. Empty for the root of the pdx.
+    pub dst: String,
+}
+
+/// The assets to bundle, resolved from `[package.metadata.playdate.assets]` for a build
+#[derive(Debug)]
+pub struct AssetRules {
+    pub roots: Vec<AssetRoot>,
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+}
+
+/// Returns the enabled features of `package`, including the features they enable
+pub fn resolve_features(package: &Package, features: &clap_cargo::Features) -> HashSet<String> {
+    if features.all_features {
+        return package.features.keys().cloned().collect();
+    }
+    let mut pending = features
+        .features
+        .iter()
+        .flat_map(|f| f.split([',', ' ']))
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_owned())
+        .collect::<Vec<_>>();
+    if !features.no_default_features && package.features.contains_key("default") {
+        pending.push("default".to_owned());
+    }
+    let mut enabled = HashSet::new();
+    while let Some(feature) = pending.pop() {
+        if !enabled.insert(feature.clone()) {
+            continue;
+        }
+        // Only follow the features of this package, not `dep:x` or `x/y`
+        for sub in package.features.get(&feature).into_iter().flatten() {
+            if package.features.contains_key(sub) {
+                pending.push(sub.clone());
+            }
+        }
+    }
+    enabled
+}
+
+fn compile_patterns(patterns: &[String], key: &str) -> anyhow::Result<Vec<glob::Pattern>> {
+    patterns
+        .iter()
+        .map(|p| {
+            glob::Pattern::new(p)
+                .map_err(|e| anyhow::anyhow!("Invalid pattern `{}` in `{}`: {}", p, key, e))
+        })
+        .collect()
+}
+
+impl AssetRules {
+    /// Resolves the asset rules of `package` for a build of `example` (or the package itself), with the `features` enabled and the `profile` (`dev` or `release`)
+    pub fn load(
+        package: &Package,
+        example: Option<&str>,
+        features: &HashSet<String>,
+        profile: &str,
+    ) -> anyhow::Result<Self> {
+        const KEY: &str = "package.metadata.playdate.assets";
+        let config = match package
+            .metadata
+            .get("playdate")
+            .and_then(|p| p.get("assets"))
+        {
+            Some(value) => serde_json::from_value::<AssetsConfig>(value.clone())
+                .map_err(|e| anyhow::anyhow!("Invalid `{}`: {}", KEY, e))?,
+            None => AssetsConfig::default(),
+        };
+        let package_dir = package.manifest_path.parent().unwrap().as_std_path();
+        // Examples share `examples/assets` by default
+        let (base, default_root) = match example {
+            Some(example) => (
+                config.examples.get(example).cloned().unwrap_or_default(),
+                "examples/assets",
+            ),
+            None => (config.package.clone(), "assets"),
+        };
+        let mut sets = vec![base];
+        let mut enabled = features.iter().collect::<Vec<_>>();
+        enabled.sort();
+        sets.extend(
+            enabled
+                .iter()
+                .filter_map(|f| config.features.get(*f).cloned()),
+        );
+        sets.extend(config.profiles.get(profile).cloned());
+        let mut roots = vec![];
+        let mut include = vec![];
+        let mut exclude = vec![];
+        for (i, set) in sets.into_iter().enumerate() {
+            let set_roots = match set.roots {
+                Some(roots) => roots,
+                // Only the base set has a default root
+                None if i == 0 => vec![RootConfig::Path(default_root.to_owned())],
+                None => vec![],
+            };
+            for root in set_roots {
+                let (src, dst) = match root {
+                    RootConfig::Path(src) => (src, String::new()),
+                    RootConfig::Table { src, dst } => (src, dst),
+                };
+                roots.push(AssetRoot {
+                    src: package_dir.join(src),
+                    dst: dst.trim_matches('/').to_owned(),
+                });
+            }
+            include.extend(compile_patterns(&set.include, KEY)?);
+            exclude.extend(compile_patterns(&set.exclude, KEY)?);
+        }
+        Ok(Self {
+            roots,
+            include,
+            exclude,
+        })
+    }
+
+    /// Returns `true` if a file at `path` inside the pdx is bundled
+    pub fn matches(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(path)))
+            && !self.exclude.iter().any(|p| p.matches(path))
+    }
+
+    /// Returns `true` if `path` is inside one of the asset folders
+    pub fn contains(&self, path: &Path) -> bool {
+        self.roots.iter().any(|r| path.starts_with(&r.src))
+    }
+
+    /// Lists the files to bundle, as their path inside the pdx and their source path. If several folders have a file with the same path, the last folder wins.
+    pub fn list_files(&self) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        let mut files = HashMap::new();
+        for root in &self.roots {
+            if !root.src.is_dir() {
+                debug!("Asset folder {} not found", root.src.to_string_lossy());
+                continue;
+            }
+            for (relative, source) in sync::list_files(&root.src)? {
+                let path = if root.dst.is_empty() {
+                    relative
+                } else {
+                    format!("{}/{}", root.dst, relative)
+                };
+                if !self.matches(&path) {
+                    continue;
+                }
+                if let Some(previous) = files.insert(path.clone(), source) {
+                    warn!(
+                        "{} is overridden by a later asset folder",
+                        previous.to_string_lossy()
+                    );
+                }
+            }
+        }
+        let mut files = files.into_iter().collect::<Vec<_>>();
+        files.sort();
+        Ok(files)
+    }
+}
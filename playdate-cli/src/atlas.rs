@@ -0,0 +1,320 @@
+//! Build-time packing of small images into texture atlases for `playdate_rs::graphics::Atlas`, configured by `[[package.metadata.playdate.atlases]]` rules.
+//!
+//! An atlas `<path>` is written as the sheets `<path>-<n>.png` and the index `<path>.atlas`: a `sheets=<count>` line, then a line per image with tab-separated fields: name, sheet, x, y, width and height of the image in the sheet, x and y of the trimmed image in the original image, width and height of the original image.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    images::{decode, Image},
+    pipeline::{self, Cache, Outputs, Rules},
+};
+
+/// Bump when the output of the pipeline changes, to invalidate the cache
+const VERSION: u32 = 1;
+
+fn default_max_size() -> [usize; 2] {
+    [512, 512]
+}
+
+fn default_padding() -> usize {
+    1
+}
+
+/// Packing settings of an atlas rule
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct AtlasSettings {
+    /// Path of the atlas inside the pdx, without extension
+    pub path: String,
+    /// Maximum width and height of a sheet. Images that do not fit in a sheet go to the next one
+    #[serde(default = "default_max_size")]
+    pub max_size: [usize; 2],
+    /// Pixels between the images of a sheet
+    #[serde(default = "default_padding")]
+    pub padding: usize,
+    /// Crop the transparent borders of the images. The atlas keeps their original size, so they are drawn at the same place
+    #[serde(default)]
+    pub trim: bool,
+}
+
+/// Packs the PNG images of the asset folders into atlases, caching the results by content
+pub struct AtlasPipeline {
+    rules: Rules<AtlasSettings>,
+    cache: Cache,
+}
+
+impl AtlasPipeline {
+    /// Loads the `[[package.metadata.playdate.atlases]]` rules from the package metadata. Returns `None` if there are no rules.
+    pub fn load(metadata: &serde_json::Value, cache_dir: &Path) -> anyhow::Result<Option<Self>> {
+        Ok(Rules::load(metadata, "atlases")?.map(|rules| Self {
+            rules,
+            cache: Cache::new(cache_dir, VERSION),
+        }))
+    }
+
+    /// Replaces the images matching a rule in `files` (paths inside the pdx and source paths) by the sheets and the index of their atlas
+    pub fn process(&self, files: Vec<(String, PathBuf)>) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        let mut outputs = Outputs::default();
+        let mut groups = vec![vec![]; self.rules.len()];
+        for (relative, source) in files {
+            match self.rules.find_index(&relative) {
+                Some(i) => groups[i].push((relative, source)),
+                None => outputs.insert(relative, source),
+            }
+        }
+        for (i, images) in groups.iter().enumerate() {
+            let settings = self.rules.get(i);
+            if images.is_empty() {
+                warn!("No image matches the atlas {}", settings.path);
+                continue;
+            }
+            let relative = format!("{}.atlas", settings.path);
+            let entry = self
+                .cache
+                .get_or_combine(&relative, images, settings, |dir| {
+                    pack(images, settings, dir)
+                })
+                .map_err(|e| anyhow::anyhow!("Failed to pack {}: {}", relative, e))?;
+            outputs.insert_entry(&relative, &entry)?;
+        }
+        Ok(outputs.into_vec())
+    }
+}
+
+/// An image placed in a sheet
+struct Placement {
+    name: String,
+    image: Image,
+    /// Position of `image` in the original image
+    offset: [usize; 2],
+    /// Size of the original image
+    size: [usize; 2],
+    sheet: usize,
+    x: usize,
+    y: usize,
+}
+
+/// Crops the transparent borders of `image`. Returns the cropped image and its position in `image`.
+fn trim(image: &Image) -> (Image, [usize; 2]) {
+    let opaque = |x: usize, y: usize| image.pixels[y * image.width + x][3] != 0;
+    let rows = (0..image.height)
+        .filter(|y| (0..image.width).any(|x| opaque(x, *y)))
+        .collect::<Vec<_>>();
+    let columns = (0..image.width)
+        .filter(|x| (0..image.height).any(|y| opaque(*x, y)))
+        .collect::<Vec<_>>();
+    match (rows.first(), rows.last(), columns.first(), columns.last()) {
+        (Some(top), Some(bottom), Some(left), Some(right)) => (
+            image.crop(*left, *top, right - left + 1, bottom - top + 1),
+            [*left, *top],
+        ),
+        // Fully transparent images keep a single pixel
+        _ => (image.crop(0, 0, 1, 1), [0, 0]),
+    }
+}
+
+/// Free space of a sheet, as the heights of consecutive segments of columns
+struct Skyline {
+    width: usize,
+    height: usize,
+    /// Start, width and height of the segments
+    segments: Vec<(usize, usize, usize)>,
+}
+
+impl Skyline {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            segments: vec![(0, width, 0)],
+        }
+    }
+
+    /// Places a `width` by `height` rect as low as possible, then as left as possible. Returns its position.
+    fn place(&mut self, width: usize, height: usize) -> Option<(usize, usize)> {
+        let mut best: Option<(usize, usize, usize)> = None;
+        for (i, (x, _, _)) in self.segments.iter().enumerate() {
+            if x + width > self.width {
+                break;
+            }
+            // The rect rests on the highest segment under it
+            let mut y = 0;
+            let mut covered = 0;
+            for (_, w, h) in &self.segments[i..] {
+                y = y.max(*h);
+                covered += w;
+                if covered >= width {
+                    break;
+                }
+            }
+            if y + height <= self.height && best.is_none_or(|(by, bx, _)| (y, *x) < (by, bx)) {
+                best = Some((y, *x, i));
+            }
+        }
+        let (y, x, _) = best?;
+        // Replace the segments under the rect by a segment on top of it
+        let end = x + width;
+        let mut segments = vec![];
+        for (sx, sw, sh) in &self.segments {
+            let send = sx + sw;
+            if send <= x || *sx >= end {
+                segments.push((*sx, *sw, *sh));
+                continue;
+            }
+            if *sx < x {
+                segments.push((*sx, x - sx, *sh));
+            }
+            if send > end {
+                segments.push((end, send - end, *sh));
+            }
+        }
+        segments.push((x, width, y + height));
+        segments.sort();
+        self.segments = segments;
+        Some((x, y))
+    }
+}
+
+/// Writes the sheets and the index of the atlas of `images` into `dir`
+fn pack(images: &[(String, PathBuf)], settings: &AtlasSettings, dir: &Path) -> anyhow::Result<()> {
+    let [max_width, max_height] = settings.max_size;
+    if max_width == 0 || max_height == 0 {
+        anyhow::bail!("`max_size` must not be 0");
+    }
+    let mut placements = vec![];
+    for (relative, source) in images {
+        if !relative.to_ascii_lowercase().ends_with(".png") {
+            anyhow::bail!("{} is not a PNG image", relative);
+        }
+        let name = relative[..relative.len() - 4].to_owned();
+        if name.contains(['\t', '\n']) {
+            anyhow::bail!("{} has a tab or a newline in its name", relative);
+        }
+        let original = decode::decode_png(source)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", relative, e))?;
+        let size = [original.width, original.height];
+        let (image, offset) = if settings.trim {
+            trim(&original)
+        } else {
+            (original, [0, 0])
+        };
+        if image.width > max_width || image.height > max_height {
+            anyhow::bail!(
+                "{} ({}x{}) is larger than `max_size`",
+                relative,
+                image.width,
+                image.height
+            );
+        }
+        placements.push(Placement {
+            name,
+            image,
+            offset,
+            size,
+            sheet: 0,
+            x: 0,
+            y: 0,
+        });
+    }
+    // Tall images first, which packs tighter
+    placements.sort_by(|a, b| {
+        (b.image.height, b.image.width)
+            .cmp(&(a.image.height, a.image.width))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    // The padding is added to the right and the bottom of each image, so the sheets can hold it past their edges
+    let padding = settings.padding;
+    let mut sheets: Vec<Skyline> = vec![];
+    for placement in &mut placements {
+        let (width, height) = (
+            placement.image.width + padding,
+            placement.image.height + padding,
+        );
+        let placed = sheets
+            .iter_mut()
+            .enumerate()
+            .find_map(|(i, sheet)| sheet.place(width, height).map(|(x, y)| (i, x, y)));
+        let (sheet, x, y) = match placed {
+            Some(placed) => placed,
+            None => {
+                let mut sheet = Skyline::new(max_width + padding, max_height + padding);
+                let (x, y) = sheet.place(width, height).unwrap();
+                sheets.push(sheet);
+                (sheets.len() - 1, x, y)
+            }
+        };
+        placement.sheet = sheet;
+        placement.x = x;
+        placement.y = y;
+    }
+    for i in 0..sheets.len() {
+        let placed = placements.iter().filter(|p| p.sheet == i);
+        let width = placed.clone().map(|p| p.x + p.image.width).max().unwrap();
+        let height = placed.clone().map(|p| p.y + p.image.height).max().unwrap();
+        let mut sheet = Image::new(width, height);
+        for p in placed {
+            sheet.paste(&p.image, p.x, p.y);
+        }
+        fs::write(
+            pipeline::output_path(dir, &format!("-{}.png", i)),
+            encode_rgba(&sheet)?,
+        )?;
+    }
+    placements.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut index = format!("sheets={}\n", sheets.len());
+    for p in &placements {
+        index.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            p.name,
+            p.sheet,
+            p.x,
+            p.y,
+            p.image.width,
+            p.image.height,
+            p.offset[0],
+            p.offset[1],
+            p.size[0],
+            p.size[1]
+        ));
+    }
+    fs::write(pipeline::output_path(dir, ".atlas"), index)?;
+    Ok(())
+}
+
+/// Encodes the sheet as is, so `pdc` converts its pixels like those of the original images
+fn encode_rgba(image: &Image) -> anyhow::Result<Vec<u8>> {
+    let mut data = vec![];
+    {
+        let mut encoder = png::Encoder::new(&mut data, image.width as u32, image.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(image.pixels.as_flattened())?;
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skyline_places_bottom_left_first() {
+        let mut sheet = Skyline::new(10, 10);
+        assert_eq!(sheet.place(4, 4), Some((0, 0)));
+        assert_eq!(sheet.place(4, 2), Some((4, 0)));
+        // Too wide for the 2 free columns on the right
+        assert_eq!(sheet.place(3, 3), Some((4, 2)));
+        assert_eq!(sheet.place(2, 8), Some((8, 0)));
+        assert_eq!(sheet.place(4, 6), Some((0, 4)));
+        // Rests on the highest segment under it
+        assert_eq!(sheet.place(6, 1), Some((4, 8)));
+        assert_eq!(sheet.place(1, 2), None);
+        assert_eq!(sheet.place(11, 1), None);
+    }
+}
@@ -0,0 +1,176 @@
+//! IMA ADPCM encoding in the WAV layout (`WAVE_FORMAT_IMA_ADPCM`), which `pdc` compiles for `AudioSample` and `FilePlayer`.
+
+const STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+const INDEX_TABLE: [i32; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+
+/// Bytes of a block for each channel
+const BLOCK_BYTES_PER_CHANNEL: usize = 256;
+
+const WAVE_FORMAT_IMA_ADPCM: u16 = 0x0011;
+
+/// Encoder state of a channel
+#[derive(Default, Clone, Copy)]
+struct Channel {
+    predictor: i32,
+    index: i32,
+}
+
+impl Channel {
+    fn encode(&mut self, sample: i16) -> u8 {
+        let step = STEP_TABLE[self.index as usize];
+        let mut diff = sample as i32 - self.predictor;
+        let mut nibble = 0;
+        if diff < 0 {
+            nibble = 8;
+            diff = -diff;
+        }
+        let mut mask = 4;
+        let mut temp_step = step;
+        while mask > 0 {
+            if diff >= temp_step {
+                nibble |= mask;
+                diff -= temp_step;
+            }
+            temp_step >>= 1;
+            mask >>= 1;
+        }
+        // Track the value the decoder will reconstruct
+        let mut delta = step >> 3;
+        if nibble & 4 != 0 {
+            delta += step;
+        }
+        if nibble & 2 != 0 {
+            delta += step >> 1;
+        }
+        if nibble & 1 != 0 {
+            delta += step >> 2;
+        }
+        self.predictor = if nibble & 8 != 0 {
+            self.predictor - delta
+        } else {
+            self.predictor + delta
+        }
+        .clamp(i16::MIN as i32, i16::MAX as i32);
+        self.index = (self.index + INDEX_TABLE[(nibble & 7) as usize]).clamp(0, 88);
+        nibble as u8
+    }
+}
+
+/// Returns the number of samples per channel in a block
+fn samples_per_block(channels: usize) -> usize {
+    let block_align = BLOCK_BYTES_PER_CHANNEL * channels;
+    (block_align - 4 * channels) * 8 / (4 * channels) + 1
+}
+
+/// Encodes the 16-bit `channels` (of equal length) as an IMA ADPCM WAV file
+pub fn encode_wav(channels: &[Vec<i16>], sample_rate: u32) -> Vec<u8> {
+    let n = channels.len();
+    let frames = channels[0].len();
+    let per_block = samples_per_block(n);
+    let block_align = BLOCK_BYTES_PER_CHANNEL * n;
+    let mut state = vec![Channel::default(); n];
+    let mut data = vec![];
+    for start in (0..frames.max(1)).step_by(per_block) {
+        // The last block is padded with its last sample
+        let sample = |c: usize, i: usize| {
+            channels[c]
+                .get(start + i)
+                .or(channels[c].last())
+                .copied()
+                .unwrap_or(0)
+        };
+        // Block header: the first sample is stored as is
+        for (c, s) in state.iter_mut().enumerate() {
+            s.predictor = sample(c, 0) as i32;
+            data.extend_from_slice(&(s.predictor as i16).to_le_bytes());
+            data.push(s.index as u8);
+            data.push(0);
+        }
+        // Then groups of 8 samples (4 bytes) per channel, low nibble first
+        for group in (1..per_block).step_by(8) {
+            for (c, s) in state.iter_mut().enumerate() {
+                for pair in 0..4 {
+                    let low = s.encode(sample(c, group + pair * 2));
+                    let high = s.encode(sample(c, group + pair * 2 + 1));
+                    data.push(low | (high << 4));
+                }
+            }
+        }
+    }
+    let mut wav = vec![];
+    let fmt_size = 20u32;
+    let riff_size = 4 + (8 + fmt_size) + (8 + 4) + (8 + data.len() as u32);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&riff_size.to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&fmt_size.to_le_bytes());
+    wav.extend_from_slice(&WAVE_FORMAT_IMA_ADPCM.to_le_bytes());
+    wav.extend_from_slice(&(n as u16).to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    let byte_rate = sample_rate as u64 * block_align as u64 / per_block as u64;
+    wav.extend_from_slice(&(byte_rate as u32).to_le_bytes());
+    wav.extend_from_slice(&(block_align as u16).to_le_bytes());
+    wav.extend_from_slice(&4u16.to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&(per_block as u16).to_le_bytes());
+    // Number of samples per channel, without the padding of the last block
+    wav.extend_from_slice(b"fact");
+    wav.extend_from_slice(&4u32.to_le_bytes());
+    wav.extend_from_slice(&(frames as u32).to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&data);
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u16(wav: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([wav[offset], wav[offset + 1]])
+    }
+
+    fn read_u32(wav: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(wav[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn encode_wav_block_layout() {
+        let samples = (0..1000).map(|i| (i * 30) as i16).collect::<Vec<_>>();
+        for n in [1, 2] {
+            let wav = encode_wav(&vec![samples.clone(); n], 22050);
+            let block_align = BLOCK_BYTES_PER_CHANNEL * n;
+            assert_eq!(&wav[0..4], b"RIFF");
+            assert_eq!(read_u32(&wav, 4) as usize, wav.len() - 8);
+            assert_eq!(read_u16(&wav, 20), WAVE_FORMAT_IMA_ADPCM);
+            assert_eq!(read_u16(&wav, 22) as usize, n);
+            assert_eq!(read_u16(&wav, 32) as usize, block_align);
+            assert_eq!(read_u16(&wav, 34), 4);
+            // 505 samples per block: the header sample, then 2 per byte
+            assert_eq!(read_u16(&wav, 38), 505);
+            assert_eq!(&wav[40..44], b"fact");
+            assert_eq!(read_u32(&wav, 48), 1000);
+            assert_eq!(&wav[52..56], b"data");
+            let data = &wav[60..];
+            assert_eq!(read_u32(&wav, 56) as usize, data.len());
+            // 1000 samples fill 2 blocks, each starting with the first sample of every channel
+            assert_eq!(data.len(), 2 * block_align);
+            for (block, first) in [(0, 0), (1, 505 * 30)] {
+                for c in 0..n {
+                    let header = block * block_align + c * 4;
+                    assert_eq!(read_u16(data, header) as i16, first as i16);
+                    assert_eq!(data[header + 3], 0);
+                }
+            }
+        }
+    }
+}
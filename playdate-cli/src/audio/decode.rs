@@ -0,0 +1,76 @@
+use std::{fs, io::Cursor, path::Path};
+
+use super::Audio;
+
+/// Splits interleaved samples into one buffer per channel
+fn deinterleave(samples: impl Iterator<Item = f32>, channels: usize) -> Vec<Vec<f32>> {
+    let mut out = vec![vec![]; channels];
+    for (i, s) in samples.enumerate() {
+        out[i % channels].push(s);
+    }
+    out
+}
+
+fn decode_wav(path: &Path) -> anyhow::Result<Audio> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+    Ok(Audio {
+        sample_rate: spec.sample_rate,
+        channels: deinterleave(samples.into_iter(), spec.channels as usize),
+    })
+}
+
+fn decode_ogg(path: &Path) -> anyhow::Result<Audio> {
+    let mut reader = lewton::inside_ogg::OggStreamReader::new(Cursor::new(fs::read(path)?))?;
+    let channels = reader.ident_hdr.audio_channels as usize;
+    let mut samples = vec![];
+    while let Some(packet) = reader.read_dec_packet_itl()? {
+        samples.extend(packet.into_iter().map(|s| s as f32 / 32768.0));
+    }
+    Ok(Audio {
+        sample_rate: reader.ident_hdr.audio_sample_rate,
+        channels: deinterleave(samples.into_iter(), channels),
+    })
+}
+
+fn decode_flac(path: &Path) -> anyhow::Result<Audio> {
+    let mut reader = claxon::FlacReader::open(path)?;
+    let info = reader.streaminfo();
+    let scale = (1u64 << (info.bits_per_sample - 1)) as f32;
+    let samples = reader
+        .samples()
+        .map(|s| s.map(|s| s as f32 / scale))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Audio {
+        sample_rate: info.sample_rate,
+        channels: deinterleave(samples.into_iter(), info.channels as usize),
+    })
+}
+
+/// Decodes an audio file from its extension
+pub fn decode(path: &Path) -> anyhow::Result<Audio> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let audio = match ext.as_str() {
+        "wav" | "wave" => decode_wav(path)?,
+        "ogg" | "oga" => decode_ogg(path)?,
+        "flac" => decode_flac(path)?,
+        _ => anyhow::bail!("Unsupported audio format, expected wav, ogg or flac"),
+    };
+    if audio.channels.is_empty() {
+        anyhow::bail!("The audio file has no channels");
+    }
+    Ok(audio)
+}
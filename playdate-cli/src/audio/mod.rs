@@ -0,0 +1,248 @@
+//! Build-time transcoding of audio files to the WAV formats `pdc` compiles, configured by `[[package.metadata.playdate.audio]]` rules.
+
+mod adpcm;
+mod decode;
+
+use std::{
+    f64::consts::PI,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::pipeline::{self, Cache, Outputs, Rules};
+
+/// Bump when the output of the pipeline changes, to invalidate the cache
+const VERSION: u32 = 1;
+
+/// Zero crossings of the resampling filter on each side of a sample
+const RESAMPLE_HALF_TAPS: f64 = 16.0;
+
+/// Decoded audio, one buffer of samples between -1 and 1 per channel
+#[derive(Debug, Clone)]
+pub struct Audio {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+/// Sample encoding of the output
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioFormat {
+    /// 4-bit IMA ADPCM, a quarter of the size of 16-bit PCM
+    #[default]
+    Adpcm,
+    /// 16-bit PCM
+    Pcm16,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Channels {
+    Mono,
+    Stereo,
+}
+
+/// Transcoding settings of an audio rule
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AudioSettings {
+    #[serde(default)]
+    pub format: AudioFormat,
+    /// Defaults to the channels of the source, which must be mono or stereo
+    pub channels: Option<Channels>,
+    /// Defaults to the sample rate of the source
+    pub sample_rate: Option<u32>,
+    /// Target RMS loudness in dBFS, e.g. `-16.0`. The gain is limited so the peak stays below 0 dBFS
+    pub normalize: Option<f32>,
+    /// Level in dBFS under which the start and the end of the audio are trimmed, e.g. `-50.0`
+    pub trim_silence: Option<f32>,
+}
+
+impl Hash for AudioSettings {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.format.hash(state);
+        self.channels.hash(state);
+        self.sample_rate.hash(state);
+        self.normalize.map(f32::to_bits).hash(state);
+        self.trim_silence.map(f32::to_bits).hash(state);
+    }
+}
+
+/// Transcodes the audio files of the asset folders, caching the results by content
+pub struct AudioPipeline {
+    rules: Rules<AudioSettings>,
+    cache: Cache,
+}
+
+impl AudioPipeline {
+    /// Loads the `[[package.metadata.playdate.audio]]` rules from the package metadata. Returns `None` if there are no rules.
+    pub fn load(metadata: &serde_json::Value, cache_dir: &Path) -> anyhow::Result<Option<Self>> {
+        Ok(Rules::load(metadata, "audio")?.map(|rules| Self {
+            rules,
+            cache: Cache::new(cache_dir, VERSION),
+        }))
+    }
+
+    /// Replaces the audio files matching a rule in `files` (paths inside the pdx and source paths) by a `.wav` file
+    pub fn process(&self, files: Vec<(String, PathBuf)>) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        let mut outputs = Outputs::default();
+        for (relative, source) in files {
+            let Some(settings) = self.rules.find(&relative) else {
+                outputs.insert(relative, source);
+                continue;
+            };
+            let entry = self
+                .cache
+                .get_or_convert(&source, settings, |source, dir| {
+                    let audio = transform(decode::decode(source)?, settings)?;
+                    fs::write(
+                        pipeline::output_path(dir, ".wav"),
+                        encode(&audio, settings)?,
+                    )?;
+                    Ok(())
+                })
+                .map_err(|e| anyhow::anyhow!("Failed to convert {}: {}", relative, e))?;
+            outputs.insert_entry(&relative, &entry)?;
+        }
+        Ok(outputs.into_vec())
+    }
+}
+
+fn db_to_amplitude(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Applies the channel, trimming, resampling and normalization settings
+fn transform(mut audio: Audio, settings: &AudioSettings) -> anyhow::Result<Audio> {
+    let frames = audio.channels[0].len();
+    audio.channels = match (settings.channels, audio.channels.len()) {
+        (Some(Channels::Mono), n) if n > 1 => {
+            let mix = (0..frames)
+                .map(|i| audio.channels.iter().map(|c| c[i]).sum::<f32>() / n as f32)
+                .collect();
+            vec![mix]
+        }
+        (Some(Channels::Stereo), 1) => vec![audio.channels[0].clone(), audio.channels[0].clone()],
+        (None, n) | (Some(Channels::Stereo), n) if n > 2 => anyhow::bail!(
+            "The source has {} channels. Set `channels` to \"mono\" to mix them down",
+            n
+        ),
+        _ => audio.channels,
+    };
+    if let Some(db) = settings.trim_silence {
+        let level = db_to_amplitude(db);
+        let loud = |i: usize| audio.channels.iter().any(|c| c[i].abs() >= level);
+        match (0..frames).find(|i| loud(*i)) {
+            Some(start) => {
+                let end = (0..frames).rev().find(|i| loud(*i)).unwrap() + 1;
+                for channel in &mut audio.channels {
+                    *channel = channel[start..end].to_vec();
+                }
+            }
+            None => warn!("Not trimming silence: the whole audio is under {} dBFS", db),
+        }
+    }
+    if let Some(rate) = settings.sample_rate {
+        if rate == 0 {
+            anyhow::bail!("`sample_rate` must not be 0");
+        }
+        for channel in &mut audio.channels {
+            *channel = resample(channel, audio.sample_rate, rate);
+        }
+        audio.sample_rate = rate;
+    }
+    if let Some(db) = settings.normalize {
+        let samples = audio.channels.iter().flatten();
+        let count = audio.channels.iter().map(|c| c.len()).sum::<usize>().max(1);
+        let rms = (samples.clone().map(|s| s * s).sum::<f32>() / count as f32).sqrt();
+        let peak = samples.fold(0f32, |p, s| p.max(s.abs()));
+        if rms > 0.0 {
+            let mut gain = db_to_amplitude(db) / rms;
+            if peak * gain > 1.0 {
+                warn!(
+                    "Normalizing to {} dBFS would clip, the gain is limited to {:.1} dB",
+                    db,
+                    20.0 * (1.0 / peak).log10()
+                );
+                gain = 1.0 / peak;
+            }
+            for s in audio.channels.iter_mut().flatten() {
+                *s *= gain;
+            }
+        }
+    }
+    Ok(audio)
+}
+
+/// Resamples with a Hann windowed sinc filter, low-passed below the new Nyquist frequency when downsampling
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to {
+        return samples.to_vec();
+    }
+    let ratio = from as f64 / to as f64;
+    let cutoff = (1.0 / ratio).min(1.0);
+    let half_width = RESAMPLE_HALF_TAPS / cutoff;
+    let len = (samples.len() as f64 / ratio).round() as usize;
+    (0..len)
+        .map(|i| {
+            let t = i as f64 * ratio;
+            let first = (t - half_width).ceil().max(0.0) as usize;
+            let last = ((t + half_width).floor() as usize).min(samples.len().saturating_sub(1));
+            let (mut sum, mut weights) = (0.0, 0.0);
+            for (k, sample) in samples.iter().enumerate().take(last + 1).skip(first) {
+                let d = t - k as f64;
+                let x = d * cutoff;
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (PI * x).sin() / (PI * x)
+                };
+                let window = 0.5 + 0.5 * (PI * d / half_width).cos();
+                let w = sinc * window;
+                sum += *sample as f64 * w;
+                weights += w;
+            }
+            if weights == 0.0 {
+                0.0
+            } else {
+                (sum / weights) as f32
+            }
+        })
+        .collect()
+}
+
+/// Encodes the audio as a WAV file in the format of `settings`
+fn encode(audio: &Audio, settings: &AudioSettings) -> anyhow::Result<Vec<u8>> {
+    let channels = audio
+        .channels
+        .iter()
+        .map(|c| {
+            c.iter()
+                .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    match settings.format {
+        AudioFormat::Adpcm => Ok(adpcm::encode_wav(&channels, audio.sample_rate)),
+        AudioFormat::Pcm16 => {
+            let spec = hound::WavSpec {
+                channels: channels.len() as u16,
+                sample_rate: audio.sample_rate,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let mut data = std::io::Cursor::new(vec![]);
+            let mut writer = hound::WavWriter::new(&mut data, spec)?;
+            for i in 0..channels[0].len() {
+                for channel in &channels {
+                    writer.write_sample(channel[i])?;
+                }
+            }
+            writer.finalize()?;
+            Ok(data.into_inner())
+        }
+    }
+}
@@ -6,12 +6,12 @@ use std::{
 
 use cargo_metadata::{Metadata, MetadataCommand, Package, Target};
 
-use crate::{util::CommandExt, Runnable};
+use playdate_cli::{
+    atlas::AtlasPipeline, audio::AudioPipeline, fonts::FontPipeline, images::ImagePipeline,
+    manifest, sync,
+};
 
-static PDXINFO: &str = include_str!(concat!(
-    env!("CARGO_MANIFEST_DIR"),
-    "/templates/pdxinfo.template"
-));
+use crate::{assets::AssetRules, pdxinfo, util::CommandExt, Runnable};
 
 static DYLIB_EXT: &str = if cfg!(target_os = "macos") {
     "dylib"
@@ -25,6 +25,74 @@ static PDEX_SO: &str = if cfg!(target_os = "macos") {
     "pdex.so"
 };
 
+/// Returns the cargo flags selecting `features`
+pub(crate) fn get_feature_flags(features: &clap_cargo::Features) -> Vec<String> {
+    let mut flags = vec![];
+    if !features.features.is_empty() {
+        flags.push("--features".to_owned());
+        flags.push(features.features.join(","));
+    }
+    if features.no_default_features {
+        flags.push("--no-default-features".to_owned());
+    }
+    if features.all_features {
+        flags.push("--all-features".to_owned());
+    }
+    flags
+}
+
+/// Returns the package named `name`, or the package under the current directory
+pub(crate) fn find_package(meta: &Metadata, name: Option<&String>) -> anyhow::Result<Package> {
+    if let Some(name) = name {
+        meta.packages
+            .iter()
+            .find(|p| &p.name == name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No rust package found with name {}", name))
+    } else {
+        meta.root_package()
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No rust package found under current directory"))
+    }
+}
+
+/// Converts the images, audio files and fonts of `files` (paths inside the pdx and source paths) matching `[[package.metadata.playdate.images]]`, `[[package.metadata.playdate.audio]]` and `[[package.metadata.playdate.fonts]]`, and packs the images matching `[[package.metadata.playdate.atlases]]`
+pub(crate) fn convert_assets(
+    meta: &Metadata,
+    package: &Package,
+    mut files: Vec<(String, PathBuf)>,
+) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    // The cache is shared by all profiles and examples
+    let cache_dir = meta.target_directory.as_std_path().join("playdate-cache");
+    if let Some(images) = ImagePipeline::load(&package.metadata, &cache_dir.join("images"))? {
+        files = images.process(files)?;
+    }
+    // Atlases pack the converted images
+    if let Some(atlases) = AtlasPipeline::load(&package.metadata, &cache_dir.join("atlases"))? {
+        files = atlases.process(files)?;
+    }
+    if let Some(audio) = AudioPipeline::load(&package.metadata, &cache_dir.join("audio"))? {
+        files = audio.process(files)?;
+    }
+    if let Some(fonts) = FontPipeline::load(&package.metadata, &cache_dir.join("fonts"))? {
+        files = fonts.process(files)?;
+    }
+    Ok(files)
+}
+
+/// Writes the asset manifest of `files` included by `playdate_rs::include_assets!` to `<dir>/<target_name>.rs`. Returns `true` if it changed.
+pub(crate) fn write_asset_manifest(
+    dir: &Path,
+    target_name: &str,
+    files: &[(String, PathBuf)],
+) -> anyhow::Result<bool> {
+    let manifest = manifest::generate(files)?;
+    Ok(sync::write_if_changed(
+        &dir.join(format!("{}.rs", target_name)),
+        manifest,
+    )?)
+}
+
 /// Compile the current package
 #[derive(clap::Args, Debug)]
 pub struct Build {
@@ -50,16 +118,7 @@ impl Build {
         if self.release {
             flags.push("--release".to_owned());
         }
-        if !self.features.features.is_empty() {
-            flags.push("--features".to_owned());
-            flags.push(self.features.features.join(","));
-        }
-        if self.features.no_default_features {
-            flags.push("--no-default-features".to_owned());
-        }
-        if self.features.all_features {
-            flags.push("--all-features".to_owned());
-        }
+        flags.extend(get_feature_flags(&self.features));
         if let Some(pkg) = self.package.as_ref() {
             flags.push("--package".to_owned());
             flags.push(pkg.to_owned());
@@ -77,31 +136,15 @@ impl Build {
         flags
     }
 
-    fn load_metadata(&self) -> anyhow::Result<Metadata> {
+    pub(crate) fn load_metadata(&self) -> anyhow::Result<Metadata> {
         let meta = MetadataCommand::new()
             .manifest_path("./Cargo.toml")
             .exec()?;
         Ok(meta)
     }
 
-    fn get_package(&self, meta: &Metadata) -> anyhow::Result<Package> {
-        if self.package.is_none() {
-            meta.root_package()
-                .cloned()
-                .ok_or_else(|| anyhow::anyhow!("No rust package found under current directory"))
-        } else {
-            let pkg = meta
-                .packages
-                .iter()
-                .find(|p| &p.name == self.package.as_ref().unwrap())
-                .cloned();
-            pkg.ok_or_else(|| {
-                anyhow::anyhow!(
-                    "No rust package found with name {}",
-                    self.package.as_ref().unwrap()
-                )
-            })
-        }
+    pub(crate) fn get_package(&self, meta: &Metadata) -> anyhow::Result<Package> {
+        find_package(meta, self.package.as_ref())
     }
 
     fn get_target(&self, package: &Package) -> anyhow::Result<Target> {
@@ -132,19 +175,26 @@ impl Build {
         Ok(target.name.replace('-', "_"))
     }
 
-    fn get_assets_dir(&self, meta: &Metadata) -> anyhow::Result<PathBuf> {
-        let project_dir = self
-            .get_package(meta)?
-            .manifest_path
-            .as_std_path()
-            .parent()
-            .unwrap()
-            .to_owned();
-        if self.example.is_some() {
-            // Use <project>/examples/assets/ as the assets dir
-            return Ok(project_dir.join("examples").join("assets"));
-        }
-        Ok(project_dir.join("assets"))
+    /// Resolves `[package.metadata.playdate.assets]` for this build
+    pub(crate) fn get_asset_rules(&self, package: &Package) -> anyhow::Result<AssetRules> {
+        let features = crate::assets::resolve_features(package, &self.features);
+        let profile = if self.release { "release" } else { "dev" };
+        AssetRules::load(package, self.example.as_deref(), &features, profile)
+    }
+
+    /// Converts the assets selected by [`Build::get_asset_rules`]
+    fn collect_assets(
+        &self,
+        meta: &Metadata,
+        package: &Package,
+    ) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        let files = self.get_asset_rules(package)?.list_files()?;
+        convert_assets(meta, package, files)
+    }
+
+    /// Folder of the asset manifests, shared by all profiles so `playdate_rs::include_assets!` finds them without `cargo playdate`
+    fn get_asset_manifest_dir(&self, meta: &Metadata) -> PathBuf {
+        meta.target_directory.as_std_path().join("playdate-assets")
     }
 
     fn get_target_dir(&self, meta: &Metadata) -> anyhow::Result<PathBuf> {
@@ -162,66 +212,33 @@ impl Build {
         }
         Ok(target_dir)
     }
+}
 
-    fn load_pdxinfo(&self, pkg: &Package, target_name: &str) -> anyhow::Result<String> {
-        if self.example.is_some() {
-            let pdxinfo = format!(
-                "name={}\nauthor=playdate-rs\ndescription=playdate-rs-example\nbundleID=me.wenyu.playdate.example.{}\n",
-                target_name, target_name
-            );
-            return Ok(pdxinfo);
-        }
-        // If there is a pdxinfo file under package root, just use it
-        let pdxinfo_path = pkg
-            .manifest_path
-            .as_std_path()
-            .parent()
-            .unwrap()
-            .join("pdxinfo");
-        if pdxinfo_path.is_file() || pdxinfo_path.is_symlink() {
-            info!("Using pdxinfo from {}", pdxinfo_path.to_string_lossy());
-            return Ok(std::fs::read_to_string(pdxinfo_path)?);
-        }
-        // Generate from pdxinfo template
-        let pdxinfo_meta = pkg
-            .metadata
-            .get("pdxinfo")
-            .map(|v| v.as_object().unwrap().clone())
-            .unwrap_or_default();
-        let get_meta = |key: &str, default: &str, warn: Option<&str>| -> String {
-            pdxinfo_meta
-                .get(key)
-                .map(|v| v.as_str().unwrap().to_owned())
-                .unwrap_or_else(|| {
-                    if let Some(warn) = warn {
-                        warn!("{}", warn);
-                    }
-                    default.to_owned()
-                })
-        };
-        let fmt_content_warning = |n: &str, s: &str| -> String {
-            let s = s.trim();
-            if !s.is_empty() {
-                format!("{}={}\n", n, s)
-            } else {
-                "".to_owned()
+/// Parts of the package changed since the last build, see [`Build::rebuild`]
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// The rust sources or the manifest changed
+    pub code: bool,
+    /// The pdxinfo file changed
+    pub pdxinfo: bool,
+    /// Changed, added or deleted files and folders of the assets folder
+    pub assets: Vec<PathBuf>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        !self.code && !self.pdxinfo && self.assets.is_empty()
+    }
+
+    /// Adds the changes of `other`, e.g. to retry a failed rebuild with the changes made since
+    pub fn merge(&mut self, other: Changes) {
+        self.code |= other.code;
+        self.pdxinfo |= other.pdxinfo;
+        for path in other.assets {
+            if !self.assets.contains(&path) {
+                self.assets.push(path);
             }
-        };
-        let mut env = minijinja::Environment::new();
-        env.add_template("pdxinfo", PDXINFO)?;
-        let template = env.get_template("pdxinfo").unwrap();
-        let default_bundle_id = format!("com.example.{}", pkg.name);
-        let s = template.render(minijinja::context! {
-            name => get_meta("name", target_name, None),
-            author => get_meta("author", &pkg.authors.join(", "), None),
-            description => get_meta("description", pkg.description.as_ref().unwrap_or(&"".to_owned()), None),
-            bundle_id => get_meta("bundle_id", &default_bundle_id, Some(&format!("Using default bundle id: {}", default_bundle_id))),
-            image_path => get_meta("image_path", "", None),
-            launch_sound_path => get_meta("launch_sound_path", "", None),
-            content_warning => fmt_content_warning("contentWarning", &get_meta("content_warning", "", None)),
-            content_warning2 => fmt_content_warning("contentWarning2", &get_meta("content_warning2", "", None)),
-        })?;
-        Ok(s)
+        }
     }
 }
 
@@ -288,46 +305,184 @@ impl Build {
         Ok(target_dir.join(format!("{}.elf", target_name)))
     }
 
+    /// Builds the rust project and returns the binary to put in the pdx
+    fn compile(
+        &self,
+        target_name: &str,
+        target_dir: &Path,
+        manifest_dir: &Path,
+    ) -> anyhow::Result<PathBuf> {
+        Command::new("cargo")
+            .arg("+nightly")
+            .arg("build")
+            .args(self.get_cargo_flags())
+            .env("PLAYDATE_ASSETS_DIR", manifest_dir)
+            .envs(&if self.device {
+                let mut map = HashMap::new();
+                map.insert("RUSTFLAGS", ["-Crelocation-model=pic"].join(" "));
+                map
+            } else {
+                Default::default()
+            })
+            .check(true)?;
+        if self.device {
+            // Link the staticlib using arm-none-eabi-gcc
+            let staticlib = target_dir.join(format!("lib{}.a", target_name));
+            return self.link_arm_binary(target_name, target_dir, &staticlib);
+        }
+        Ok(target_dir.join(format!("lib{}.{}", target_name, DYLIB_EXT)))
+    }
+
+    fn get_pdx_src(&self, target_name: &str, target_dir: &Path) -> PathBuf {
+        target_dir.join(format!("{}.source", target_name))
+    }
+
+    fn get_pdex_name(&self) -> &'static str {
+        if self.device {
+            "pdex.elf"
+        } else {
+            PDEX_SO
+        }
+    }
+
+    /// Copies the binary and writes the pdxinfo to the pdx folder, after the assets it refers to are synced (`assets_changed` if the sync changed them). Returns `true` if one of them changed.
     fn copy_build_output(
         &self,
         target_name: &str,
         target_dir: &Path,
         binary: &Path,
         package: &Package,
-    ) -> anyhow::Result<PathBuf> {
-        // Create pdx folder
-        let pdx_src = target_dir.join(format!("{}.source", target_name));
-        Command::new("rm").arg("-rf").arg(&pdx_src).check(false)?;
-        Command::new("mkdir").arg("-p").arg(&pdx_src).check(false)?;
-        // Copy output files
-        let pdex_so = if self.device { "pdex.elf" } else { PDEX_SO };
-        Command::new("cp")
-            .arg(binary)
-            .arg(pdx_src.join(pdex_so))
-            .check(false)?;
-        let pdxinfo = self.load_pdxinfo(package, target_name)?;
-        std::fs::write(pdx_src.join("pdxinfo"), pdxinfo)?;
-        Ok(pdx_src)
+        assets_changed: bool,
+    ) -> anyhow::Result<bool> {
+        let pdx_src = self.get_pdx_src(target_name, target_dir);
+        std::fs::create_dir_all(&pdx_src)?;
+        let binary_changed = sync::sync_file(binary, &pdx_src.join(self.get_pdex_name()))?;
+        // A build that changes nothing keeps its build number, so the pdx stays up to date
+        let new_build = self.release && (binary_changed || assets_changed);
+        let pdxinfo = pdxinfo::load(
+            package,
+            target_name,
+            self.example.is_some(),
+            new_build,
+            &pdx_src,
+        )?;
+        let pdxinfo_changed = sync::write_if_changed(&pdx_src.join("pdxinfo"), pdxinfo)?;
+        Ok(binary_changed || pdxinfo_changed)
+    }
+
+    /// Returns the glob patterns and the level of `[package.metadata.playdate.compress]`
+    fn load_compress_config(
+        &self,
+        package: &Package,
+    ) -> anyhow::Result<Option<(Vec<glob::Pattern>, u8)>> {
+        let Some(compress_meta) = package
+            .metadata
+            .get("playdate")
+            .and_then(|p| p.get("compress"))
+        else {
+            return Ok(None);
+        };
+        let patterns = compress_meta
+            .get("assets")
+            .and_then(|v| v.as_array())
+            .map(|v| v.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|v| {
+                let pattern = v.as_str().ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "`package.metadata.playdate.compress.assets` must be a list of strings"
+                    )
+                })?;
+                Ok(glob::Pattern::new(pattern)?)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let level = compress_meta
+            .get("level")
+            .map(|v| {
+                v.as_u64().filter(|l| *l <= 10).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "`package.metadata.playdate.compress.level` must be between 0 and 10"
+                    )
+                })
+            })
+            .transpose()?
+            .unwrap_or(6) as u8;
+        Ok(Some((patterns, level)))
     }
 
-    fn copy_assets(&self, meta: &Metadata, pdx_src: &PathBuf) -> anyhow::Result<()> {
-        let assets_dir = self.get_assets_dir(meta)?;
-        if assets_dir.exists() && assets_dir.is_dir() {
-            for entry in std::fs::read_dir(&assets_dir)? {
-                let entry = entry?;
-                let path: PathBuf = entry.path();
-                if path.is_dir() {
-                    Command::new("cp")
-                        .arg("-r")
-                        .arg(&path)
-                        .arg(pdx_src)
-                        .check(false)?;
-                } else {
-                    Command::new("cp").arg(&path).arg(pdx_src).check(false)?;
+    /// Syncs the converted assets (see [`Build::collect_assets`]) into the pdx folder. The assets matching `[package.metadata.playdate.compress]` are compressed so they can be read with `playdate_rs::fs::compress::read`
+    fn sync_assets(
+        &self,
+        files: Vec<(String, PathBuf)>,
+        package: &Package,
+        target_name: &str,
+        target_dir: &Path,
+    ) -> anyhow::Result<sync::SyncReport> {
+        let pdx_src = self.get_pdx_src(target_name, target_dir);
+        let compress = self.load_compress_config(package)?;
+        let report = sync::sync_files(
+            files,
+            &pdx_src,
+            &target_dir.join(format!("{}.assets", target_name)),
+            &["pdxinfo", self.get_pdex_name()],
+            |relative| match &compress {
+                Some((patterns, level)) if patterns.iter().any(|p| p.matches(relative)) => {
+                    sync::Transfer::Compress(*level)
                 }
-            }
+                _ => sync::Transfer::Copy,
+            },
+        )?;
+        report.log();
+        Ok(report)
+    }
+
+    /// Rebuilds the parts of the pdx affected by `changes`, after a full build with [`Runnable::run`]. Returns `None` if the pdx is unchanged, in which case `pdc` is not invoked. If the rebuild fails, `changes` must be passed again to the next rebuild.
+    pub fn rebuild(&self, changes: &Changes) -> anyhow::Result<Option<BuildInfo>> {
+        let meta = self.load_metadata()?;
+        let package = self.get_package(&meta)?;
+        let target = self.get_target(&package)?;
+        let target_name = self.get_target_name(&target)?;
+        let target_dir = self.get_target_dir(&meta)?;
+        let pdx_src = self.get_pdx_src(&target_name, &target_dir);
+        let manifest_dir = self.get_asset_manifest_dir(&meta);
+        let mut binary = pdx_src.join(self.get_pdex_name());
+        let mut updated = false;
+        let mut compile = changes.code;
+        let mut files = None;
+        if !changes.assets.is_empty() {
+            let converted = self.collect_assets(&meta, &package)?;
+            // Added, renamed or deleted assets change the manifest included by the code
+            compile |= write_asset_manifest(&manifest_dir, &target_name, &converted)?;
+            files = Some(converted);
+        }
+        if compile {
+            info!("Building {}", target.name);
+            binary = self.compile(&target_name, &target_dir, &manifest_dir)?;
         }
-        Ok(())
+        let mut assets_removed = false;
+        let assets_changed = files.is_some();
+        if let Some(files) = files {
+            let report = self.sync_assets(files, &package, &target_name, &target_dir)?;
+            assets_removed = !report.removed.is_empty();
+            updated |= !report.is_empty();
+        }
+        // The pdxinfo is validated against the assets it refers to
+        if compile || changes.pdxinfo || assets_changed {
+            updated |=
+                self.copy_build_output(&target_name, &target_dir, &binary, &package, updated)?;
+        }
+        // A failed `pdc` removes the pdx
+        let pdx = target_dir.join(format!("{}.pdx", target_name));
+        if !updated && pdx.exists() {
+            return Ok(None);
+        }
+        let pdx = self.invoke_pdc(&target_name, &target_dir, &pdx_src, assets_removed)?;
+        Ok(Some(BuildInfo {
+            name: target_name,
+            binary,
+            pdx,
+        }))
     }
 
     fn invoke_pdc(
@@ -335,15 +490,25 @@ impl Build {
         target_name: &str,
         target_dir: &Path,
         pdx_src: &Path,
+        clean: bool,
     ) -> anyhow::Result<PathBuf> {
         let pdx_out = target_dir.join(format!("{}.pdx", target_name));
+        // pdc does not remove the compiled files of deleted assets
+        if clean && pdx_out.exists() {
+            std::fs::remove_dir_all(&pdx_out)?;
+        }
         let playdate_sdk_path = crate::util::get_playdate_sdk_path()?;
         let pdx_bin = playdate_sdk_path.join("bin").join("pdc");
-        Command::new(pdx_bin)
+        let result = Command::new(pdx_bin)
             .arg("--strip")
             .arg(pdx_src)
             .arg(&pdx_out)
-            .check(true)?;
+            .check(true);
+        // A partial pdx must not be mistaken for an up to date one by the next build
+        if result.is_err() && pdx_out.exists() {
+            std::fs::remove_dir_all(&pdx_out)?;
+        }
+        result?;
         Ok(pdx_out)
     }
 }
@@ -358,31 +523,35 @@ impl Runnable<BuildInfo> for Build {
         // Find target name and target output dir
         let target_name = self.get_target_name(&target)?;
         let target_dir = self.get_target_dir(&meta)?;
-        let mut binary = target_dir.join(format!("lib{}.{}", target_name, DYLIB_EXT));
+        // Convert the assets and generate their manifest, which the code may include
+        let files = self.collect_assets(&meta, &package)?;
+        let manifest_dir = self.get_asset_manifest_dir(&meta);
+        write_asset_manifest(&manifest_dir, &target_name, &files)?;
         // Build rust project
-        Command::new("cargo")
-            .arg("+nightly")
-            .arg("build")
-            .args(self.get_cargo_flags())
-            .envs(&if self.device {
-                let mut map = HashMap::new();
-                map.insert("RUSTFLAGS", ["-Crelocation-model=pic"].join(" "));
-                map
-            } else {
-                Default::default()
-            })
-            .check(true)?;
-        if self.device {
-            // Link the staticlib using arm-none-eabi-gcc
-            let staticlib = target_dir.join(format!("lib{}.a", target_name));
-            binary = self.link_arm_binary(&target_name, &target_dir, &staticlib)?;
-        }
-        // Create pdx folder and copy output files
-        let pdx_src = self.copy_build_output(&target_name, &target_dir, &binary, &package)?;
+        let binary = self.compile(&target_name, &target_dir, &manifest_dir)?;
         // Copy assets
-        self.copy_assets(&meta, &pdx_src)?;
-        // call pdc
-        let pdx = self.invoke_pdc(&target_name, &target_dir, &pdx_src)?;
+        let report = self.sync_assets(files, &package, &target_name, &target_dir)?;
+        // Copy output files, and write the pdxinfo once the assets it refers to are in place
+        let output_changed = self.copy_build_output(
+            &target_name,
+            &target_dir,
+            &binary,
+            &package,
+            !report.is_empty(),
+        )?;
+        // call pdc, unless the pdx is up to date
+        let pdx_src = self.get_pdx_src(&target_name, &target_dir);
+        let pdx = target_dir.join(format!("{}.pdx", target_name));
+        if output_changed || !report.is_empty() || !pdx.exists() {
+            self.invoke_pdc(
+                &target_name,
+                &target_dir,
+                &pdx_src,
+                !report.removed.is_empty(),
+            )?;
+        } else {
+            info!("{} is up to date", pdx.to_string_lossy());
+        }
 
         Ok(BuildInfo {
             name: target_name,
@@ -0,0 +1,239 @@
+//! Build-time rasterization of TrueType/OpenType fonts to Playdate `.fnt` fonts, configured by `[[package.metadata.playdate.fonts]]` rules.
+
+use std::{
+    fs,
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::pipeline::{self, Cache, Outputs, Rules};
+
+/// Bump when the output of the pipeline changes, to invalidate the cache
+const VERSION: u32 = 2;
+
+/// Number of glyph cells per row of the glyph table
+const TABLE_COLUMNS: usize = 16;
+
+/// Named character sets
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Charset {
+    /// U+0020 to U+007E
+    Ascii,
+    /// The Latin-1 supplement, U+00A0 to U+00FF
+    Latin1,
+    /// U+0100 to U+017F
+    LatinExtendedA,
+    /// Japanese punctuation, hiragana and katakana, U+3000 to U+30FF
+    Kana,
+}
+
+impl Charset {
+    fn range(&self) -> RangeInclusive<char> {
+        match self {
+            Charset::Ascii => ' '..='~',
+            Charset::Latin1 => '\u{a0}'..='\u{ff}',
+            Charset::LatinExtendedA => '\u{100}'..='\u{17f}',
+            Charset::Kana => '\u{3000}'..='\u{30ff}',
+        }
+    }
+}
+
+fn default_charset() -> Vec<Charset> {
+    vec![Charset::Ascii]
+}
+
+fn default_threshold() -> u8 {
+    128
+}
+
+fn default_kerning() -> bool {
+    true
+}
+
+/// Rasterization settings of a font rule
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FontSettings {
+    /// Size of the em square, in pixels
+    pub size: f32,
+    /// Coverage (0-255) at or above which a pixel of a glyph is drawn
+    #[serde(default = "default_threshold")]
+    pub threshold: u8,
+    #[serde(default = "default_charset")]
+    pub charset: Vec<Charset>,
+    /// Characters added to the charset
+    #[serde(default)]
+    pub chars: String,
+    /// Pixels added between characters
+    #[serde(default)]
+    pub tracking: i32,
+    /// Write the kerning pairs of the font for the characters of the charset
+    #[serde(default = "default_kerning")]
+    pub kerning: bool,
+}
+
+impl Hash for FontSettings {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.size.to_bits().hash(state);
+        self.threshold.hash(state);
+        self.charset.hash(state);
+        self.chars.hash(state);
+        self.tracking.hash(state);
+        self.kerning.hash(state);
+    }
+}
+
+/// Generates `.fnt` fonts from the TrueType/OpenType fonts of the asset folders, caching the results by content
+pub struct FontPipeline {
+    rules: Rules<FontSettings>,
+    cache: Cache,
+}
+
+impl FontPipeline {
+    /// Loads the `[[package.metadata.playdate.fonts]]` rules from the package metadata. Returns `None` if there are no rules.
+    pub fn load(metadata: &serde_json::Value, cache_dir: &Path) -> anyhow::Result<Option<Self>> {
+        Ok(Rules::load(metadata, "fonts")?.map(|rules| Self {
+            rules,
+            cache: Cache::new(cache_dir, VERSION),
+        }))
+    }
+
+    /// Replaces the fonts matching a rule in `files` (paths inside the pdx and source paths) by a `.fnt` file and its glyph table
+    pub fn process(&self, files: Vec<(String, PathBuf)>) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        let mut outputs = Outputs::default();
+        for (relative, source) in files {
+            let Some(settings) = self.rules.find(&relative) else {
+                outputs.insert(relative, source);
+                continue;
+            };
+            let entry = self
+                .cache
+                .get_or_convert(&source, settings, |source, dir| {
+                    generate(source, settings, dir)
+                })
+                .map_err(|e| anyhow::anyhow!("Failed to convert {}: {}", relative, e))?;
+            outputs.insert_entry(&relative, &entry)?;
+        }
+        Ok(outputs.into_vec())
+    }
+}
+
+/// A rasterized glyph, placed in its cell
+struct Glyph {
+    ch: char,
+    advance: i32,
+    /// Position of the bitmap in the cell
+    x: i32,
+    y: i32,
+    width: usize,
+    height: usize,
+    coverage: Vec<u8>,
+}
+
+/// Writes the `.fnt` file and the `-table-<width>-<height>.png` glyph table of `source` into `dir`
+fn generate(source: &Path, settings: &FontSettings, dir: &Path) -> anyhow::Result<()> {
+    if settings.size <= 0.0 {
+        anyhow::bail!("`size` must be positive");
+    }
+    let font = fontdue::Font::from_bytes(fs::read(source)?, fontdue::FontSettings::default())
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let size = settings.size;
+    let mut chars = settings
+        .charset
+        .iter()
+        .flat_map(|c| c.range())
+        .chain(settings.chars.chars())
+        .filter(|c| !c.is_control())
+        .collect::<Vec<_>>();
+    chars.sort();
+    chars.dedup();
+    let (chars, missing): (Vec<_>, Vec<_>) = chars.into_iter().partition(|c| font.has_glyph(*c));
+    if !missing.is_empty() {
+        debug!("{} characters are not in the font", missing.len());
+    }
+    if chars.is_empty() {
+        anyhow::bail!("The font has none of the characters of the charset");
+    }
+    let line = font
+        .horizontal_line_metrics(size)
+        .ok_or_else(|| anyhow::anyhow!("The font has no horizontal metrics"))?;
+    let baseline = line.ascent.ceil() as i32;
+    let cell_height = (baseline + (-line.descent).ceil() as i32).max(1) as usize;
+    let mut glyphs = chars
+        .iter()
+        .map(|ch| {
+            let (metrics, coverage) = font.rasterize(*ch, size);
+            Glyph {
+                ch: *ch,
+                advance: metrics.advance_width.round() as i32,
+                x: metrics.xmin,
+                y: baseline - (metrics.ymin + metrics.height as i32),
+                width: metrics.width,
+                height: metrics.height,
+                coverage,
+            }
+        })
+        .collect::<Vec<_>>();
+    // A cell is drawn at the pen position, so the glyphs extending left of it, e.g. `j` or italics, are all moved right by the largest overhang. This keeps their spacing, and shifts the text by that many pixels
+    let overhang = glyphs.iter().map(|g| -g.x).max().unwrap_or(0).max(0);
+    for glyph in &mut glyphs {
+        glyph.x += overhang;
+    }
+    let cell_width = glyphs
+        .iter()
+        .map(|g| g.advance.max(g.x + g.width as i32))
+        .max()
+        .unwrap_or(1)
+        .max(1) as usize;
+    // Glyph table, black glyphs on a transparent background
+    let columns = TABLE_COLUMNS.min(glyphs.len());
+    let rows = glyphs.len().div_ceil(columns);
+    let (width, height) = (columns * cell_width, rows * cell_height);
+    let mut opaque = vec![false; width * height];
+    for (i, glyph) in glyphs.iter().enumerate() {
+        let (cell_x, cell_y) = ((i % columns) * cell_width, (i / columns) * cell_height);
+        for gy in 0..glyph.height {
+            for gx in 0..glyph.width {
+                let (x, y) = (glyph.x + gx as i32, glyph.y + gy as i32);
+                if x < 0 || y < 0 || x as usize >= cell_width || y as usize >= cell_height {
+                    continue;
+                }
+                if glyph.coverage[gy * glyph.width + gx] >= settings.threshold {
+                    opaque[(cell_y + y as usize) * width + cell_x + x as usize] = true;
+                }
+            }
+        }
+    }
+    let png = crate::images::encode_1bit(width, height, &vec![false; width * height], &opaque)?;
+    fs::write(
+        pipeline::output_path(dir, &format!("-table-{}-{}.png", cell_width, cell_height)),
+        png,
+    )?;
+    // The glyphs are listed in the order of the cells of the table
+    let mut fnt = format!("tracking={}\n", settings.tracking);
+    for glyph in &glyphs {
+        let name = match glyph.ch {
+            ' ' => "space".to_owned(),
+            c => c.to_string(),
+        };
+        fnt.push_str(&format!("{}\t{}\n", name, glyph.advance));
+    }
+    if settings.kerning {
+        for left in &chars {
+            for right in &chars {
+                let kern = font
+                    .horizontal_kern(*left, *right, size)
+                    .map_or(0, |k| k.round() as i32);
+                if kern != 0 {
+                    fnt.push_str(&format!("{}{}\t{}\n", left, right, kern));
+                }
+            }
+        }
+    }
+    fs::write(pipeline::output_path(dir, ".fnt"), fnt)?;
+    Ok(())
+}
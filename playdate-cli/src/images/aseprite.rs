@@ -0,0 +1,298 @@
+//! A reader for the frames of `.aseprite`/`.ase` files. See <https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md>.
+//!
+//! The visible layers of each frame are flattened with the normal blend mode. Tilemap layers are skipped.
+
+use std::{fs, path::Path};
+
+use super::Image;
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_PALETTE: u16 = 0x2019;
+
+/// Little-endian reader over a byte slice
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| anyhow::anyhow!("Unexpected end of file"))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> anyhow::Result<i16> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn skip_string(&mut self) -> anyhow::Result<()> {
+        let len = self.u16()? as usize;
+        self.bytes(len)?;
+        Ok(())
+    }
+}
+
+struct Layer {
+    visible: bool,
+    opacity: u8,
+    is_tilemap: bool,
+}
+
+#[derive(Clone)]
+struct Cel {
+    layer: usize,
+    x: i32,
+    y: i32,
+    opacity: u8,
+    z_index: i32,
+    image: Image,
+}
+
+/// Decodes the pixels of a cel to RGBA
+fn decode_pixels(
+    data: &[u8],
+    depth: u16,
+    palette: &[[u8; 4]],
+    transparent: u8,
+) -> anyhow::Result<Vec<[u8; 4]>> {
+    Ok(match depth {
+        32 => data.as_chunks::<4>().0.to_vec(),
+        16 => data
+            .as_chunks::<2>()
+            .0
+            .iter()
+            .map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        8 => data
+            .iter()
+            .map(|i| match palette.get(*i as usize) {
+                _ if *i == transparent => [0; 4],
+                Some(color) => *color,
+                None => [0; 4],
+            })
+            .collect(),
+        _ => anyhow::bail!("Unsupported color depth: {}", depth),
+    })
+}
+
+/// Draws `src` over `dst` at `(x, y)` with the normal blend mode
+fn blend(dst: &mut Image, src: &Image, x: i32, y: i32, opacity: u8) {
+    for sy in 0..src.height {
+        for sx in 0..src.width {
+            let (dx, dy) = (x + sx as i32, y + sy as i32);
+            if dx < 0 || dy < 0 || dx as usize >= dst.width || dy as usize >= dst.height {
+                continue;
+            }
+            let s = src.pixels[sy * src.width + sx];
+            let d = &mut dst.pixels[dy as usize * dst.width + dx as usize];
+            let sa = s[3] as u32 * opacity as u32 / 255;
+            if sa == 0 {
+                continue;
+            }
+            let da = d[3] as u32 * (255 - sa) / 255;
+            let a = sa + da;
+            for c in 0..3 {
+                d[c] = ((s[c] as u32 * sa + d[c] as u32 * da) / a) as u8;
+            }
+            d[3] = a as u8;
+        }
+    }
+}
+
+/// Decodes every frame of an Aseprite file, flattening the visible layers
+pub fn decode_aseprite(path: &Path) -> anyhow::Result<Vec<Image>> {
+    let data = fs::read(path)?;
+    let mut r = Reader::new(&data);
+    r.u32()?;
+    if r.u16()? != HEADER_MAGIC {
+        anyhow::bail!("Not an Aseprite file");
+    }
+    let frame_count = r.u16()? as usize;
+    let width = r.u16()? as usize;
+    let height = r.u16()? as usize;
+    let depth = r.u16()?;
+    let flags = r.u32()?;
+    r.bytes(10)?;
+    let transparent = r.u8()?;
+    r.pos = 128;
+    let layer_opacity_valid = flags & 1 != 0;
+    let mut layers: Vec<Layer> = vec![];
+    // Visibility of the groups containing the next layer, by child level
+    let mut group_visible: Vec<bool> = vec![];
+    let mut palette = vec![[0u8; 4]; 256];
+    let mut frames_cels: Vec<Vec<Cel>> = vec![];
+    for _ in 0..frame_count {
+        let frame_start = r.pos;
+        let frame_size = r.u32()? as usize;
+        if r.u16()? != FRAME_MAGIC {
+            anyhow::bail!("Corrupted frame header");
+        }
+        let old_chunks = r.u16()? as u32;
+        r.bytes(4)?;
+        let new_chunks = r.u32()?;
+        let chunks = if new_chunks == 0 {
+            old_chunks
+        } else {
+            new_chunks
+        };
+        let mut cels = vec![];
+        for _ in 0..chunks {
+            let chunk_start = r.pos;
+            let chunk_size = r.u32()? as usize;
+            let chunk_type = r.u16()?;
+            let mut c = Reader::new(r.bytes(chunk_size.saturating_sub(6))?);
+            match chunk_type {
+                CHUNK_LAYER => {
+                    let layer_flags = c.u16()?;
+                    let layer_type = c.u16()?;
+                    let level = c.u16()? as usize;
+                    c.bytes(6)?;
+                    let opacity = c.u8()?;
+                    group_visible.truncate(level);
+                    let visible = layer_flags & 1 != 0 && group_visible.iter().all(|v| *v);
+                    if layer_type == 1 {
+                        group_visible.push(visible);
+                    }
+                    layers.push(Layer {
+                        visible: visible && layer_type != 1,
+                        opacity: if layer_opacity_valid { opacity } else { 255 },
+                        is_tilemap: layer_type == 2,
+                    });
+                }
+                CHUNK_PALETTE => {
+                    c.u32()?;
+                    let first = c.u32()? as usize;
+                    let last = c.u32()? as usize;
+                    c.bytes(8)?;
+                    for i in first..=last {
+                        let entry_flags = c.u16()?;
+                        let color = [c.u8()?, c.u8()?, c.u8()?, c.u8()?];
+                        if entry_flags & 1 != 0 {
+                            c.skip_string()?;
+                        }
+                        if i < palette.len() {
+                            palette[i] = color;
+                        }
+                    }
+                }
+                // Written before the new palette chunk, which overrides it
+                CHUNK_OLD_PALETTE if frames_cels.is_empty() => {
+                    let mut index = 0;
+                    for _ in 0..c.u16()? {
+                        index += c.u8()? as usize;
+                        let count = match c.u8()? {
+                            0 => 256,
+                            n => n as usize,
+                        };
+                        for _ in 0..count {
+                            let color = [c.u8()?, c.u8()?, c.u8()?, 255];
+                            if index < palette.len() {
+                                palette[index] = color;
+                            }
+                            index += 1;
+                        }
+                    }
+                }
+                CHUNK_CEL => {
+                    let layer = c.u16()? as usize;
+                    let x = c.i16()? as i32;
+                    let y = c.i16()? as i32;
+                    let opacity = c.u8()?;
+                    let cel_type = c.u16()?;
+                    let z_index = c.i16()? as i32;
+                    c.bytes(5)?;
+                    let image = match cel_type {
+                        0 | 2 => {
+                            let w = c.u16()? as usize;
+                            let h = c.u16()? as usize;
+                            let rest = &c.data[c.pos..];
+                            let raw = if cel_type == 2 {
+                                miniz_oxide::inflate::decompress_to_vec_zlib(rest)
+                                    .map_err(|e| anyhow::anyhow!("Corrupted cel data: {:?}", e))?
+                            } else {
+                                rest.to_vec()
+                            };
+                            let pixels = decode_pixels(&raw, depth, &palette, transparent)?;
+                            if pixels.len() < w * h {
+                                anyhow::bail!("Corrupted cel data");
+                            }
+                            Image {
+                                width: w,
+                                height: h,
+                                pixels: pixels[..w * h].to_vec(),
+                            }
+                        }
+                        1 => {
+                            let linked = c.u16()? as usize;
+                            let source = frames_cels
+                                .get(linked)
+                                .and_then(|cels: &Vec<Cel>| cels.iter().find(|c| c.layer == layer))
+                                .ok_or_else(|| anyhow::anyhow!("Broken linked cel"))?;
+                            source.image.clone()
+                        }
+                        // Compressed tilemaps
+                        _ => continue,
+                    };
+                    cels.push(Cel {
+                        layer,
+                        x,
+                        y,
+                        opacity,
+                        z_index,
+                        image,
+                    });
+                }
+                _ => {}
+            }
+            r.pos = chunk_start + chunk_size;
+        }
+        frames_cels.push(cels);
+        r.pos = frame_start + frame_size;
+    }
+    let frames = frames_cels
+        .iter()
+        .map(|cels| {
+            let mut canvas = Image::new(width, height);
+            let mut cels = cels
+                .iter()
+                .filter(|c| {
+                    layers
+                        .get(c.layer)
+                        .is_some_and(|l| l.visible && !l.is_tilemap)
+                })
+                .collect::<Vec<_>>();
+            // Cels are drawn by layer, moved by their z-index
+            cels.sort_by_key(|c| (c.layer as i32 + c.z_index, c.z_index));
+            for cel in cels {
+                let opacity = cel.opacity as u32 * layers[cel.layer].opacity as u32 / 255;
+                blend(&mut canvas, &cel.image, cel.x, cel.y, opacity as u8);
+            }
+            canvas
+        })
+        .collect();
+    Ok(frames)
+}
@@ -0,0 +1,71 @@
+use std::{fs, io::Cursor, path::Path};
+
+use super::Image;
+
+/// Decodes a PNG file to RGBA
+pub fn decode_png(path: &Path) -> anyhow::Result<Image> {
+    let mut decoder = png::Decoder::new(Cursor::new(fs::read(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let data = &buf[..info.buffer_size()];
+    let pixels = match info.color_type {
+        png::ColorType::Grayscale => data.iter().map(|g| [*g, *g, *g, 255]).collect(),
+        png::ColorType::GrayscaleAlpha => data
+            .as_chunks::<2>()
+            .0
+            .iter()
+            .map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Rgb => data
+            .as_chunks::<3>()
+            .0
+            .iter()
+            .map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::Rgba => data.as_chunks::<4>().0.to_vec(),
+        // Palettes are expanded to RGB(A) by `Transformations::EXPAND`
+        png::ColorType::Indexed => unreachable!(),
+    };
+    Ok(Image {
+        width: info.width as usize,
+        height: info.height as usize,
+        pixels,
+    })
+}
+
+/// Decodes every frame of a GIF file to RGBA, applying the disposal method of each frame
+pub fn decode_gif(path: &Path) -> anyhow::Result<Vec<Image>> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(Cursor::new(fs::read(path)?))?;
+    let (width, height) = (decoder.width() as usize, decoder.height() as usize);
+    let mut canvas = Image::new(width, height);
+    let mut frames = vec![];
+    while let Some(frame) = decoder.read_next_frame()? {
+        let previous = canvas.clone();
+        let (left, top) = (frame.left as usize, frame.top as usize);
+        let frame_width = frame.width as usize;
+        for (i, p) in frame.buffer.as_chunks::<4>().0.iter().enumerate() {
+            let (x, y) = (left + i % frame_width, top + i / frame_width);
+            // Transparent pixels show the previous frame
+            if p[3] != 0 && x < width && y < height {
+                canvas.pixels[y * width + x] = *p;
+            }
+        }
+        frames.push(canvas.clone());
+        match frame.dispose {
+            gif::DisposalMethod::Background => {
+                for y in top..(top + frame.height as usize).min(height) {
+                    for x in left..(left + frame_width).min(width) {
+                        canvas.pixels[y * width + x] = [0; 4];
+                    }
+                }
+            }
+            gif::DisposalMethod::Previous => canvas = previous,
+            _ => {}
+        }
+    }
+    Ok(frames)
+}
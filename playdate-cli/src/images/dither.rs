@@ -0,0 +1,93 @@
+use serde::Deserialize;
+
+/// How gray levels are reduced to black and white
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Dither {
+    /// Pixels at or above the threshold are white, the others black
+    Threshold,
+    /// Error diffusion to 4 neighbours. Smooth gradients
+    #[default]
+    FloydSteinberg,
+    /// Error diffusion of 3/4 of the error to 6 neighbours. Higher contrast, the classic Mac look
+    Atkinson,
+    /// Ordered dithering with a 4x4 Bayer matrix. Stable between animation frames
+    Bayer4,
+    /// Ordered dithering with an 8x8 Bayer matrix
+    Bayer8,
+}
+
+/// Returns the index of the `n`x`n` Bayer matrix at `(x, y)`, between 0 and `n * n - 1`
+fn bayer(n: usize, x: usize, y: usize) -> usize {
+    if n == 1 {
+        return 0;
+    }
+    let half = n / 2;
+    let quadrant = match (x >= half, y >= half) {
+        (false, false) => 0,
+        (true, true) => 1,
+        (true, false) => 2,
+        (false, true) => 3,
+    };
+    4 * bayer(half, x % half, y % half) + quadrant
+}
+
+/// Converts `gray` (one value per pixel, row major) to black and white. Returns `true` for white pixels.
+pub fn dither(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    algorithm: Dither,
+    threshold: u8,
+) -> Vec<bool> {
+    let threshold = threshold as f32;
+    match algorithm {
+        Dither::Threshold => gray.iter().map(|g| *g as f32 >= threshold).collect(),
+        Dither::Bayer4 | Dither::Bayer8 => {
+            let n = if algorithm == Dither::Bayer4 { 4 } else { 8 };
+            gray.iter()
+                .enumerate()
+                .map(|(i, g)| {
+                    let level = (bayer(n, i % width, i / width) as f32 + 0.5) / (n * n) as f32;
+                    *g as f32 + (level - 0.5) * 255.0 >= threshold
+                })
+                .collect()
+        }
+        Dither::FloydSteinberg | Dither::Atkinson => {
+            // (dx, dy, weight) of the neighbours receiving the error
+            let (kernel, divisor): (&[(isize, usize, f32)], f32) = match algorithm {
+                Dither::FloydSteinberg => {
+                    (&[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)], 16.0)
+                }
+                _ => (
+                    &[
+                        (1, 0, 1.0),
+                        (2, 0, 1.0),
+                        (-1, 1, 1.0),
+                        (0, 1, 1.0),
+                        (1, 1, 1.0),
+                        (0, 2, 1.0),
+                    ],
+                    8.0,
+                ),
+            };
+            let mut values = gray.iter().map(|g| *g as f32).collect::<Vec<_>>();
+            let mut out = vec![false; gray.len()];
+            for y in 0..height {
+                for x in 0..width {
+                    let i = y * width + x;
+                    let white = values[i] >= threshold;
+                    out[i] = white;
+                    let error = values[i] - if white { 255.0 } else { 0.0 };
+                    for (dx, dy, weight) in kernel {
+                        let (nx, ny) = (x as isize + dx, y + dy);
+                        if nx >= 0 && (nx as usize) < width && ny < height {
+                            values[ny * width + nx as usize] += error * weight / divisor;
+                        }
+                    }
+                }
+            }
+            out
+        }
+    }
+}
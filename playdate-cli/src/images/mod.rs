@@ -0,0 +1,285 @@
+//! Build-time conversion of images to 1-bit PNGs for `pdc`, configured by `[[package.metadata.playdate.images]]` rules.
+
+mod aseprite;
+pub(crate) mod decode;
+mod dither;
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::pipeline::{self, Cache, Outputs, Rules};
+
+pub use dither::Dither;
+
+/// Bump when the output of the pipeline changes, to invalidate the cache
+const VERSION: u32 = 2;
+
+/// An RGBA image
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    /// Creates a transparent image
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0; 4]; width * height],
+        }
+    }
+
+    pub(crate) fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Image {
+        let mut out = Image::new(width, height);
+        for row in 0..height.min(self.height.saturating_sub(y)) {
+            for col in 0..width.min(self.width.saturating_sub(x)) {
+                out.pixels[row * width + col] = self.pixels[(y + row) * self.width + x + col];
+            }
+        }
+        out
+    }
+
+    pub(crate) fn paste(&mut self, src: &Image, x: usize, y: usize) {
+        for row in 0..src.height {
+            let start = (y + row) * self.width + x;
+            self.pixels[start..start + src.width]
+                .copy_from_slice(&src.pixels[row * src.width..(row + 1) * src.width]);
+        }
+    }
+}
+
+/// How animation frames and sprite-sheet cells are written for `BitmapTable::open`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum TableLayout {
+    /// A single `<name>-table-<width>-<height>.png` sheet
+    Grid,
+    /// One `<name>-table-<n>.png` per cell, counting from 1
+    Sequence,
+    /// A plain `<name>.png`. Only the first frame of an animation is kept
+    None,
+}
+
+/// Conversion settings of an image rule
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct ImageSettings {
+    #[serde(default)]
+    pub dither: Dither,
+    /// Gray level (0-255) at or above which a pixel is white
+    #[serde(default = "default_threshold")]
+    pub threshold: u8,
+    /// Write the alpha channel as the mask of the bitmap. If `false`, transparent pixels are drawn over white
+    #[serde(default = "default_mask")]
+    pub mask: bool,
+    /// Alpha (0-255) at or above which a pixel is opaque
+    #[serde(default = "default_threshold")]
+    pub alpha_threshold: u8,
+    /// Size of the cells of a sprite sheet, as `[width, height]`
+    pub cell: Option<[usize; 2]>,
+    /// Defaults to `grid` for animations and sprite sheets, and `none` for other images
+    pub table: Option<TableLayout>,
+}
+
+fn default_threshold() -> u8 {
+    128
+}
+
+fn default_mask() -> bool {
+    true
+}
+
+/// Converts the images of the asset folders to 1-bit, caching the results by content
+pub struct ImagePipeline {
+    rules: Rules<ImageSettings>,
+    cache: Cache,
+}
+
+impl ImagePipeline {
+    /// Loads the `[[package.metadata.playdate.images]]` rules from the package metadata. Returns `None` if there are no rules.
+    pub fn load(metadata: &serde_json::Value, cache_dir: &Path) -> anyhow::Result<Option<Self>> {
+        Ok(Rules::load(metadata, "images")?.map(|rules| Self {
+            rules,
+            cache: Cache::new(cache_dir, VERSION),
+        }))
+    }
+
+    /// Replaces the images matching a rule in `files` (paths inside the pdx and source paths) by their converted outputs
+    pub fn process(&self, files: Vec<(String, PathBuf)>) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        let mut outputs = Outputs::default();
+        for (relative, source) in files {
+            let Some(settings) = self.rules.find(&relative) else {
+                outputs.insert(relative, source);
+                continue;
+            };
+            let entry = self
+                .cache
+                .get_or_convert(&source, settings, |source, dir| {
+                    for (suffix, image) in layout(decode(source)?, settings)? {
+                        fs::write(
+                            pipeline::output_path(dir, &suffix),
+                            encode(&image, settings)?,
+                        )?;
+                    }
+                    Ok(())
+                })
+                .map_err(|e| anyhow::anyhow!("Failed to convert {}: {}", relative, e))?;
+            outputs.insert_entry(&relative, &entry)?;
+        }
+        Ok(outputs.into_vec())
+    }
+}
+
+/// Decodes the frames of an image from its extension
+fn decode(path: &Path) -> anyhow::Result<Vec<Image>> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let frames = match ext.as_str() {
+        "png" => vec![decode::decode_png(path)?],
+        "gif" => decode::decode_gif(path)?,
+        "aseprite" | "ase" => aseprite::decode_aseprite(path)?,
+        _ => anyhow::bail!("Unsupported image format, expected png, gif, aseprite or ase"),
+    };
+    if frames.is_empty() {
+        anyhow::bail!("The image has no frames");
+    }
+    Ok(frames)
+}
+
+/// Arranges the frames, or the cells of a sprite sheet, into the files expected by `BitmapTable::open`. Returns the suffix of each file and its image.
+fn layout(frames: Vec<Image>, settings: &ImageSettings) -> anyhow::Result<Vec<(String, Image)>> {
+    let is_table = frames.len() > 1 || settings.cell.is_some();
+    let table = settings.table.unwrap_or(if is_table {
+        TableLayout::Grid
+    } else {
+        TableLayout::None
+    });
+    let (width, height) = (frames[0].width, frames[0].height);
+    let cell = settings.cell.unwrap_or([width, height]);
+    if cell[0] == 0 || cell[1] == 0 {
+        anyhow::bail!("`cell` must not be empty");
+    }
+    match table {
+        TableLayout::None => Ok(vec![(
+            ".png".to_owned(),
+            frames.into_iter().next().unwrap(),
+        )]),
+        // A sheet is already a grid
+        TableLayout::Grid if frames.len() == 1 => {
+            if width % cell[0] != 0 || height % cell[1] != 0 {
+                warn!(
+                    "The image size {}x{} is not a multiple of the cell size {}x{}",
+                    width, height, cell[0], cell[1]
+                );
+            }
+            let suffix = format!("-table-{}-{}.png", cell[0], cell[1]);
+            Ok(vec![(suffix, frames.into_iter().next().unwrap())])
+        }
+        TableLayout::Grid => {
+            let columns = (frames.len() as f64).sqrt().ceil() as usize;
+            let rows = frames.len().div_ceil(columns);
+            let mut sheet = Image::new(columns * width, rows * height);
+            for (i, frame) in frames.iter().enumerate() {
+                sheet.paste(frame, (i % columns) * width, (i / columns) * height);
+            }
+            Ok(vec![(format!("-table-{}-{}.png", width, height), sheet)])
+        }
+        TableLayout::Sequence => {
+            let mut cells = vec![];
+            for frame in &frames {
+                for y in (0..frame.height).step_by(cell[1]) {
+                    for x in (0..frame.width).step_by(cell[0]) {
+                        cells.push(frame.crop(x, y, cell[0], cell[1]));
+                    }
+                }
+            }
+            Ok(cells
+                .into_iter()
+                .enumerate()
+                .map(|(i, image)| (format!("-table-{}.png", i + 1), image))
+                .collect())
+        }
+    }
+}
+
+/// Dithers `image` to black and white and encodes it as a PNG
+fn encode(image: &Image, settings: &ImageSettings) -> anyhow::Result<Vec<u8>> {
+    let opaque = image
+        .pixels
+        .iter()
+        .map(|p| !settings.mask || p[3] >= settings.alpha_threshold)
+        .collect::<Vec<_>>();
+    let gray = image
+        .pixels
+        .iter()
+        .map(|p| {
+            let luma = (p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000;
+            if settings.mask {
+                luma as u8
+            } else {
+                // Draw over white
+                ((luma * p[3] as u32 + 255 * (255 - p[3] as u32)) / 255) as u8
+            }
+        })
+        .collect::<Vec<_>>();
+    let white = dither::dither(
+        &gray,
+        image.width,
+        image.height,
+        settings.dither,
+        settings.threshold,
+    );
+    encode_1bit(image.width, image.height, &white, &opaque)
+}
+
+/// Encodes a black and white image as a PNG. `white` and `opaque` have one value per pixel, row major. Images with transparent pixels are written with an alpha channel, which `pdc` turns into a mask.
+pub(crate) fn encode_1bit(
+    width: usize,
+    height: usize,
+    white: &[bool],
+    opaque: &[bool],
+) -> anyhow::Result<Vec<u8>> {
+    let has_mask = opaque.iter().any(|o| !o);
+    let mut data = vec![];
+    {
+        let mut encoder = png::Encoder::new(&mut data, width as u32, height as u32);
+        if has_mask {
+            encoder.set_color(png::ColorType::GrayscaleAlpha);
+            encoder.set_depth(png::BitDepth::Eight);
+        } else {
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::One);
+        }
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        let pixels = if has_mask {
+            white
+                .iter()
+                .zip(opaque)
+                .flat_map(|(w, o)| [if *w { 255 } else { 0 }, if *o { 255 } else { 0 }])
+                .collect::<Vec<_>>()
+        } else {
+            // 8 pixels per byte, rows padded to a whole byte
+            white
+                .chunks(width)
+                .flat_map(|row| {
+                    row.chunks(8).map(|bits| {
+                        bits.iter()
+                            .enumerate()
+                            .fold(0u8, |b, (i, w)| b | ((*w as u8) << (7 - i)))
+                    })
+                })
+                .collect()
+        };
+        writer.write_image_data(&pixels)?;
+    }
+    Ok(data)
+}
@@ -1,4 +1,11 @@
 #[macro_use]
 extern crate log;
 
+pub mod atlas;
+pub mod audio;
+pub mod fonts;
+pub mod images;
+pub mod manifest;
+mod pipeline;
+pub mod sync;
 pub mod util;
@@ -4,10 +4,14 @@ extern crate log;
 use clap::Parser;
 
 use playdate_cli::util;
+mod assets;
 mod build;
 mod init;
 mod new;
+mod pdxinfo;
 mod run;
+mod test;
+mod watch;
 
 /// playdate rust development tools
 #[derive(Parser, Debug)]
@@ -17,6 +21,10 @@ enum Commands {
     Build(build::Build),
     #[clap(alias = "r")]
     Run(run::Run),
+    #[clap(alias = "t")]
+    Test(test::Test),
+    #[clap(alias = "w")]
+    Watch(watch::Watch),
     New(new::New),
     Init(init::Init),
 }
@@ -49,6 +57,12 @@ fn main() -> anyhow::Result<()> {
         Commands::Run(cmd) => {
             cmd.run()?;
         }
+        Commands::Test(cmd) => {
+            cmd.run()?;
+        }
+        Commands::Watch(cmd) => {
+            cmd.run()?;
+        }
         Commands::New(cmd) => {
             cmd.run()?;
         }
@@ -0,0 +1,342 @@
+//! Generation of the typed asset manifest included by `playdate_rs::include_assets!`: a module of constants per kind of asset, with a submodule per folder.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Files of the pdx folder that are not assets
+const RESERVED: &[&str] = &["pdxinfo"];
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv",
+    "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// Kinds of assets, and the type and module of their constants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AssetKind {
+    Image,
+    ImageTable,
+    Font,
+    Sound,
+    Atlas,
+    File,
+}
+
+impl AssetKind {
+    const ALL: [AssetKind; 6] = [
+        AssetKind::Image,
+        AssetKind::ImageTable,
+        AssetKind::Font,
+        AssetKind::Sound,
+        AssetKind::Atlas,
+        AssetKind::File,
+    ];
+
+    fn module(&self) -> &'static str {
+        match self {
+            AssetKind::Image => "images",
+            AssetKind::ImageTable => "image_tables",
+            AssetKind::Font => "fonts",
+            AssetKind::Sound => "sounds",
+            AssetKind::Atlas => "atlases",
+            AssetKind::File => "files",
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            AssetKind::Image => "ImageAsset",
+            AssetKind::ImageTable => "ImageTableAsset",
+            AssetKind::Font => "FontAsset",
+            AssetKind::Sound => "SoundAsset",
+            AssetKind::Atlas => "AtlasAsset",
+            AssetKind::File => "FileAsset",
+        }
+    }
+}
+
+/// An asset of the pdx, with the path its loader takes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Asset {
+    pub kind: AssetKind,
+    pub path: String,
+}
+
+/// Splits `name` into its stem and lowercase extension
+fn split_extension(name: &str) -> (&str, String) {
+    let name_start = name.rfind('/').map_or(0, |i| i + 1);
+    match name.rfind('.') {
+        Some(i) if i > name_start => (&name[..i], name[i + 1..].to_ascii_lowercase()),
+        _ => (name, String::new()),
+    }
+}
+
+/// Strips the `-table-<width>-<height>` or `-table-<count>` suffix of an image table
+fn strip_table_suffix(stem: &str) -> Option<&str> {
+    let (base, suffix) = stem.rsplit_once("-table-")?;
+    let numbers = suffix.split('-').collect::<Vec<_>>();
+    let valid = matches!(numbers.len(), 1 | 2)
+        && numbers
+            .iter()
+            .all(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    valid.then_some(base)
+}
+
+/// Returns `true` if the GIF file has more than one frame, in which case `pdc` compiles it to an image table
+fn is_animated_gif(source: &Path) -> anyhow::Result<bool> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(fs::File::open(source)?)?;
+    let mut frames = 0;
+    while decoder.next_frame_info()?.is_some() {
+        frames += 1;
+        if frames > 1 {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Strips the `-<n>` suffix of an atlas sheet
+fn strip_sheet_suffix(stem: &str) -> Option<&str> {
+    let (base, n) = stem.rsplit_once('-')?;
+    (!n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())).then_some(base)
+}
+
+/// Classifies the files to bundle (paths inside the pdx and source paths). Glyph tables of fonts and sheets of atlases are part of the font or the atlas, and the frames of a `-table-<n>` sequence are a single image table.
+pub fn classify(files: &[(String, PathBuf)]) -> anyhow::Result<Vec<Asset>> {
+    let stems = |extension: &str| {
+        files
+            .iter()
+            .filter_map(|(relative, _)| match split_extension(relative) {
+                (stem, ext) if ext == extension => Some(stem.to_owned()),
+                _ => None,
+            })
+            .collect::<HashSet<_>>()
+    };
+    let (fonts, atlases) = (stems("fnt"), stems("atlas"));
+    let mut assets = vec![];
+    let mut seen = HashSet::new();
+    for (relative, source) in files {
+        if RESERVED.contains(&relative.as_str()) {
+            continue;
+        }
+        let (stem, ext) = split_extension(relative);
+        let (kind, path) = match ext.as_str() {
+            "png" if strip_sheet_suffix(stem).is_some_and(|base| atlases.contains(base)) => {
+                continue
+            }
+            "png" => match strip_table_suffix(stem) {
+                Some(base) if fonts.contains(base) => continue,
+                Some(base) => (AssetKind::ImageTable, base),
+                None => (AssetKind::Image, stem),
+            },
+            "gif" => {
+                let animated = is_animated_gif(source)
+                    .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", relative, e))?;
+                if animated {
+                    (AssetKind::ImageTable, stem)
+                } else {
+                    (AssetKind::Image, stem)
+                }
+            }
+            "fnt" => (AssetKind::Font, stem),
+            "wav" | "aif" | "aiff" | "mp3" => (AssetKind::Sound, stem),
+            "atlas" => (AssetKind::Atlas, stem),
+            _ => (AssetKind::File, relative.as_str()),
+        };
+        let asset = Asset {
+            kind,
+            path: path.to_owned(),
+        };
+        if seen.insert(asset.clone()) {
+            assets.push(asset);
+        }
+    }
+    Ok(assets)
+}
+
+/// Turns a file or folder name into an identifier: characters other than ASCII letters and digits become `_`, and an identifier starting with a digit is prefixed with `_`
+fn identifier(name: &str, uppercase: bool) -> String {
+    let mut ident = name
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() && uppercase => c.to_ascii_uppercase(),
+            c if c.is_ascii_alphanumeric() => c.to_ascii_lowercase(),
+            _ => '_',
+        })
+        .collect::<String>();
+    if ident.is_empty() || ident == "_" || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+/// A module of the manifest: constants and submodules by identifier, with the path they were named after
+#[derive(Default)]
+struct Module {
+    constants: BTreeMap<String, (String, AssetKind)>,
+    modules: BTreeMap<String, (String, Module)>,
+}
+
+impl Module {
+    fn insert(&mut self, folders: &[&str], name: &str, asset: &Asset) -> anyhow::Result<()> {
+        let conflict = |ident: &str, a: &str, b: &str| {
+            anyhow::anyhow!(
+                "{} and {} are both named `{}` in the asset manifest, rename one of them",
+                a,
+                b,
+                ident
+            )
+        };
+        match folders.split_first() {
+            Some((folder, rest)) => {
+                let ident = identifier(folder, false);
+                let (path, module) = self
+                    .modules
+                    .entry(ident.clone())
+                    .or_insert_with(|| (folder.to_string(), Module::default()));
+                if path != folder {
+                    return Err(conflict(&ident, path, folder));
+                }
+                module.insert(rest, name, asset)
+            }
+            None => {
+                let ident = identifier(name, true);
+                if let Some((path, _)) = self.constants.get(&ident) {
+                    return Err(conflict(&ident, path, &asset.path));
+                }
+                self.constants
+                    .insert(ident, (asset.path.clone(), asset.kind));
+                Ok(())
+            }
+        }
+    }
+
+    fn write(&self, out: &mut String, depth: usize) {
+        let indent = "    ".repeat(depth);
+        for (ident, (path, kind)) in &self.constants {
+            let type_name = kind.type_name();
+            writeln!(
+                out,
+                "{indent}pub const {ident}: ::playdate_rs::assets::{type_name} = ::playdate_rs::assets::{type_name}::new({path:?});",
+            )
+            .unwrap();
+        }
+        for (ident, (_, module)) in &self.modules {
+            writeln!(out, "{indent}pub mod {ident} {{").unwrap();
+            module.write(out, depth + 1);
+            writeln!(out, "{indent}}}").unwrap();
+        }
+    }
+}
+
+/// Generates the manifest of the files to bundle (paths inside the pdx and source paths)
+pub fn generate(files: &[(String, PathBuf)]) -> anyhow::Result<String> {
+    let assets = classify(files)?;
+    let mut out = String::from("// Generated by `cargo playdate build` from the asset folders\n");
+    for kind in AssetKind::ALL {
+        let mut module = Module::default();
+        for asset in assets.iter().filter(|a| a.kind == kind) {
+            // The extension of other files is kept in the name of their constant, e.g. `LEVEL_JSON`
+            let segments = asset.path.split('/').collect::<Vec<_>>();
+            let (name, folders) = segments.split_last().unwrap();
+            module.insert(folders, name, asset)?;
+        }
+        writeln!(out, "pub mod {} {{", kind.module()).unwrap();
+        module.write(&mut out, 1);
+        writeln!(out, "}}").unwrap();
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(paths: &[&str]) -> Vec<(String, PathBuf)> {
+        paths
+            .iter()
+            .map(|p| (p.to_string(), PathBuf::from(p)))
+            .collect()
+    }
+
+    #[test]
+    fn classify_sequence_table_once() {
+        let assets = classify(&files(&[
+            "hero-table-1.png",
+            "hero-table-2.png",
+            "hero-table-3.png",
+        ]))
+        .unwrap();
+        assert_eq!(
+            assets,
+            vec![Asset {
+                kind: AssetKind::ImageTable,
+                path: "hero".to_owned(),
+            }]
+        );
+        let manifest = generate(&files(&["hero-table-1.png", "hero-table-2.png"])).unwrap();
+        assert!(manifest.contains("pub const HERO: ::playdate_rs::assets::ImageTableAsset"));
+    }
+
+    #[test]
+    fn classify_kinds() {
+        let assets = classify(&files(&[
+            "pdxinfo",
+            "fonts/big.fnt",
+            "fonts/big-table-8-16.png",
+            "ui.atlas",
+            "ui-0.png",
+            "ui-1.png",
+            "walk-table-32-32.png",
+            "music/theme.mp3",
+            "levels/1.json",
+            "rust.png",
+        ]))
+        .unwrap();
+        let asset = |kind, path: &str| Asset {
+            kind,
+            path: path.to_owned(),
+        };
+        assert_eq!(
+            assets,
+            vec![
+                asset(AssetKind::Font, "fonts/big"),
+                asset(AssetKind::Atlas, "ui"),
+                asset(AssetKind::ImageTable, "walk"),
+                asset(AssetKind::Sound, "music/theme"),
+                asset(AssetKind::File, "levels/1.json"),
+                asset(AssetKind::Image, "rust"),
+            ]
+        );
+    }
+
+    #[test]
+    fn generate_identifiers() {
+        let manifest = generate(&files(&[
+            "ui/Title Screen.png",
+            "levels/1.json",
+            "type.png",
+        ]))
+        .unwrap();
+        let lines = manifest.lines().map(str::trim).collect::<Vec<_>>();
+        assert!(lines.contains(&"pub mod ui {"));
+        assert!(lines.contains(&"pub const TITLE_SCREEN: ::playdate_rs::assets::ImageAsset = ::playdate_rs::assets::ImageAsset::new(\"ui/Title Screen\");"));
+        assert!(lines.contains(&"pub mod levels {"));
+        assert!(lines.contains(&"pub const _1_JSON: ::playdate_rs::assets::FileAsset = ::playdate_rs::assets::FileAsset::new(\"levels/1.json\");"));
+        assert!(lines.contains(&"pub const TYPE: ::playdate_rs::assets::ImageAsset = ::playdate_rs::assets::ImageAsset::new(\"type\");"));
+        let conflict = generate(&files(&["a-b.png", "a_b.png"])).unwrap_err();
+        assert!(conflict.to_string().contains("are both named `A_B`"));
+    }
+}
@@ -0,0 +1,393 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use cargo_metadata::Package;
+use serde::Deserialize;
+
+static PDXINFO: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/templates/pdxinfo.template"
+));
+
+/// File storing the last build number of `build_number = "auto"`, next to `Cargo.toml`
+const BUILD_NUMBER_FILE: &str = ".playdate-build-number";
+
+/// Documented pdxinfo keys, with the longest value accepted for each, in characters
+const KEYS: &[(&str, usize)] = &[
+    ("name", 64),
+    ("author", 64),
+    ("description", 512),
+    ("bundleID", 128),
+    ("version", 32),
+    ("buildNumber", 10),
+    ("imagePath", 256),
+    ("launchSoundPath", 256),
+    ("contentWarning", 256),
+    ("contentWarning2", 256),
+];
+
+/// Where the build number comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BuildNumberSource {
+    /// Incremented by every `--release` build that changes the binary or the assets, and stored in [`BUILD_NUMBER_FILE`]
+    Auto,
+    /// The number of commits of the git repository
+    Git,
+}
+
+/// `build_number`, as a number or a [`BuildNumberSource`]
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(try_from = "serde_json::Value")]
+enum BuildNumberConfig {
+    Fixed(u32),
+    Source(BuildNumberSource),
+}
+
+impl TryFrom<serde_json::Value> for BuildNumberConfig {
+    type Error = String;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        match &value {
+            serde_json::Value::Number(n) => {
+                if let Some(n) = n.as_u64().and_then(|n| u32::try_from(n).ok()) {
+                    return Ok(Self::Fixed(n));
+                }
+            }
+            serde_json::Value::String(s) if s == "auto" => {
+                return Ok(Self::Source(BuildNumberSource::Auto))
+            }
+            serde_json::Value::String(s) if s == "git" => {
+                return Ok(Self::Source(BuildNumberSource::Git))
+            }
+            _ => {}
+        }
+        Err(format!(
+            "`build_number` must be a positive integer, \"auto\" or \"git\", not {}",
+            value
+        ))
+    }
+}
+
+/// `[package.metadata.pdxinfo]`
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct PdxInfoConfig {
+    name: Option<String>,
+    author: Option<String>,
+    description: Option<String>,
+    bundle_id: Option<String>,
+    version: Option<String>,
+    build_number: Option<BuildNumberConfig>,
+    image_path: Option<String>,
+    launch_sound_path: Option<String>,
+    content_warning: Option<String>,
+    content_warning2: Option<String>,
+}
+
+impl PdxInfoConfig {
+    fn load(package: &Package) -> anyhow::Result<Self> {
+        let Some(value) = package.metadata.get("pdxinfo") else {
+            return Ok(Self::default());
+        };
+        serde_json::from_value(value.clone())
+            .map_err(|e| anyhow::anyhow!("Invalid `package.metadata.pdxinfo`: {}", e))
+    }
+}
+
+/// Returns the build number of this build, 1 if `build_number` is not set. `new_build` increments the number of `build_number = "auto"`.
+fn resolve_build_number(
+    config: &PdxInfoConfig,
+    package_dir: &Path,
+    new_build: bool,
+) -> anyhow::Result<u32> {
+    match config.build_number.unwrap_or(BuildNumberConfig::Fixed(1)) {
+        BuildNumberConfig::Fixed(n) => Ok(n),
+        BuildNumberConfig::Source(BuildNumberSource::Git) => {
+            let output = Command::new("git")
+                .args(["rev-list", "--count", "HEAD"])
+                .current_dir(package_dir)
+                .output()?;
+            let count = String::from_utf8_lossy(&output.stdout)
+                .trim()
+                .parse::<u32>();
+            match count {
+                Ok(count) if output.status.success() => Ok(count),
+                _ => anyhow::bail!(
+                    "Failed to count the git commits for `build_number = \"git\"`: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            }
+        }
+        BuildNumberConfig::Source(BuildNumberSource::Auto) => {
+            let path = package_dir.join(BUILD_NUMBER_FILE);
+            let last = match fs::read_to_string(&path) {
+                Ok(s) => s.trim().parse::<u32>().map_err(|e| {
+                    anyhow::anyhow!("Invalid build number in {}: {}", path.to_string_lossy(), e)
+                })?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+                Err(e) => return Err(e.into()),
+            };
+            if !new_build {
+                return Ok(last.max(1));
+            }
+            let next = last + 1;
+            fs::write(&path, format!("{}\n", next))?;
+            info!(
+                "Build number {} (stored in {})",
+                next,
+                path.to_string_lossy()
+            );
+            Ok(next)
+        }
+    }
+}
+
+/// Parses the `key=value` lines of a pdxinfo file
+fn parse(pdxinfo: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let mut entries: Vec<(String, String)> = vec![];
+    for (i, line) in pdxinfo.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            anyhow::bail!("Invalid pdxinfo: line {} is not `key=value`", i + 1);
+        };
+        if entries.iter().any(|(k, _)| k == key) {
+            anyhow::bail!("Invalid pdxinfo: `{}` is set twice", key);
+        }
+        entries.push((key.to_owned(), value.to_owned()));
+    }
+    Ok(entries)
+}
+
+/// Returns `true` if `bundle_id` is in reverse DNS notation, e.g. `com.example.game`
+fn is_valid_bundle_id(bundle_id: &str) -> bool {
+    let segments = bundle_id.split('.').collect::<Vec<_>>();
+    segments.len() >= 2
+        && segments.iter().all(|s| {
+            !s.is_empty()
+                && s.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+/// Returns `true` if `path` is a file or a folder of the pdx folder. The extension of a file may be omitted.
+fn exists_in_pdx(pdx_dir: &Path, path: &str) -> bool {
+    let full = pdx_dir.join(path.trim_start_matches('/'));
+    if full.exists() {
+        return true;
+    }
+    let (Some(dir), Some(name)) = (full.parent(), full.file_name()) else {
+        return false;
+    };
+    fs::read_dir(dir).is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|e| PathBuf::from(e.file_name()).file_stem() == Some(name))
+    })
+}
+
+/// Checks the keys and values of a pdxinfo file. The referenced images and sounds must be in `pdx_dir`.
+fn validate(entries: &[(String, String)], pdx_dir: &Path) -> anyhow::Result<()> {
+    let mut errors = vec![];
+    let get = |key: &str| {
+        entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    for (key, value) in entries {
+        let Some((_, max_len)) = KEYS.iter().find(|(k, _)| k == key) else {
+            warn!("Unknown pdxinfo key `{}`", key);
+            continue;
+        };
+        let len = value.chars().count();
+        if len > *max_len {
+            errors.push(format!(
+                "`{}` is {} characters long, the limit is {}",
+                key, len, max_len
+            ));
+        }
+    }
+    for key in ["name", "bundleID", "version", "buildNumber"] {
+        if get(key).is_none_or(|v| v.trim().is_empty()) {
+            errors.push(format!("`{}` is missing", key));
+        }
+    }
+    if let Some(bundle_id) = get("bundleID").filter(|b| !b.is_empty()) {
+        if !is_valid_bundle_id(bundle_id) {
+            errors.push(format!(
+                "`bundleID` must be in reverse DNS notation with letters, digits, `-` and `_`, e.g. `com.example.game`, not `{}`",
+                bundle_id
+            ));
+        }
+    }
+    if let Some(build_number) = get("buildNumber").filter(|b| !b.is_empty()) {
+        if build_number.parse::<u32>().is_err() {
+            errors.push(format!(
+                "`buildNumber` must be a positive integer, not `{}`",
+                build_number
+            ));
+        }
+    }
+    for key in ["imagePath", "launchSoundPath"] {
+        if let Some(path) = get(key).filter(|p| !p.is_empty()) {
+            if !exists_in_pdx(pdx_dir, path) {
+                errors.push(format!(
+                    "`{}` refers to `{}`, which is not in the asset folders",
+                    key, path
+                ));
+            }
+        }
+    }
+    if get("contentWarning2").is_some() && get("contentWarning").is_none() {
+        errors.push("`contentWarning2` is set without `contentWarning`".to_owned());
+    }
+    if !errors.is_empty() {
+        anyhow::bail!("Invalid pdxinfo:\n  {}", errors.join("\n  "));
+    }
+    Ok(())
+}
+
+/// Generates the pdxinfo of a target of `package`, or reads the pdxinfo file of the package, and validates it against the assets synced to `pdx_dir`. `new_build` is `true` for the release builds that changed the binary or the assets, which increment `build_number = "auto"`.
+pub fn load(
+    package: &Package,
+    target_name: &str,
+    example: bool,
+    new_build: bool,
+    pdx_dir: &Path,
+) -> anyhow::Result<String> {
+    let config = PdxInfoConfig::load(package)?;
+    let package_dir = package.manifest_path.as_std_path().parent().unwrap();
+    let version = config
+        .version
+        .clone()
+        .unwrap_or_else(|| package.version.to_string());
+    let pdxinfo_path = package_dir.join("pdxinfo");
+    let pdxinfo = if !example && (pdxinfo_path.is_file() || pdxinfo_path.is_symlink()) {
+        // If there is a pdxinfo file under package root, use it, with the default version and build number
+        info!("Using pdxinfo from {}", pdxinfo_path.to_string_lossy());
+        let mut pdxinfo = fs::read_to_string(pdxinfo_path)?;
+        let keys = parse(&pdxinfo)?
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect::<HashSet<_>>();
+        if !pdxinfo.is_empty() && !pdxinfo.ends_with('\n') {
+            pdxinfo.push('\n');
+        }
+        if !keys.contains("version") {
+            pdxinfo.push_str(&format!("version={}\n", version));
+        }
+        if !keys.contains("buildNumber") {
+            let build_number = resolve_build_number(&config, package_dir, new_build)?;
+            pdxinfo.push_str(&format!("buildNumber={}\n", build_number));
+        }
+        pdxinfo
+    } else {
+        let get = |value: &Option<String>, default: &str| -> String {
+            value.clone().unwrap_or_else(|| default.to_owned())
+        };
+        let (name, author, description, bundle_id) = if example {
+            (
+                target_name.to_owned(),
+                "playdate-rs".to_owned(),
+                "playdate-rs-example".to_owned(),
+                format!("me.wenyu.playdate.example.{}", target_name),
+            )
+        } else {
+            let default_bundle_id = format!("com.example.{}", package.name);
+            if config.bundle_id.is_none() {
+                warn!("Using default bundle id: {}", default_bundle_id);
+            }
+            (
+                get(&config.name, target_name),
+                get(&config.author, &package.authors.join(", ")),
+                get(
+                    &config.description,
+                    package.description.as_deref().unwrap_or_default(),
+                ),
+                get(&config.bundle_id, &default_bundle_id),
+            )
+        };
+        let mut env = minijinja::Environment::new();
+        env.add_template("pdxinfo", PDXINFO)?;
+        let template = env.get_template("pdxinfo").unwrap();
+        template.render(minijinja::context! {
+            name => name,
+            author => author,
+            description => description,
+            bundle_id => bundle_id,
+            version => version,
+            build_number => resolve_build_number(&config, package_dir, new_build)?,
+            image_path => get(&config.image_path, "").trim(),
+            launch_sound_path => get(&config.launch_sound_path, "").trim(),
+            content_warning => get(&config.content_warning, "").trim(),
+            content_warning2 => get(&config.content_warning2, "").trim(),
+        })?
+    };
+    let entries = parse(&pdxinfo)?;
+    validate(&entries, pdx_dir)?;
+    Ok(pdxinfo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str =
+        "name=Game\nbundleID=com.example.game\nversion=0.1.0\nbuildNumber=1\nimagePath=launcher\n";
+
+    fn check(pdxinfo: &str) -> anyhow::Result<()> {
+        let pdx_dir =
+            std::env::temp_dir().join(format!("playdate-cli-pdxinfo-{}", std::process::id()));
+        fs::create_dir_all(pdx_dir.join("launcher")).unwrap();
+        fs::write(pdx_dir.join("launcher/card.png"), []).unwrap();
+        validate(&parse(pdxinfo)?, &pdx_dir)
+    }
+
+    fn errors(pdxinfo: &str) -> String {
+        check(pdxinfo).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parse_lines() {
+        let entries = parse("name=a=b\r\n\nauthor=\n").unwrap();
+        assert_eq!(
+            entries,
+            [("name".into(), "a=b".into()), ("author".into(), "".into())]
+        );
+        assert!(parse("name=a\nname=b\n").is_err());
+        assert!(parse("name=a\nauthor\n").is_err());
+    }
+
+    #[test]
+    fn validate_valid_pdxinfo() {
+        check(VALID).unwrap();
+        // The extension of the referenced files may be omitted
+        check(&VALID.replace("imagePath=launcher", "imagePath=launcher/card")).unwrap();
+        check(&format!("{}contentWarning=Flashing lights\n", VALID)).unwrap();
+    }
+
+    #[test]
+    fn validate_errors() {
+        assert!(errors("name=Game\n").contains("`bundleID` is missing"));
+        assert!(errors(&VALID.replace("com.example.game", "game")).contains("reverse DNS"));
+        assert!(
+            errors(&VALID.replace("com.example.game", "com.example game")).contains("reverse DNS")
+        );
+        assert!(
+            errors(&VALID.replace("buildNumber=1", "buildNumber=-1")).contains("positive integer")
+        );
+        assert!(errors(&VALID.replace("launcher", "missing")).contains("`missing`"));
+        let name = "a".repeat(65);
+        assert!(errors(&VALID.replace("Game", &name))
+            .contains("`name` is 65 characters long, the limit is 64"));
+        assert!(errors(&format!("{}contentWarning2=Loud\n", VALID))
+            .contains("without `contentWarning`"));
+    }
+}
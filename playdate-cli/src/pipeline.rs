@@ -0,0 +1,213 @@
+//! Shared parts of the build-time asset conversions: per-glob rules, the content-addressed cache and the naming of the outputs.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use serde::de::DeserializeOwned;
+
+/// Name of the outputs in a cache entry, replaced by the name of the source file
+const OUTPUT_STEM: &str = "out";
+
+/// The `[[package.metadata.playdate.<key>]]` rules: a `files` list of glob patterns, matching the path of the assets inside the pdx, and the settings `S`. The first rule matching an asset is used.
+pub(crate) struct Rules<S> {
+    rules: Vec<(Vec<glob::Pattern>, S)>,
+}
+
+impl<S: DeserializeOwned> Rules<S> {
+    /// Loads the rules under `playdate.<key>` of the package metadata. Returns `None` if there are no rules.
+    pub fn load(metadata: &serde_json::Value, key: &str) -> anyhow::Result<Option<Self>> {
+        let full_key = format!("package.metadata.playdate.{}", key);
+        let Some(value) = metadata.get("playdate").and_then(|p| p.get(key)) else {
+            return Ok(None);
+        };
+        let invalid = |e: &dyn std::fmt::Display| anyhow::anyhow!("Invalid `{}`: {}", full_key, e);
+        let configs = serde_json::from_value::<Vec<serde_json::Map<String, serde_json::Value>>>(
+            value.clone(),
+        )
+        .map_err(|e| invalid(&e))?;
+        let mut rules = vec![];
+        for mut config in configs {
+            // `files` is split from the settings, so unknown settings are rejected
+            let files = config
+                .remove("files")
+                .ok_or_else(|| invalid(&"missing field `files`"))?;
+            let files = serde_json::from_value::<Vec<String>>(files).map_err(|e| invalid(&e))?;
+            let settings = serde_json::from_value::<S>(config.into()).map_err(|e| invalid(&e))?;
+            let patterns = files
+                .iter()
+                .map(|p| {
+                    glob::Pattern::new(p).map_err(|e| {
+                        anyhow::anyhow!("Invalid pattern `{}` in `{}`: {}", p, full_key, e)
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            rules.push((patterns, settings));
+        }
+        Ok(Some(Self { rules }))
+    }
+
+    /// Returns the settings of the first rule matching `path`
+    pub fn find(&self, path: &str) -> Option<&S> {
+        self.find_index(path).map(|i| self.get(i))
+    }
+
+    /// Returns the index of the first rule matching `path`
+    pub fn find_index(&self, path: &str) -> Option<usize> {
+        self.rules
+            .iter()
+            .position(|(patterns, _)| patterns.iter().any(|p| p.matches(path)))
+    }
+
+    /// Returns the settings of the rule at `index`
+    pub fn get(&self, index: usize) -> &S {
+        &self.rules[index].1
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+}
+
+/// Converted assets, stored in a folder per source file and settings
+pub(crate) struct Cache {
+    dir: PathBuf,
+    version: u32,
+}
+
+impl Cache {
+    /// `version` must be bumped when the output of a conversion changes
+    pub fn new(dir: &Path, version: u32) -> Self {
+        Self {
+            dir: dir.to_owned(),
+            version,
+        }
+    }
+
+    /// Returns the cache entry of `source` converted with `settings`. If there is none, `convert` is called with the source file and an empty folder, where it writes `out<suffix>` files.
+    pub fn get_or_convert(
+        &self,
+        source: &Path,
+        settings: &impl Hash,
+        convert: impl FnOnce(&Path, &Path) -> anyhow::Result<()>,
+    ) -> anyhow::Result<PathBuf> {
+        let mut hasher = self.hasher(settings);
+        source
+            .extension()
+            .map(|e| e.to_ascii_lowercase())
+            .hash(&mut hasher);
+        fs::read(source)?.hash(&mut hasher);
+        self.get_or_create(hasher.finish(), &source.to_string_lossy(), |dir| {
+            convert(source, dir)
+        })
+    }
+
+    /// Returns the cache entry of the files `sources` (paths inside the pdx and source paths) combined into `name` with `settings`. If there is none, `convert` is called with an empty folder, where it writes `out<suffix>` files.
+    pub fn get_or_combine(
+        &self,
+        name: &str,
+        sources: &[(String, PathBuf)],
+        settings: &impl Hash,
+        convert: impl FnOnce(&Path) -> anyhow::Result<()>,
+    ) -> anyhow::Result<PathBuf> {
+        let mut hasher = self.hasher(settings);
+        for (relative, source) in sources {
+            relative.hash(&mut hasher);
+            fs::read(source)?.hash(&mut hasher);
+        }
+        self.get_or_create(hasher.finish(), name, convert)
+    }
+
+    fn hasher(&self, settings: &impl Hash) -> impl Hasher {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.version.hash(&mut hasher);
+        settings.hash(&mut hasher);
+        hasher
+    }
+
+    fn get_or_create(
+        &self,
+        hash: u64,
+        name: &str,
+        convert: impl FnOnce(&Path) -> anyhow::Result<()>,
+    ) -> anyhow::Result<PathBuf> {
+        let entry = self.dir.join(format!("{:016x}", hash));
+        if entry.is_dir() {
+            return Ok(entry);
+        }
+        // Write to a temporary folder first, so an interrupted build never leaves a partial entry
+        let tmp = entry.with_extension("tmp");
+        if tmp.exists() {
+            fs::remove_dir_all(&tmp)?;
+        }
+        fs::create_dir_all(&tmp)?;
+        convert(&tmp)?;
+        fs::rename(&tmp, &entry)?;
+        info!("Converted {}", name);
+        Ok(entry)
+    }
+}
+
+/// Returns the path of an output file named `out<suffix>` in a cache entry
+pub(crate) fn output_path(entry: &Path, suffix: &str) -> PathBuf {
+    entry.join(format!("{}{}", OUTPUT_STEM, suffix))
+}
+
+/// Strips the extension of the file name in `relative`, but not of a folder or a dotfile
+fn strip_extension(relative: &str) -> &str {
+    let name_start = relative.rfind('/').map_or(0, |i| i + 1);
+    match relative.rfind('.') {
+        Some(i) if i > name_start => &relative[..i],
+        _ => relative,
+    }
+}
+
+/// The files to bundle, as their path inside the pdx and their source path
+#[derive(Default)]
+pub(crate) struct Outputs {
+    files: BTreeMap<String, PathBuf>,
+}
+
+impl Outputs {
+    pub fn insert(&mut self, relative: String, source: PathBuf) {
+        if let Some(previous) = self.files.insert(relative.clone(), source) {
+            warn!(
+                "{} is overridden by a converted asset ({})",
+                relative,
+                previous.to_string_lossy()
+            );
+        }
+    }
+
+    /// Adds the outputs in the cache `entry` of the asset at `relative`, renamed after it
+    pub fn insert_entry(&mut self, relative: &str, entry: &Path) -> anyhow::Result<()> {
+        let stem = strip_extension(relative);
+        for output in fs::read_dir(entry)? {
+            let name = output?.file_name().to_string_lossy().into_owned();
+            let suffix = name.strip_prefix(OUTPUT_STEM).unwrap_or(&name);
+            self.insert(format!("{}{}", stem, suffix), entry.join(&name));
+        }
+        Ok(())
+    }
+
+    pub fn into_vec(self) -> Vec<(String, PathBuf)> {
+        self.files.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_extension_of_file_names_only() {
+        assert_eq!(strip_extension("rust.png"), "rust");
+        assert_eq!(strip_extension("sprites/hero.tar.gz"), "sprites/hero.tar");
+        assert_eq!(strip_extension("sprites.v2/hero"), "sprites.v2/hero");
+        assert_eq!(strip_extension("data/.hidden"), "data/.hidden");
+        assert_eq!(strip_extension("README"), "README");
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    hash::Hasher,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// How a source file is written to the destination folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    /// Copy the file as is
+    Copy,
+    /// Write a zlib compressed `<file>.z` with the given level, for `playdate_rs::fs::compress::read`
+    Compress(u8),
+}
+
+impl Transfer {
+    fn get_tag(&self) -> String {
+        match self {
+            Transfer::Copy => "copy".to_owned(),
+            Transfer::Compress(level) => format!("zlib{}", level),
+        }
+    }
+
+    fn get_destination(&self, relative: &str) -> String {
+        match self {
+            Transfer::Copy => relative.to_owned(),
+            Transfer::Compress(_) => format!("{}.z", relative),
+        }
+    }
+}
+
+/// Changes made to the destination folder by [`sync_files`]. Paths are relative to the destination folder.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub copied: Vec<String>,
+    pub compressed: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl SyncReport {
+    /// Returns `true` if the destination folder was not modified
+    pub fn is_empty(&self) -> bool {
+        self.copied.is_empty() && self.compressed.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn log(&self) {
+        for path in &self.copied {
+            info!("Copied {}", path);
+        }
+        for path in &self.compressed {
+            info!("Compressed {}", path);
+        }
+        for path in &self.removed {
+            info!("Removed {}", path);
+        }
+        debug!("{} files unchanged", self.unchanged);
+    }
+}
+
+/// State of a source file after it was last written to the destination folder
+struct ManifestEntry {
+    mtime: u128,
+    size: u64,
+    hash: u64,
+    transfer: String,
+    destination: String,
+}
+
+/// Reads the manifest written by the previous sync. A missing or malformed manifest syncs every file again.
+fn load_manifest(path: &Path) -> HashMap<String, ManifestEntry> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    let mut manifest = HashMap::new();
+    for line in content.lines() {
+        let fields = line.splitn(6, '\t').collect::<Vec<_>>();
+        let [mtime, size, hash, transfer, source, destination] = fields[..] else {
+            return HashMap::new();
+        };
+        let (Ok(mtime), Ok(size), Ok(hash)) = (mtime.parse(), size.parse(), hash.parse()) else {
+            return HashMap::new();
+        };
+        manifest.insert(
+            source.to_owned(),
+            ManifestEntry {
+                mtime,
+                size,
+                hash,
+                transfer: transfer.to_owned(),
+                destination: destination.to_owned(),
+            },
+        );
+    }
+    manifest
+}
+
+fn save_manifest(path: &Path, manifest: &HashMap<String, ManifestEntry>) -> io::Result<()> {
+    let mut sources = manifest.keys().collect::<Vec<_>>();
+    sources.sort();
+    let mut content = String::new();
+    for source in sources {
+        let e = &manifest[source];
+        content.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\n",
+            e.mtime, e.size, e.hash, e.transfer, source, e.destination
+        ));
+    }
+    fs::write(path, content)
+}
+
+/// Hashes the content of a file. The hash only needs to be stable between two builds with the same CLI.
+fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    let mut file = fs::File::open(path)?;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.write(&buf[..n]);
+    }
+    Ok(hasher.finish())
+}
+
+/// Joins the components of a relative path with `/`, so paths are the same on every platform
+fn to_slash_path(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Lists the files under `dir` with their path relative to `dir`, following symlinks. The game needs the content of the linked files, so a link is synced as the file or folder it points to. Broken links and links to a parent folder are skipped with a warning.
+pub fn list_files(dir: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    fn visit(
+        root: &Path,
+        dir: &Path,
+        ancestors: &mut Vec<PathBuf>,
+        files: &mut Vec<(String, PathBuf)>,
+    ) -> io::Result<()> {
+        let canonical = fs::canonicalize(dir)?;
+        if ancestors.contains(&canonical) {
+            warn!(
+                "Skipping {}: symlink to a parent folder",
+                dir.to_string_lossy()
+            );
+            return Ok(());
+        }
+        ancestors.push(canonical);
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            // `metadata` follows symlinks
+            let Ok(metadata) = fs::metadata(&path) else {
+                warn!("Skipping {}: broken symlink", path.to_string_lossy());
+                continue;
+            };
+            if metadata.is_dir() {
+                visit(root, &path, ancestors, files)?;
+            } else {
+                files.push((to_slash_path(path.strip_prefix(root).unwrap()), path));
+            }
+        }
+        ancestors.pop();
+        Ok(())
+    }
+    let mut files = vec![];
+    if dir.is_dir() {
+        visit(dir, dir, &mut vec![], &mut files)?;
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Lists the files and folders under `dir`, without following symlinks. Folders are listed after their content.
+fn list_destination_entries(dir: &Path) -> io::Result<Vec<(String, bool)>> {
+    let mut entries = vec![];
+    if !dir.is_dir() {
+        return Ok(entries);
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_dir() {
+            for (path, is_dir) in list_destination_entries(&entry.path())? {
+                entries.push((format!("{}/{}", name, path), is_dir));
+            }
+            entries.push((name, true));
+        } else {
+            entries.push((name, false));
+        }
+    }
+    Ok(entries)
+}
+
+fn write_file(source: &Path, destination: &Path, transfer: Transfer) -> io::Result<()> {
+    if destination.is_dir() {
+        fs::remove_dir_all(destination)?;
+    } else if destination.is_symlink() || destination.exists() {
+        // Never write through a symlink left in the destination folder
+        fs::remove_file(destination)?;
+    }
+    fs::create_dir_all(destination.parent().unwrap())?;
+    match transfer {
+        Transfer::Copy => {
+            fs::copy(source, destination)?;
+        }
+        Transfer::Compress(level) => {
+            let data = fs::read(source)?;
+            let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&data, level);
+            fs::write(destination, compressed)?;
+        }
+    }
+    Ok(())
+}
+
+/// Mirrors the files of `source_dir` into `destination_dir`. See [`sync_files`].
+pub fn sync_dir(
+    source_dir: &Path,
+    destination_dir: &Path,
+    manifest_path: &Path,
+    keep: &[&str],
+    transfer: impl Fn(&str) -> Transfer,
+) -> anyhow::Result<SyncReport> {
+    sync_files(
+        list_files(source_dir)?,
+        destination_dir,
+        manifest_path,
+        keep,
+        transfer,
+    )
+}
+
+/// Writes the `files`, given as their path relative to `destination_dir` and their source path, into `destination_dir`, writing only the files that changed since the last sync.
+///
+/// A file is unchanged if its modification time and size match the manifest at `manifest_path`, or else if its content hash does, e.g. after a `git checkout`. Destination files without a source file are removed, except the `keep` files, given relative to the destination folder. `transfer` decides how each file is written, from its relative path.
+pub fn sync_files(
+    files: Vec<(String, PathBuf)>,
+    destination_dir: &Path,
+    manifest_path: &Path,
+    keep: &[&str],
+    transfer: impl Fn(&str) -> Transfer,
+) -> anyhow::Result<SyncReport> {
+    let old_manifest = load_manifest(manifest_path);
+    let mut manifest = HashMap::new();
+    let mut report = SyncReport::default();
+    let mut expected = keep.iter().map(|k| k.to_string()).collect::<HashSet<_>>();
+    for (relative, source) in files {
+        let metadata = fs::metadata(&source)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        let size = metadata.len();
+        let transfer = transfer(&relative);
+        let destination = transfer.get_destination(&relative);
+        let destination_path = destination_dir.join(&destination);
+        let old = old_manifest.get(&relative).filter(|e| {
+            e.transfer == transfer.get_tag()
+                && e.destination == destination
+                && destination_path.is_file()
+        });
+        let hash = match old {
+            Some(e) if e.mtime == mtime && e.size == size => e.hash,
+            _ => hash_file(&source)?,
+        };
+        if old.is_some_and(|e| e.hash == hash) {
+            report.unchanged += 1;
+        } else {
+            write_file(&source, &destination_path, transfer)?;
+            match transfer {
+                Transfer::Copy => report.copied.push(destination.clone()),
+                Transfer::Compress(_) => report.compressed.push(destination.clone()),
+            }
+        }
+        expected.insert(destination.clone());
+        manifest.insert(
+            relative,
+            ManifestEntry {
+                mtime,
+                size,
+                hash,
+                transfer: transfer.get_tag(),
+                destination,
+            },
+        );
+    }
+    // Remove the stale files, then the folders left empty
+    for (relative, is_dir) in list_destination_entries(destination_dir)? {
+        let path = destination_dir.join(&relative);
+        if is_dir {
+            if fs::read_dir(&path)?.next().is_none() {
+                fs::remove_dir(&path)?;
+            }
+        } else if !expected.contains(&relative) {
+            fs::remove_file(&path)?;
+            report.removed.push(relative);
+        }
+    }
+    fs::create_dir_all(destination_dir)?;
+    save_manifest(manifest_path, &manifest)?;
+    Ok(report)
+}
+
+/// Copies `source` to `destination` unless both files have the same content. Returns `true` if the file was copied.
+pub fn sync_file(source: &Path, destination: &Path) -> io::Result<bool> {
+    let unchanged = fs::metadata(destination)
+        .is_ok_and(|m| m.len() == fs::metadata(source).map_or(u64::MAX, |m| m.len()))
+        && fs::read(source)? == fs::read(destination)?;
+    if unchanged {
+        return Ok(false);
+    }
+    write_file(source, destination, Transfer::Copy)?;
+    Ok(true)
+}
+
+/// Writes `content` to `path` unless the file already has this content. Returns `true` if the file was written.
+pub fn write_if_changed(path: &Path, content: impl AsRef<[u8]>) -> io::Result<bool> {
+    if fs::read(path).is_ok_and(|c| c == content.as_ref()) {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("playdate-cli-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn sync_dir_skips_unchanged_and_removes_stale_files() {
+        let dir = temp_dir("sync");
+        let (src, dst, manifest) = (dir.join("src"), dir.join("dst"), dir.join("manifest"));
+        fs::create_dir_all(src.join("levels")).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(src.join("levels/1.json"), "{}").unwrap();
+        let sync = || sync_dir(&src, &dst, &manifest, &["pdxinfo"], |_| Transfer::Copy).unwrap();
+        let mut report = sync();
+        report.copied.sort();
+        assert_eq!(report.copied, vec!["a.txt", "levels/1.json"]);
+        assert_eq!(fs::read_to_string(dst.join("levels/1.json")).unwrap(), "{}");
+        // Nothing changed
+        let report = sync();
+        assert!(report.is_empty());
+        assert_eq!(report.unchanged, 2);
+        // Stale files and the folders left empty are removed, but not the `keep` files
+        fs::write(dst.join("pdxinfo"), "name=test").unwrap();
+        fs::remove_dir_all(src.join("levels")).unwrap();
+        let report = sync();
+        assert_eq!(report.removed, vec!["levels/1.json"]);
+        assert!(!dst.join("levels").exists());
+        assert!(dst.join("pdxinfo").exists());
+        // A changed file is copied again
+        fs::write(src.join("a.txt"), "bb").unwrap();
+        assert_eq!(sync().copied, vec!["a.txt"]);
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "bb");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+use std::process::Command;
+
+use cargo_metadata::{MetadataCommand, Package};
+
+use playdate_cli::sync;
+
+use crate::{assets::AssetRules, util::CommandExt, Runnable};
+
+/// Run the tests of the local package on the host, with a headless playdate API
+#[derive(clap::Args, Debug)]
+pub struct Test {
+    /// Build the tests in release mode, with optimizations
+    #[clap(long)]
+    release: bool,
+    #[command(flatten)]
+    features: clap_cargo::Features,
+    #[arg(short, long)]
+    /// Package to test (see `cargo help pkgid`)
+    pub package: Option<String>,
+    /// If specified, only run tests containing this string in their names
+    testname: Option<String>,
+    /// Arguments for the test binaries, e.g. `-- --nocapture`
+    #[arg(last = true)]
+    args: Vec<String>,
+}
+
+impl Test {
+    /// Returns the feature enabling the headless shim of playdate-rs, as seen from `package`
+    fn get_headless_feature(&self, package: &Package) -> anyhow::Result<String> {
+        if package.name == "playdate-rs" {
+            return Ok("headless".to_owned());
+        }
+        let dep = package
+            .dependencies
+            .iter()
+            .find(|d| d.name == "playdate-rs")
+            .ok_or_else(|| anyhow::anyhow!("{} does not depend on playdate-rs", package.name))?;
+        Ok(format!(
+            "{}/headless",
+            dep.rename.as_ref().unwrap_or(&dep.name)
+        ))
+    }
+
+    /// Integration tests can only link the crate if it has a rust library crate type
+    fn has_linkable_lib(&self, package: &Package) -> bool {
+        package.targets.iter().any(|t| {
+            t.kind.iter().any(|k| k == "lib" || k == "rlib")
+                && t.crate_types.iter().any(|c| c == "lib" || c == "rlib")
+        })
+    }
+
+    /// Name of the library crate, which names its asset manifest
+    fn get_lib_name(&self, package: &Package) -> String {
+        package
+            .targets
+            .iter()
+            .find(|t| {
+                t.kind
+                    .iter()
+                    .any(|k| matches!(k.as_str(), "lib" | "rlib" | "cdylib" | "staticlib"))
+            })
+            .map_or(&package.name, |t| &t.name)
+            .replace('-', "_")
+    }
+
+    fn get_cargo_flags(&self, package: &Package) -> anyhow::Result<Vec<String>> {
+        let mut flags = vec![];
+        if self.release {
+            flags.push("--release".to_owned());
+        }
+        let mut features = self.features.clone();
+        features.features.push(self.get_headless_feature(package)?);
+        flags.extend(crate::build::get_feature_flags(&features));
+        flags.push("--package".to_owned());
+        flags.push(package.name.clone());
+        if !self.has_linkable_lib(package) {
+            if package.targets.iter().any(|t| t.is_test()) {
+                warn!(
+                    "Skipping the integration tests of {}: add \"lib\" to its `crate-type` to run them",
+                    package.name
+                );
+            }
+            flags.push("--lib".to_owned());
+        }
+        if let Some(testname) = self.testname.as_ref() {
+            flags.push(testname.to_owned());
+        }
+        if !self.args.is_empty() {
+            flags.push("--".to_owned());
+            flags.extend(self.args.iter().cloned());
+        }
+        Ok(flags)
+    }
+}
+
+impl Runnable for Test {
+    fn run(&self) -> anyhow::Result<()> {
+        let meta = MetadataCommand::new()
+            .manifest_path("./Cargo.toml")
+            .exec()?;
+        let package = crate::build::find_package(&meta, self.package.as_ref())?;
+        // Every run starts with an empty data folder
+        let data_dir = meta
+            .target_directory
+            .as_std_path()
+            .join("playdate-test")
+            .join(&package.name)
+            .join("data");
+        if data_dir.exists() {
+            std::fs::remove_dir_all(&data_dir)?;
+        }
+        std::fs::create_dir_all(&data_dir)?;
+        // The pdx folder holds the converted assets selected by `[package.metadata.playdate.assets]`
+        let test_dir = data_dir.parent().unwrap();
+        let pdx_dir = test_dir.join("pdx");
+        let features = crate::assets::resolve_features(&package, &self.features);
+        let profile = if self.release { "release" } else { "dev" };
+        let rules = AssetRules::load(&package, None, &features, profile)?;
+        let files = crate::build::convert_assets(&meta, &package, rules.list_files()?)?;
+        let manifest_dir = test_dir.join("assets");
+        crate::build::write_asset_manifest(&manifest_dir, &self.get_lib_name(&package), &files)?;
+        sync::sync_files(files, &pdx_dir, &test_dir.join("pdx.assets"), &[], |_| {
+            sync::Transfer::Copy
+        })?
+        .log();
+        info!("Testing {}", package.name);
+        Command::new("cargo")
+            .arg("+nightly")
+            .arg("test")
+            .args(self.get_cargo_flags(&package)?)
+            .env("PLAYDATE_HEADLESS_DATA_DIR", &data_dir)
+            .env("PLAYDATE_HEADLESS_PDX_DIR", &pdx_dir)
+            .env("PLAYDATE_ASSETS_DIR", &manifest_dir)
+            .check(true)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Child, Command},
+    sync::mpsc,
+    time::Duration,
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::{
+    assets::AssetRules,
+    build::{BuildInfo, Changes},
+    Runnable,
+};
+
+/// Time to wait for more changes after a change, so a save touching several files triggers a single build
+static DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Rebuild the local package on changes and relaunch it on the playdate simulator
+#[derive(clap::Args, Debug)]
+pub struct Watch {
+    #[command(flatten)]
+    build: crate::build::Build,
+}
+
+impl Watch {
+    /// Returns the package directory, the asset rules and the directories to watch recursively
+    fn get_watched_paths(&self) -> anyhow::Result<(PathBuf, AssetRules, Vec<PathBuf>)> {
+        let meta = self.build.load_metadata()?;
+        let package = self.build.get_package(&meta)?;
+        let package_dir = package.manifest_path.parent().unwrap().as_std_path();
+        let rules = self.build.get_asset_rules(&package)?;
+        let mut paths = vec![package_dir.join("src")];
+        paths.extend(rules.roots.iter().map(|r| r.src.clone()));
+        if self.build.example.is_some() {
+            paths.push(package_dir.join("examples"));
+        }
+        Ok((package_dir.to_owned(), rules, paths))
+    }
+
+    /// Sorts the changed paths into the parts of the pipeline to rerun
+    fn add_changes(
+        changes: &mut Changes,
+        package_dir: &Path,
+        rules: &AssetRules,
+        paths: &[PathBuf],
+    ) {
+        for path in paths {
+            if rules.contains(path) {
+                if !changes.assets.contains(path) {
+                    changes.assets.push(path.clone());
+                }
+            } else if path == &package_dir.join("pdxinfo") {
+                changes.pdxinfo = true;
+            } else if path == &package_dir.join("Cargo.toml") {
+                // pdxinfo may be generated from the manifest, and the asset rules are read from it
+                changes.code = true;
+                changes.pdxinfo = true;
+                if !changes.assets.contains(path) {
+                    changes.assets.push(path.clone());
+                }
+            } else if path.extension().is_some_and(|e| e == "rs") {
+                changes.code = true;
+            }
+        }
+    }
+
+    /// Blocks until a watched file changes
+    fn wait_for_changes(
+        &self,
+        rx: &mpsc::Receiver<notify::Result<notify::Event>>,
+        package_dir: &Path,
+        rules: &AssetRules,
+    ) -> anyhow::Result<Changes> {
+        info!("Watching for changes ...");
+        let mut changes = Changes::default();
+        let mut event = rx.recv()?;
+        loop {
+            match event {
+                Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                    Self::add_changes(&mut changes, package_dir, rules, &event.paths)
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Watch error: {}", e),
+            }
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(next) => event = next,
+                Err(mpsc::RecvTimeoutError::Timeout) if changes.is_empty() => event = rx.recv()?,
+                Err(mpsc::RecvTimeoutError::Timeout) => return Ok(changes),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Watches the `paths` that exist and are not watched yet, and stops watching the `watched` paths not in `paths`
+    fn update_watches(
+        watcher: &mut impl Watcher,
+        watched: &mut Vec<PathBuf>,
+        paths: &[PathBuf],
+    ) -> anyhow::Result<()> {
+        for path in watched.iter().filter(|p| !paths.contains(p)) {
+            // The folder may have been deleted, which already removed its watch
+            let _ = watcher.unwatch(path);
+        }
+        watched.retain(|p| paths.contains(p));
+        for path in paths {
+            if !watched.contains(path) && path.exists() {
+                watcher.watch(path, RecursiveMode::Recursive)?;
+                watched.push(path.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Launches the simulator. Errors are logged, so the watcher keeps running.
+    fn launch_simulator(&self, build_info: &BuildInfo) -> Option<Child> {
+        let launch = || -> anyhow::Result<Child> {
+            let simulator = crate::util::get_playdate_simulator()?;
+            info!("Running {}", build_info.name);
+            Ok(Command::new(simulator).arg(&build_info.pdx).spawn()?)
+        };
+        match launch() {
+            Ok(child) => Some(child),
+            Err(e) => {
+                error!("Failed to launch the simulator: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+impl Runnable for Watch {
+    fn run(&self) -> anyhow::Result<()> {
+        if self.build.device {
+            anyhow::bail!("`cargo playdate watch` only supports the simulator");
+        }
+        let (package_dir, mut rules, paths) = self.get_watched_paths()?;
+        let manifest_path = package_dir.join("Cargo.toml");
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        // `Cargo.toml`, `pdxinfo` and `build.rs`
+        watcher.watch(&package_dir, RecursiveMode::NonRecursive)?;
+        let mut watched = vec![];
+        Self::update_watches(&mut watcher, &mut watched, &paths)?;
+        // The first build is a full build. If it fails, wait for changes and retry
+        let mut simulator = None;
+        let mut full_build = true;
+        // Changes of failed rebuilds, retried with the next changes
+        let mut pending = Changes::default();
+        loop {
+            if full_build {
+                match self.build.run() {
+                    Ok(build_info) => {
+                        simulator = self.launch_simulator(&build_info);
+                        full_build = false;
+                    }
+                    Err(e) => error!("{:?}", e),
+                }
+            }
+            let changes = self.wait_for_changes(&rx, &package_dir, &rules)?;
+            // The asset roots and rules are read from `Cargo.toml`
+            if changes.assets.contains(&manifest_path) {
+                match self.get_watched_paths() {
+                    Ok((_, new_rules, paths)) => {
+                        rules = new_rules;
+                        Self::update_watches(&mut watcher, &mut watched, &paths)?;
+                    }
+                    Err(e) => error!("{:?}", e),
+                }
+            }
+            if full_build {
+                continue;
+            }
+            pending.merge(changes);
+            let result = self.build.rebuild(&pending);
+            if result.is_ok() {
+                pending = Changes::default();
+            }
+            match result {
+                Ok(Some(build_info)) => {
+                    if let Some(mut child) = simulator.take() {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                    }
+                    simulator = self.launch_simulator(&build_info);
+                }
+                Ok(None) => info!("No changes to the pdx"),
+                // Keep watching, the next change may fix the build
+                Err(e) => error!("{:?}", e),
+            }
+        }
+    }
+}
@@ -1,3 +1,8 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
 use proc_macro::TokenStream;
 use quote::quote;
 
@@ -11,3 +16,51 @@ pub fn app(_attr: TokenStream, item: TokenStream) -> TokenStream {
     };
     result.into()
 }
+
+/// Environment variable set by `cargo playdate build` to the folder of the generated asset manifests
+const ASSETS_DIR_VAR: &str = "PLAYDATE_ASSETS_DIR";
+
+/// Finds the asset manifest of the crate being compiled: in `$PLAYDATE_ASSETS_DIR`, or in `target/playdate-assets` of the package or workspace when building without `cargo playdate`, e.g. in an IDE
+fn find_asset_manifest() -> Result<PathBuf, String> {
+    let crate_name = env::var("CARGO_CRATE_NAME").map_err(|e| e.to_string())?;
+    let file_name = format!("{}.rs", crate_name);
+    if let Ok(dir) = env::var(ASSETS_DIR_VAR) {
+        let path = Path::new(&dir).join(&file_name);
+        return if path.is_file() {
+            Ok(path)
+        } else {
+            Err(format!("{} does not exist", path.to_string_lossy()))
+        };
+    }
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").map_err(|e| e.to_string())?;
+    Path::new(&manifest_dir)
+        .ancestors()
+        .map(|dir| dir.join("target").join("playdate-assets").join(&file_name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            format!(
+                "No asset manifest for `{}`. Build the game with `cargo playdate build` to generate it",
+                crate_name
+            )
+        })
+}
+
+/// Includes the typed constants that `cargo playdate build` generates for the asset folders, as a module named `assets`
+#[proc_macro]
+pub fn include_assets(input: TokenStream) -> TokenStream {
+    if !input.is_empty() {
+        return quote! { compile_error!("`include_assets!` takes no arguments"); }.into();
+    }
+    let path = match find_asset_manifest() {
+        Ok(path) => path.to_string_lossy().into_owned(),
+        Err(e) => return quote! { compile_error!(#e); }.into(),
+    };
+    let result = quote! {
+        pub mod assets {
+            include!(#path);
+            // Rebuild when the manifests move, e.g. on the first `cargo playdate build`
+            const _: Option<&str> = option_env!(#ASSETS_DIR_VAR);
+        }
+    };
+    result.into()
+}
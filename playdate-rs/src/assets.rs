@@ -0,0 +1,168 @@
+//! Typed handles to the assets bundled in the game's pdx.
+//!
+//! `cargo playdate build` generates a constant for every asset of the asset folders, which [`include_assets!`](crate::include_assets) includes as a module named `assets`:
+//!
+//! ```ignore
+//! playdate_rs::include_assets!();
+//!
+//! let image = assets::images::RUST.load()?;
+//! let jump = assets::sounds::sfx::JUMP.load()?;
+//! ```
+//!
+//! A renamed or deleted asset is then a compile error instead of a runtime [`Error`].
+
+use alloc::vec::Vec;
+
+use crate::{
+    error::Error,
+    fs::{self, AssetReader},
+    graphics::{Atlas, Bitmap, BitmapTable, Font},
+    io,
+    sound::{AudioSample, FilePlayer},
+    PLAYDATE,
+};
+
+/// An image, compiled by `pdc` from a `.png` file or a still `.gif` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageAsset {
+    path: &'static str,
+}
+
+impl ImageAsset {
+    pub const fn new(path: &'static str) -> Self {
+        Self { path }
+    }
+
+    /// Returns the path of the image in the pdx, without extension.
+    pub const fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Loads the image.
+    pub fn load(&self) -> Result<Bitmap, Error> {
+        PLAYDATE.graphics.load_bitmap(self.path)
+    }
+}
+
+/// An image table, compiled by `pdc` from a `-table-<width>-<height>.png` sheet, a `-table-<n>.png` strip or an animated `.gif` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageTableAsset {
+    path: &'static str,
+}
+
+impl ImageTableAsset {
+    pub const fn new(path: &'static str) -> Self {
+        Self { path }
+    }
+
+    /// Returns the path of the image table in the pdx, without the `-table-` suffix and the extension.
+    pub const fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Loads the image table.
+    pub fn load(&self) -> Result<BitmapTable, Error> {
+        PLAYDATE.graphics.load_bitmap_table(self.path)
+    }
+}
+
+/// A font, compiled by `pdc` from a `.fnt` file and its glyph table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontAsset {
+    path: &'static str,
+}
+
+impl FontAsset {
+    pub const fn new(path: &'static str) -> Self {
+        Self { path }
+    }
+
+    /// Returns the path of the font in the pdx, without extension.
+    pub const fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Loads the font.
+    pub fn load(&self) -> Result<Font, Error> {
+        PLAYDATE.graphics.load_font(self.path)
+    }
+}
+
+/// A sound, compiled by `pdc` from a `.wav` or `.aif` file, or bundled as is from a `.mp3` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SoundAsset {
+    path: &'static str,
+}
+
+impl SoundAsset {
+    pub const fn new(path: &'static str) -> Self {
+        Self { path }
+    }
+
+    /// Returns the path of the sound in the pdx, without extension.
+    pub const fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Loads the whole sound in memory, for short sound effects.
+    pub fn load(&self) -> Result<AudioSample, Error> {
+        AudioSample::open(self.path)
+    }
+
+    /// Creates a player streaming the sound from the disk, for music and long sounds.
+    pub fn open_player(&self) -> Result<FilePlayer, Error> {
+        FilePlayer::open(self.path)
+    }
+}
+
+/// An atlas, packed by `cargo playdate build` from the images matching a `[[package.metadata.playdate.atlases]]` rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AtlasAsset {
+    path: &'static str,
+}
+
+impl AtlasAsset {
+    pub const fn new(path: &'static str) -> Self {
+        Self { path }
+    }
+
+    /// Returns the path of the atlas in the pdx, without extension.
+    pub const fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Loads the index and the sheets of the atlas.
+    pub fn load(&self) -> Result<Atlas, Error> {
+        Atlas::open(self.path)
+    }
+}
+
+/// Any other file, bundled as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileAsset {
+    path: &'static str,
+}
+
+impl FileAsset {
+    pub const fn new(path: &'static str) -> Self {
+        Self { path }
+    }
+
+    /// Returns the path of the file in the pdx, with its extension.
+    pub const fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Reads the whole file, decompressing it if it was compressed by `cargo playdate build` and the `compress` feature is enabled.
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        #[cfg(feature = "compress")]
+        return fs::compress::read(self.path);
+        #[cfg(not(feature = "compress"))]
+        return fs::read(self.path);
+    }
+
+    /// Opens the file for reading. Compressed files must be read with [`read`](Self::read).
+    pub fn open(&self) -> io::Result<AssetReader> {
+        AssetReader::open(self.path)
+    }
+}
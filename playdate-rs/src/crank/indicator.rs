@@ -0,0 +1,237 @@
+// required for thumbv7em builds
+#[allow(unused_imports)]
+use num_traits::Float;
+
+use crate::{
+    display::{DISPLAY_HEIGHT, DISPLAY_WIDTH},
+    graphics::{Bitmap, BitmapFlip, Color},
+    math::{Rect, Vec2},
+    PLAYDATE,
+};
+
+const BUBBLE_WIDTH: usize = 56;
+const BUBBLE_HEIGHT: usize = 44;
+/// Center of the crank drawing, relative to the top-left corner of the bubble.
+const CRANK_CENTER: Vec2<i32> = Vec2::new(24, 22);
+const CRANK_RADIUS: f32 = 11.0;
+/// Duration of the slide-in animation, in seconds.
+const SLIDE_IN_DURATION: f32 = 0.25;
+/// Crank rotation speed of the animation, in degrees per second.
+const ROTATION_SPEED: f32 = 360.0;
+
+/// A "use the crank" hint, similar to the Lua `playdate.ui.crankIndicator`.
+///
+/// The indicator slides in from the right edge of the screen, next to the crank, and shows an animated crank turning in the given direction. Call [`CrankIndicator::update`] and then [`CrankIndicator::draw`] once per frame, after all other drawing is done.
+pub struct CrankIndicator {
+    bubble: Bitmap,
+    clockwise: bool,
+    elapsed: f32,
+}
+
+impl Default for CrankIndicator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrankIndicator {
+    /// Create a new crank indicator, animating clockwise rotation.
+    pub fn new() -> Self {
+        Self {
+            bubble: render_bubble(),
+            clockwise: true,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Sets the rotation direction of the animated crank.
+    pub fn set_clockwise(&mut self, clockwise: bool) {
+        self.clockwise = clockwise;
+    }
+
+    /// Returns the rotation direction of the animated crank.
+    pub fn is_clockwise(&self) -> bool {
+        self.clockwise
+    }
+
+    /// Restarts the slide-in animation. Call this when the indicator is shown again after being hidden.
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    /// Advances the animation. `delta` is the time in seconds since the last frame.
+    pub fn update(&mut self, delta: f32) {
+        self.elapsed += delta;
+    }
+
+    /// Returns the screen rect covered by the indicator when drawn with the given offset.
+    pub fn get_bounds(&self, offset: Vec2<i32>) -> Rect<i32> {
+        let pos = self.get_position(offset);
+        Rect::new(pos.x, pos.y, BUBBLE_WIDTH as _, BUBBLE_HEIGHT as _)
+    }
+
+    /// Draws the indicator against the right edge of the screen, vertically centered. `offset` moves the indicator relative to that position.
+    pub fn draw(&self, offset: Vec2<i32>) {
+        let pos = self.get_position(offset);
+        PLAYDATE
+            .graphics
+            .draw_bitmap(&self.bubble, pos, BitmapFlip::Unflipped);
+        // Animated crank: a hub, an arm and a knob
+        let direction = if self.clockwise { 1.0 } else { -1.0 };
+        let angle = (self.elapsed * ROTATION_SPEED * direction).to_radians();
+        let center = pos + CRANK_CENTER;
+        let knob = Vec2::new(
+            center.x + (angle.sin() * CRANK_RADIUS).round() as i32,
+            center.y - (angle.cos() * CRANK_RADIUS).round() as i32,
+        );
+        PLAYDATE.graphics.fill_ellipse(
+            Rect::new(center.x - 4, center.y - 4, 9, 9),
+            0.0,
+            0.0,
+            Color::Black,
+        );
+        PLAYDATE.graphics.draw_line(center, knob, 3, Color::Black);
+        PLAYDATE.graphics.fill_ellipse(
+            Rect::new(knob.x - 3, knob.y - 3, 7, 7),
+            0.0,
+            0.0,
+            Color::Black,
+        );
+    }
+
+    fn get_position(&self, offset: Vec2<i32>) -> Vec2<i32> {
+        // Ease-out slide from outside the screen
+        let t = (self.elapsed / SLIDE_IN_DURATION).min(1.0);
+        let slide = ((1.0 - t) * (1.0 - t) * BUBBLE_WIDTH as f32) as i32;
+        Vec2::new(
+            DISPLAY_WIDTH as i32 - BUBBLE_WIDTH as i32 + slide,
+            (DISPLAY_HEIGHT as i32 - BUBBLE_HEIGHT as i32) / 2,
+        ) + offset
+    }
+}
+
+/// Renders the bundled bubble art into a new bitmap with a transparent background.
+fn render_bubble() -> Bitmap {
+    let bitmap = Bitmap::new(
+        size!(BUBBLE_WIDTH as u32, BUBBLE_HEIGHT as u32),
+        Color::Clear,
+    );
+    PLAYDATE.graphics.push_context(&bitmap);
+    for (y, (opaque, black)) in BUBBLE_OPAQUE.iter().zip(BUBBLE_BLACK.iter()).enumerate() {
+        // Draw each horizontal run of same-colored pixels as one rect
+        let mut x = 0;
+        while x < BUBBLE_WIDTH {
+            let bit = |x: usize| 1u64 << (63 - x);
+            if opaque & bit(x) == 0 {
+                x += 1;
+                continue;
+            }
+            let is_black = black & bit(x) != 0;
+            let start = x;
+            while x < BUBBLE_WIDTH && opaque & bit(x) != 0 && (black & bit(x) != 0) == is_black {
+                x += 1;
+            }
+            let color = if is_black { Color::Black } else { Color::White };
+            PLAYDATE.graphics.fill_rect(
+                Rect::new(start as i32, y as i32, (x - start) as i32, 1),
+                color,
+            );
+        }
+    }
+    PLAYDATE.graphics.pop_context();
+    bitmap
+}
+
+// Bubble art, one row per entry, MSB is the leftmost pixel.
+const BUBBLE_OPAQUE: [u64; BUBBLE_HEIGHT] = [
+    0x03ffffffffc00000,
+    0x0ffffffffff00000,
+    0x1ffffffffff80000,
+    0x3ffffffffffc0000,
+    0x7ffffffffffe0000,
+    0x7ffffffffffe0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffffc000,
+    0xfffffffffffff000,
+    0xfffffffffffff800,
+    0xfffffffffffffe00,
+    0xfffffffffffffe00,
+    0xfffffffffffff800,
+    0xfffffffffffff000,
+    0xffffffffffffc000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0xffffffffffff0000,
+    0x7ffffffffffe0000,
+    0x7ffffffffffe0000,
+    0x3ffffffffffc0000,
+    0x1ffffffffff80000,
+    0x0ffffffffff00000,
+    0x03ffffffffc00000,
+];
+const BUBBLE_BLACK: [u64; BUBBLE_HEIGHT] = [
+    0x03ffffffffc00000,
+    0x0ffffffffff00000,
+    0x1c00000000380000,
+    0x30000000000c0000,
+    0x6000000000060000,
+    0x6000000000060000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc00000000001c000,
+    0xc00000000000f000,
+    0xc000000000003800,
+    0xc000000000000e00,
+    0xc000000000000e00,
+    0xc000000000003800,
+    0xc00000000000f000,
+    0xc00000000001c000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0xc000000000030000,
+    0x6000000000060000,
+    0x6000000000060000,
+    0x30000000000c0000,
+    0x1c00000000380000,
+    0x0ffffffffff00000,
+    0x03ffffffffc00000,
+];
@@ -0,0 +1,188 @@
+mod indicator;
+
+// required for thumbv7em builds
+#[allow(unused_imports)]
+use num_traits::Float;
+use spin::Mutex;
+
+use crate::PLAYDATE;
+
+pub use indicator::CrankIndicator;
+
+/// The direction the crank was turned during the last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CrankDirection {
+    /// The crank did not move, or it is docked.
+    #[default]
+    Stopped,
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Docking state transitions detected during the last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CrankEvent {
+    /// The crank was folded back into the unit.
+    Docked,
+    /// The crank was pulled out of the unit.
+    Undocked,
+}
+
+/// Default weight of the previous velocity sample when smoothing the crank velocity.
+pub const DEFAULT_VELOCITY_SMOOTHING: f32 = 0.75;
+
+static STATE: Mutex<CrankState> = Mutex::new(CrankState::new());
+
+struct CrankState {
+    initialized: bool,
+    angle: f32,
+    change: f32,
+    velocity: f32,
+    smoothing: f32,
+    docked: bool,
+    event: Option<CrankEvent>,
+    direction: CrankDirection,
+    last_moving_direction: CrankDirection,
+    direction_changed: bool,
+}
+
+impl CrankState {
+    const fn new() -> Self {
+        Self {
+            initialized: false,
+            angle: 0.0,
+            change: 0.0,
+            velocity: 0.0,
+            smoothing: DEFAULT_VELOCITY_SMOOTHING,
+            docked: true,
+            event: None,
+            direction: CrankDirection::Stopped,
+            last_moving_direction: CrankDirection::Stopped,
+            direction_changed: false,
+        }
+    }
+
+    fn update(&mut self, delta: f32) {
+        let docked = PLAYDATE.system.is_crank_docked();
+        let angle = PLAYDATE.system.get_crank_angle();
+        if !self.initialized {
+            self.initialized = true;
+            self.angle = angle;
+            self.docked = docked;
+        }
+        // Docking edges
+        self.event = match (self.docked, docked) {
+            (true, false) => Some(CrankEvent::Undocked),
+            (false, true) => Some(CrankEvent::Docked),
+            _ => None,
+        };
+        self.docked = docked;
+        // Angle change since last frame, taking the 360 -> 0 wraparound into account.
+        // This is computed from the absolute angle instead of `get_crank_change`, so that
+        // user code calling `get_crank_change` directly is not affected.
+        self.change = if docked {
+            0.0
+        } else {
+            wrap_degrees(angle - self.angle)
+        };
+        self.angle = angle;
+        // Smoothed angular velocity, in degrees per second
+        if delta > 0.0 {
+            let instant = self.change / delta;
+            self.velocity = self.velocity * self.smoothing + instant * (1.0 - self.smoothing);
+        }
+        if docked {
+            self.velocity = 0.0;
+        }
+        // Direction changes
+        self.direction = if self.change > 0.0 {
+            CrankDirection::Clockwise
+        } else if self.change < 0.0 {
+            CrankDirection::CounterClockwise
+        } else {
+            CrankDirection::Stopped
+        };
+        self.direction_changed = false;
+        if self.direction != CrankDirection::Stopped {
+            self.direction_changed = self.last_moving_direction != CrankDirection::Stopped
+                && self.last_moving_direction != self.direction;
+            self.last_moving_direction = self.direction;
+        }
+    }
+}
+
+/// Wraps an angle difference into the range (-180, 180].
+fn wrap_degrees(mut delta: f32) -> f32 {
+    while delta > 180.0 {
+        delta -= 360.0;
+    }
+    while delta <= -180.0 {
+        delta += 360.0;
+    }
+    delta
+}
+
+/// Sample the crank once per frame. Called by the runtime before `App::update`.
+pub(crate) fn update(delta: f32) {
+    STATE.lock().update(delta);
+}
+
+/// Returns the number of "ticks" the crank has turned through during the last frame, where a full revolution is divided into `ticks_per_revolution` ticks. Ticks can be positive or negative, depending upon the direction of rotation.
+///
+/// For example, if `ticks_per_revolution` is 6, a tick is reported every time the crank passes a 60° boundary (0°, 60°, 120°, ...). This is equivalent to the Lua `playdate.getCrankTicks()`.
+pub fn get_crank_ticks(ticks_per_revolution: u32) -> i32 {
+    if ticks_per_revolution == 0 {
+        return 0;
+    }
+    let state = STATE.lock();
+    let degrees_per_tick = 360.0 / ticks_per_revolution as f32;
+    // `angle - change` is intentionally left unwrapped, so that crossing 0° counts as a tick boundary.
+    let this_tick = (state.angle / degrees_per_tick).ceil() as i32;
+    let last_tick = ((state.angle - state.change) / degrees_per_tick).ceil() as i32;
+    this_tick - last_tick
+}
+
+/// Returns the crank angle sampled at the beginning of this frame, in the range 0-360. Zero is pointing up, and the value increases as the crank moves clockwise.
+pub fn get_angle() -> f32 {
+    STATE.lock().angle
+}
+
+/// Returns the angle change of the crank during the last frame, in degrees. Negative values are anti-clockwise. Unlike [`PlaydateSystem::get_crank_change`](crate::system::PlaydateSystem::get_crank_change), this can be called any number of times per frame.
+pub fn get_change() -> f32 {
+    STATE.lock().change
+}
+
+/// Returns the smoothed angular velocity of the crank, in degrees per second. Negative values are anti-clockwise.
+pub fn get_velocity() -> f32 {
+    STATE.lock().velocity
+}
+
+/// Sets how much the previous velocity contributes to the smoothed velocity, between 0.0 (no smoothing) and 1.0 (exclusive). The default is [`DEFAULT_VELOCITY_SMOOTHING`].
+pub fn set_velocity_smoothing(smoothing: f32) {
+    debug_assert!(
+        (0.0..1.0).contains(&smoothing),
+        "invalid velocity smoothing: {}",
+        smoothing
+    );
+    STATE.lock().smoothing = smoothing.clamp(0.0, 0.99);
+}
+
+/// Returns the direction the crank was turned during the last frame.
+pub fn get_direction() -> CrankDirection {
+    STATE.lock().direction
+}
+
+/// Returns `true` if the crank started turning in the opposite direction during the last frame. Frames where the crank does not move are ignored.
+pub fn direction_changed() -> bool {
+    STATE.lock().direction_changed
+}
+
+/// Returns `true` if the crank is docked, as sampled at the beginning of this frame.
+pub fn is_docked() -> bool {
+    STATE.lock().docked
+}
+
+/// Returns the docking event that happened during the last frame, if any.
+pub fn get_event() -> Option<CrankEvent> {
+    STATE.lock().event
+}
@@ -0,0 +1,276 @@
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    fmt::{self, Write as _},
+    panic::Location,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use spin::Mutex;
+
+use crate::{
+    display::{DISPLAY_HEIGHT, DISPLAY_WIDTH},
+    fs::{File, FileOptions, Path, PathBuf, Write},
+    graphics::{BitmapDrawMode, Color},
+    math::{Rect, Vec2},
+    memory::MemoryStats,
+    PLAYDATE,
+};
+
+/// Default path of the crash log, in the game's data folder.
+pub const DEFAULT_LOG_PATH: &str = "crash.log";
+/// Number of characters per line of the crash screen. The system font is about 8 pixels wide.
+const SCREEN_LINE_LENGTH: usize = 46;
+const SCREEN_LINE_HEIGHT: i32 = 18;
+const SCREEN_MARGIN: i32 = 8;
+
+/// Everything known about a panic, written to the crash log and passed to the hook set with [`set_hook`].
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    /// The panic message.
+    pub message: String,
+    /// The source location of the panic, as `file:line:column`.
+    pub location: Option<String>,
+    /// The frame number at the time of the panic, see [`PlaydateSystem::get_frame_number`](crate::system::PlaydateSystem::get_frame_number).
+    pub frame: u32,
+    /// The most recent lines logged with the `log` macros, oldest first.
+    pub recent_logs: Vec<String>,
+    /// Heap statistics at the time of the panic.
+    pub memory: MemoryStats,
+}
+
+impl CrashReport {
+    fn new(message: String, location: Option<&Location>) -> Self {
+        Self {
+            message,
+            location: location.map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            frame: PLAYDATE.system.get_frame_number(),
+            // The logger may be locked if the panic happened while logging
+            recent_logs: crate::logger::try_get_history().unwrap_or_default(),
+            memory: crate::memory::stats(),
+        }
+    }
+}
+
+impl fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "panicked: {}", self.message)?;
+        if let Some(location) = &self.location {
+            writeln!(f, "location: {}", location)?;
+        }
+        writeln!(f, "frame: {}", self.frame)?;
+        let (s, ms) = PLAYDATE.system.get_seconds_since_epoch();
+        let time = PLAYDATE.system.convert_epoch_to_date_time(s as _);
+        writeln!(
+            f,
+            "time: {}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+            time.year, time.month, time.day, time.hour, time.minute, time.second, ms
+        )?;
+        writeln!(
+            f,
+            "memory: {} bytes in {} allocations, {} bytes peak, {} failed allocations",
+            self.memory.live_bytes,
+            self.memory.live_allocations,
+            self.memory.peak_bytes,
+            self.memory.failed_allocations
+        )?;
+        if !self.recent_logs.is_empty() {
+            writeln!(f, "recent logs:")?;
+            for line in &self.recent_logs {
+                writeln!(f, "  {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Callback invoked with the crash report, after the crash log is written and before the crash screen is drawn.
+pub type CrashHook = Box<dyn Fn(&CrashReport) + Send>;
+
+struct CrashConfig {
+    write_log: bool,
+    /// `None` for [`DEFAULT_LOG_PATH`]
+    log_path: Option<PathBuf>,
+    show_crash_screen: bool,
+    hook: Option<CrashHook>,
+}
+
+impl CrashConfig {
+    fn get_log_path(&self) -> Option<PathBuf> {
+        if !self.write_log {
+            return None;
+        }
+        Some(
+            self.log_path
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_LOG_PATH)),
+        )
+    }
+}
+
+static CONFIG: Mutex<CrashConfig> = Mutex::new(CrashConfig {
+    write_log: true,
+    log_path: None,
+    show_crash_screen: true,
+    hook: None,
+});
+
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// Sets the file of the data folder the crash reports are appended to. Defaults to [`DEFAULT_LOG_PATH`].
+pub fn set_log_path(path: impl AsRef<Path>) {
+    CONFIG.lock().log_path = Some(path.as_ref().to_path_buf());
+}
+
+/// Sets whether crash reports are appended to the crash log. Enabled by default.
+pub fn set_write_log(write: bool) {
+    CONFIG.lock().write_log = write;
+}
+
+/// Sets whether a crash screen with the panic message is drawn before `system.error` is called. Enabled by default.
+pub fn set_show_crash_screen(show: bool) {
+    CONFIG.lock().show_crash_screen = show;
+}
+
+/// Sets a callback invoked when the game panics, e.g. to save the game state or record analytics. The callback must not panic.
+pub fn set_hook(hook: impl Fn(&CrashReport) + Send + 'static) {
+    CONFIG.lock().hook = Some(Box::new(hook));
+}
+
+/// Removes the callback set with [`set_hook`].
+pub fn take_hook() -> Option<CrashHook> {
+    CONFIG.lock().hook.take()
+}
+
+/// Reads the crash log written by previous runs, e.g. to upload it or show it to testers. Returns `None` if the game never crashed.
+pub fn read_log() -> Option<String> {
+    let path = CONFIG.lock().get_log_path()?;
+    crate::fs::read_to_string(path).ok()
+}
+
+/// Deletes the crash log written by previous runs.
+pub fn clear_log() {
+    if let Some(path) = CONFIG.lock().get_log_path() {
+        let _ = crate::fs::remove_file(path);
+    }
+}
+
+fn append_to_log(path: &Path, report: &str) -> crate::io::Result<()> {
+    let mut file = File::open(path, FileOptions::kFileAppend)?;
+    file.write_all(report.as_bytes())?;
+    file.write_all(b"\n")?;
+    file.flush()
+}
+
+/// Splits `text` into lines of at most `SCREEN_LINE_LENGTH` characters, breaking at spaces when possible.
+fn wrap(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            if !line.is_empty()
+                && line.chars().count() + 1 + word.chars().count() > SCREEN_LINE_LENGTH
+            {
+                lines.push(core::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+            while line.chars().count() > SCREEN_LINE_LENGTH {
+                let split = line.char_indices().nth(SCREEN_LINE_LENGTH).unwrap().0;
+                let rest = line.split_off(split);
+                lines.push(core::mem::replace(&mut line, rest));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn draw_crash_screen(report: &CrashReport, log_path: Option<&Path>) {
+    let graphics = &PLAYDATE.graphics;
+    let (width, height) = (DISPLAY_WIDTH as i32, DISPLAY_HEIGHT as i32);
+    graphics.clear_clip_rect();
+    graphics.set_draw_mode(BitmapDrawMode::Copy);
+    graphics.clear(Color::White);
+    graphics.fill_rect(Rect::new(0, 0, width, SCREEN_LINE_HEIGHT + 4), Color::Black);
+    graphics.set_draw_mode(BitmapDrawMode::FillWhite);
+    graphics.draw_text("The game crashed", Vec2::new(SCREEN_MARGIN, 2));
+    graphics.set_draw_mode(BitmapDrawMode::Copy);
+    let mut text = report.message.clone();
+    if let Some(location) = &report.location {
+        let _ = write!(text, "\n\nat {}", location);
+    }
+    let _ = write!(text, "\nframe {}", report.frame);
+    if let Some(path) = log_path {
+        let _ = write!(text, "\n\nDetails were written to {}", path);
+    }
+    let mut y = SCREEN_LINE_HEIGHT + 4 + SCREEN_MARGIN;
+    for line in wrap(&text) {
+        if y + SCREEN_LINE_HEIGHT > height {
+            break;
+        }
+        graphics.draw_text(line, Vec2::new(SCREEN_MARGIN, y));
+        y += SCREEN_LINE_HEIGHT;
+    }
+    graphics.display();
+}
+
+/// Runs the panic pipeline: writes the crash log, calls the hook, draws the crash screen, and stops the game with `system.error`.
+pub(crate) fn handle_panic(message: impl fmt::Display, location: Option<&Location>) {
+    // A panic inside the pipeline, e.g. in the hook or when the heap is exhausted, skips straight to `system.error` without allocating
+    if PANICKING.swap(true, Ordering::SeqCst) {
+        PLAYDATE.system.error_static(c"panicked while panicking");
+        return;
+    }
+    let report = CrashReport::new(message.to_string(), location);
+    let (log_path, show_crash_screen) = match CONFIG.try_lock() {
+        Some(config) => (config.get_log_path(), config.show_crash_screen),
+        None => (Some(PathBuf::from(DEFAULT_LOG_PATH)), true),
+    };
+    let log_path = log_path.filter(|path| {
+        let written = append_to_log(path, &report.to_string());
+        if let Err(e) = &written {
+            PLAYDATE
+                .system
+                .log_to_console(format!("Failed to write {}: {:?}", path, e));
+        }
+        written.is_ok()
+    });
+    // The hook is called without holding the lock, so it can use the setters of this module
+    let hook = CONFIG.try_lock().and_then(|mut config| config.hook.take());
+    if let Some(hook) = hook {
+        hook(&report);
+        if let Some(mut config) = CONFIG.try_lock() {
+            config.hook.get_or_insert(hook);
+        }
+    }
+    let error = match &report.location {
+        Some(location) => format!("panicked at {}:\n{}", location, report.message),
+        None => format!("panicked: {}", report.message),
+    };
+    if show_crash_screen {
+        draw_crash_screen(&report, log_path.as_deref());
+    }
+    PLAYDATE.system.error(error);
+}
+
+/// Sends the panics of simulator builds through the same pipeline as device builds.
+#[cfg(not(all(target_arch = "arm", target_os = "none")))]
+pub(crate) fn install_std_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(s) => s.to_string(),
+            None => match info.payload().downcast_ref::<String>() {
+                Some(s) => s.clone(),
+                None => "Box<dyn Any>".to_string(),
+            },
+        };
+        handle_panic(message, info.location());
+    }));
+}
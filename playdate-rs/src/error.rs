@@ -9,9 +9,15 @@ pub enum Error {
     FailedToSetBitmapMask,
     FailedToLoadBitMapFromFile(String),
     FailedToLoadBitMapFromBitMapTable(String),
+    InvalidAtlas(String),
+    AtlasImageNotFound(String),
     // IO Error
     IO(io::Error),
     FileNotExists(String),
+    // Storage
+    Serialization(String),
+    CorruptedSaveFile(String),
+    UnsupportedSchemaVersion(u32),
     // Lua
     Lua(String),
     // All other unknown errors
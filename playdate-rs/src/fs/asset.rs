@@ -0,0 +1,182 @@
+use alloc::{vec, vec::Vec};
+use core::task::Poll;
+
+use no_std_io::io::{self, Read};
+
+use crate::PLAYDATE;
+
+use super::{File, FileOptions, Path, PathBuf};
+
+/// Default number of bytes read per [`ChunkedReader::poll`].
+pub const DEFAULT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Loads files bundled in the game's pdx.
+///
+/// The size of the file is queried with `stat` before reading, so the data is read into a single allocation of the right size, and truncated reads are reported as errors instead of silently returning partial data.
+///
+/// ```ignore
+/// let level = AssetReader::open("levels/1.bin")?.read_all()?;
+/// ```
+pub struct AssetReader {
+    path: PathBuf,
+    file: File,
+    len: usize,
+}
+
+impl AssetReader {
+    /// Opens a file in the game's pdx.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open_with_options(path, FileOptions::kFileRead)
+    }
+
+    /// Opens a file with the given options, e.g. `kFileRead | kFileReadData` to let files in the data folder override the pdx.
+    pub fn open_with_options(path: impl AsRef<Path>, options: FileOptions) -> io::Result<Self> {
+        let path = path.as_ref();
+        let stat = PLAYDATE.file.stat(path)?;
+        if stat.isdir != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot read a directory",
+            ));
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: File::open(path, options)?,
+            len: stat.size as usize,
+        })
+    }
+
+    /// Reads a whole file in the game's pdx.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+        Self::open(path)?.read_all()
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the size of the file reported by `stat`, in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reads the whole file, checking that its size matches the size reported by `stat`.
+    pub fn read_all(mut self) -> io::Result<Vec<u8>> {
+        let mut data = vec![0; self.len];
+        self.file.read_exact(&mut data)?;
+        check_eof(&mut self.file)?;
+        Ok(data)
+    }
+
+    /// Turns the reader into a [`ChunkedReader`] that reads the file over several frames.
+    pub fn into_chunked(self) -> ChunkedReader {
+        ChunkedReader {
+            file: self.file,
+            data: Vec::with_capacity(self.len),
+            len: self.len,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            done: false,
+        }
+    }
+}
+
+/// Reads a large file in chunks spread over several frames, so that loading it does not stall `App::update`.
+///
+/// Call [`ChunkedReader::poll`] once per frame until it returns [`Poll::Ready`].
+///
+/// ```ignore
+/// // in init()
+/// self.loader = Some(AssetReader::open("music.pda")?.into_chunked());
+///
+/// // in update()
+/// if let Some(loader) = &mut self.loader {
+///     if let Poll::Ready(data) = loader.poll() {
+///         self.music = Some(data?);
+///         self.loader = None;
+///     } else {
+///         draw_progress_bar(loader.progress());
+///     }
+/// }
+/// ```
+pub struct ChunkedReader {
+    file: File,
+    data: Vec<u8>,
+    len: usize,
+    chunk_size: usize,
+    done: bool,
+}
+
+impl ChunkedReader {
+    /// Opens a file in the game's pdx for chunked reading.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(AssetReader::open(path)?.into_chunked())
+    }
+
+    /// Sets how many bytes are read per call to [`ChunkedReader::poll`]. Defaults to [`DEFAULT_CHUNK_SIZE`].
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size.max(1);
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn bytes_read(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns the size of the file, in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the loading progress, between 0.0 and 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.len == 0 {
+            1.0
+        } else {
+            self.data.len() as f32 / self.len as f32
+        }
+    }
+
+    /// Reads the next chunk. Returns [`Poll::Ready`] with the whole file once the last chunk is read, and [`Poll::Pending`] before that.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called again after returning [`Poll::Ready`].
+    pub fn poll(&mut self) -> Poll<io::Result<Vec<u8>>> {
+        assert!(!self.done, "ChunkedReader polled after completion");
+        let start = self.data.len();
+        let end = (start + self.chunk_size).min(self.len);
+        self.data.resize(end, 0);
+        if let Err(e) = self.file.read_exact(&mut self.data[start..end]) {
+            self.done = true;
+            return Poll::Ready(Err(e));
+        }
+        if end < self.len {
+            return Poll::Pending;
+        }
+        self.done = true;
+        Poll::Ready(check_eof(&mut self.file).map(|_| core::mem::take(&mut self.data)))
+    }
+}
+
+/// Checks that nothing is left to read, i.e. the file did not grow after `stat`.
+fn check_eof(file: &mut File) -> io::Result<()> {
+    let mut extra = [0u8; 1];
+    if file.read(&mut extra)? != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "file is larger than reported by stat",
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,308 @@
+use alloc::{string::String, vec, vec::Vec};
+use core::str;
+
+use no_std_io::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+/// Default buffer size of [`BufReader`] and [`BufWriter`]. Large enough to amortize the cost of a filesystem call, small enough to keep a few readers open at once.
+pub const DEFAULT_BUF_SIZE: usize = 8 * 1024;
+
+/// Adds buffering to a reader, similar to [`std::io::BufReader`](https://doc.rust-lang.org/std/io/struct.BufReader.html).
+///
+/// Every [`File::read`](super::File) is a call into the Playdate filesystem, so reading a file a few bytes at a time is slow on device. `BufReader` reads large blocks at once and serves small reads from memory.
+pub struct BufReader<R> {
+    inner: R,
+    buf: Vec<u8>,
+    pos: usize,
+    filled: usize,
+}
+
+impl<R: Read> BufReader<R> {
+    /// Creates a reader with a buffer of [`DEFAULT_BUF_SIZE`] bytes.
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Creates a reader with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            inner,
+            buf: vec![0; capacity.max(1)],
+            pos: 0,
+            filled: 0,
+        }
+    }
+}
+
+impl<R> BufReader<R> {
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader. Reading from it directly skips the buffered data.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the data that is buffered but not consumed yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    /// Returns the size of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Unwraps the reader. Buffered data is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn discard_buffer(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+impl<R: Read> Read for BufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Bypass the buffer for large reads when it is empty
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            self.discard_buffer();
+            return self.inner.read(buf);
+        }
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: Read> BufRead for BufReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.filled {
+            self.filled = self.inner.read(&mut self.buf)?;
+            self.pos = 0;
+        }
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+impl<R: Read + Seek> Seek for BufReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            // The inner reader is ahead of our position by the buffered data
+            SeekFrom::Current(offset) => {
+                let remaining = (self.filled - self.pos) as i64;
+                self.inner.seek(SeekFrom::Current(offset - remaining))?
+            }
+            pos => self.inner.seek(pos)?,
+        };
+        self.discard_buffer();
+        Ok(pos)
+    }
+}
+
+/// Adds buffering to a writer, similar to [`std::io::BufWriter`](https://doc.rust-lang.org/std/io/struct.BufWriter.html).
+///
+/// Small writes are collected in memory and written to the underlying writer in large blocks. The buffer is flushed when it is full, when [`Write::flush`] is called, and when the writer is dropped. Errors that happen while flushing on drop are ignored, so call [`Write::flush`] explicitly to handle them.
+pub struct BufWriter<W: Write> {
+    inner: W,
+    buf: Vec<u8>,
+    capacity: usize,
+}
+
+impl<W: Write> BufWriter<W> {
+    /// Creates a writer with a buffer of [`DEFAULT_BUF_SIZE`] bytes.
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Creates a writer with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            inner,
+            buf: Vec::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns the data that is buffered but not written yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns the size of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Flushes the buffer and unwraps the writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush_buf()?;
+        // `self` implements `Drop`, so the writer has to be moved out manually
+        let this = core::mem::ManuallyDrop::new(self);
+        // SAFETY: `this` is never used or dropped again, and the buffer is dropped here
+        unsafe {
+            drop(core::ptr::read(&this.buf));
+            Ok(core::ptr::read(&this.inner))
+        }
+    }
+
+    fn flush_buf(&mut self) -> io::Result<()> {
+        let mut written = 0;
+        while written < self.buf.len() {
+            match self.inner.write(&self.buf[written..]) {
+                Ok(0) => {
+                    self.buf.drain(..written);
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write the buffered data",
+                    ));
+                }
+                Ok(n) => written += n,
+                Err(e) => {
+                    self.buf.drain(..written);
+                    return Err(e);
+                }
+            }
+        }
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for BufWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + buf.len() > self.capacity {
+            self.flush_buf()?;
+        }
+        if buf.len() >= self.capacity {
+            // Too large to be buffered
+            self.inner.write(buf)
+        } else {
+            self.buf.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buf()?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + Seek> Seek for BufWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.flush_buf()?;
+        self.inner.seek(pos)
+    }
+}
+
+impl<W: Write> Drop for BufWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
+    }
+}
+
+/// Reads a text file line by line, without allocating a new string for every line.
+///
+/// Lines are split on `\n`, and a trailing `\r` is removed. The returned line borrows an internal buffer that is reused for the next line.
+///
+/// ```ignore
+/// let file = File::open("levels/1.txt", FileOptions::kFileRead)?;
+/// let mut lines = LineReader::new(file);
+/// while let Some(line) = lines.next_line()? {
+///     // ...
+/// }
+/// ```
+pub struct LineReader<R> {
+    reader: BufReader<R>,
+    line: Vec<u8>,
+    line_number: usize,
+}
+
+impl<R: Read> LineReader<R> {
+    /// Creates a line reader with a buffer of [`DEFAULT_BUF_SIZE`] bytes.
+    pub fn new(inner: R) -> Self {
+        Self::from_buf_reader(BufReader::new(inner))
+    }
+
+    /// Creates a line reader from an existing buffered reader.
+    pub fn from_buf_reader(reader: BufReader<R>) -> Self {
+        Self {
+            reader,
+            line: Vec::new(),
+            line_number: 0,
+        }
+    }
+
+    /// Returns the next line, or `None` at the end of the file. Fails if the line is not valid UTF-8.
+    pub fn next_line(&mut self) -> io::Result<Option<&str>> {
+        let Some(line) = self.next_line_bytes()? else {
+            return Ok(None);
+        };
+        str::from_utf8(line)
+            .map(Some)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "line is not valid UTF-8"))
+    }
+
+    /// Returns the next line as raw bytes, or `None` at the end of the file.
+    pub fn next_line_bytes(&mut self) -> io::Result<Option<&[u8]>> {
+        self.line.clear();
+        let mut eof = true;
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                break;
+            }
+            eof = false;
+            if let Some(i) = available.iter().position(|b| *b == b'\n') {
+                self.line.extend_from_slice(&available[..i]);
+                self.reader.consume(i + 1);
+                break;
+            }
+            let len = available.len();
+            self.line.extend_from_slice(available);
+            self.reader.consume(len);
+        }
+        if eof {
+            return Ok(None);
+        }
+        if self.line.last() == Some(&b'\r') {
+            self.line.pop();
+        }
+        self.line_number += 1;
+        Ok(Some(&self.line))
+    }
+
+    /// Returns the 1-based number of the last line returned, or 0 if no line was read yet.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// Reads all remaining lines into owned strings.
+    pub fn collect_lines(&mut self) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        while let Some(line) = self.next_line()? {
+            lines.push(String::from(line));
+        }
+        Ok(lines)
+    }
+
+    /// Unwraps the underlying buffered reader.
+    pub fn into_inner(self) -> BufReader<R> {
+        self.reader
+    }
+}
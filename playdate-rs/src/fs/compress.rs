@@ -0,0 +1,236 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use miniz_oxide::{
+    deflate::{
+        core::{create_comp_flags_from_zip_params, CompressorOxide},
+        stream::deflate,
+    },
+    inflate::stream::{inflate, InflateState},
+    DataFormat, MZError, MZFlush, MZStatus, MZ_DEFAULT_WINDOW_BITS,
+};
+use no_std_io::io::{self, Read, Write};
+
+use super::{File, FileOptions, Path, PathBuf};
+
+/// Default compression level, between 0 (no compression) and 10 (best compression).
+pub const DEFAULT_LEVEL: u8 = 6;
+/// Extension appended to the file name of assets compressed by `cargo playdate build`.
+pub const EXTENSION: &str = "z";
+/// Size of the intermediate buffers of [`CompressedReader`] and [`CompressedWriter`].
+const CHUNK_SIZE: usize = 4 * 1024;
+
+/// Compresses everything written to it in the zlib format, and writes the result to the underlying writer.
+///
+/// Call [`CompressedWriter::finish`] once all data is written. Dropping the writer finishes the stream too, but ignores errors.
+///
+/// ```ignore
+/// let file = File::open("level.z", FileOptions::kFileWrite)?;
+/// let mut writer = CompressedWriter::new(BufWriter::new(file));
+/// writer.write_all(&level_data)?;
+/// writer.finish()?;
+/// ```
+pub struct CompressedWriter<W: Write> {
+    inner: Option<W>,
+    compressor: Box<CompressorOxide>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// Creates a writer with the [`DEFAULT_LEVEL`] compression level.
+    pub fn new(inner: W) -> Self {
+        Self::with_level(inner, DEFAULT_LEVEL)
+    }
+
+    /// Creates a writer with the given compression level, between 0 (no compression) and 10 (best compression).
+    pub fn with_level(inner: W, level: u8) -> Self {
+        let flags =
+            create_comp_flags_from_zip_params(level.min(10) as _, MZ_DEFAULT_WINDOW_BITS, 0);
+        Self {
+            inner: Some(inner),
+            compressor: Box::new(CompressorOxide::new(flags)),
+            buf: vec![0; CHUNK_SIZE],
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    /// Writes the end of the compressed stream, flushes and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.finish_stream()?;
+        Ok(self.inner.take().unwrap())
+    }
+
+    fn finish_stream(&mut self) -> io::Result<()> {
+        while !self.deflate(&[], MZFlush::Finish)?.2 {}
+        self.inner.as_mut().unwrap().flush()
+    }
+
+    /// Compresses `input` and writes the output. Returns the number of bytes consumed and produced, and whether the stream ended.
+    fn deflate(&mut self, input: &[u8], flush: MZFlush) -> io::Result<(usize, usize, bool)> {
+        let result = deflate(&mut self.compressor, input, &mut self.buf, flush);
+        let inner = self.inner.as_mut().unwrap();
+        inner.write_all(&self.buf[..result.bytes_written])?;
+        match result.status {
+            Ok(MZStatus::StreamEnd) => Ok((result.bytes_consumed, result.bytes_written, true)),
+            Ok(_) => Ok((result.bytes_consumed, result.bytes_written, false)),
+            // No progress possible, e.g. nothing left to flush
+            Err(MZError::Buf) => Ok((result.bytes_consumed, result.bytes_written, true)),
+            Err(_) => Err(io::Error::new(io::ErrorKind::Other, "compression failed")),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut consumed = 0;
+        while consumed < buf.len() {
+            consumed += self.deflate(&buf[consumed..], MZFlush::None)?.0;
+        }
+        Ok(buf.len())
+    }
+
+    /// Writes all pending compressed data to the underlying writer. Flushing often hurts the compression ratio.
+    fn flush(&mut self) -> io::Result<()> {
+        // The output buffer is only filled completely if more data is pending
+        while self.deflate(&[], MZFlush::Sync)?.1 == self.buf.len() {}
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl<W: Write> Drop for CompressedWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.finish_stream();
+        }
+    }
+}
+
+/// Decompresses zlib data read from the underlying reader.
+///
+/// ```ignore
+/// let file = File::open("level.z", FileOptions::kFileRead)?;
+/// let mut reader = CompressedReader::new(file);
+/// let mut level_data = Vec::new();
+/// reader.read_to_end(&mut level_data)?;
+/// ```
+pub struct CompressedReader<R: Read> {
+    inner: R,
+    state: Box<InflateState>,
+    buf: Vec<u8>,
+    pos: usize,
+    filled: usize,
+    eof: bool,
+    done: bool,
+}
+
+impl<R: Read> CompressedReader<R> {
+    /// Creates a reader decompressing the zlib data of `inner`.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: InflateState::new_boxed(DataFormat::Zlib),
+            buf: vec![0; CHUNK_SIZE],
+            pos: 0,
+            filled: 0,
+            eof: false,
+            done: false,
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Unwraps the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CompressedReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.done || out.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.pos == self.filled && !self.eof {
+                self.filled = self.inner.read(&mut self.buf)?;
+                self.pos = 0;
+                self.eof = self.filled == 0;
+            }
+            let flush = if self.eof {
+                MZFlush::Finish
+            } else {
+                MZFlush::None
+            };
+            let result = inflate(
+                &mut self.state,
+                &self.buf[self.pos..self.filled],
+                out,
+                flush,
+            );
+            self.pos += result.bytes_consumed;
+            match result.status {
+                Ok(MZStatus::StreamEnd) => {
+                    self.done = true;
+                    return Ok(result.bytes_written);
+                }
+                Ok(_) | Err(MZError::Buf) if result.bytes_written > 0 => {
+                    return Ok(result.bytes_written)
+                }
+                // More input is needed
+                Ok(_) | Err(MZError::Buf) if !self.eof => {}
+                Ok(_) | Err(MZError::Buf) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "truncated compressed data",
+                    ))
+                }
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "corrupted compressed data",
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// Compresses a buffer in the zlib format.
+pub fn compress(data: &[u8], level: u8) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec_zlib(data, level.min(10))
+}
+
+/// Decompresses a buffer of zlib data.
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_zlib(data)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "corrupted compressed data"))
+}
+
+/// Reads an asset that may have been compressed by `cargo playdate build`.
+///
+/// If `<path>.z` exists, it is decompressed. Otherwise the file at `path` is read as-is. The game's data folder is searched first, then the pdx.
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let path = path.as_ref();
+    let compressed = PathBuf::from(alloc::format!(
+        "{}.{}",
+        path.trim_trailing_slash(),
+        EXTENSION
+    ));
+    if super::exists(&compressed) {
+        let file = File::open(
+            &compressed,
+            FileOptions::kFileRead | FileOptions::kFileReadData,
+        )?;
+        let mut data = Vec::new();
+        CompressedReader::new(file).read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        super::read(path)
+    }
+}
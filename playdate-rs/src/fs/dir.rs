@@ -0,0 +1,169 @@
+use alloc::{vec, vec::Vec};
+
+use no_std_io::io;
+
+use crate::{system::DateTime, PLAYDATE};
+
+use super::{FileStat, Path, PathBuf};
+
+/// Metadata of a file or directory, returned by [`metadata`](super::metadata).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    is_dir: bool,
+    len: u64,
+    modified: DateTime,
+}
+
+impl Metadata {
+    /// Returns `true` if this is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Returns `true` if this is a regular file.
+    pub fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+
+    /// Returns the size of the file, in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the last modification time.
+    pub fn modified(&self) -> DateTime {
+        self.modified
+    }
+}
+
+impl From<FileStat> for Metadata {
+    fn from(stat: FileStat) -> Self {
+        let modified = DateTime {
+            year: stat.m_year as _,
+            month: stat.m_month as _,
+            day: stat.m_day as _,
+            weekday: 0,
+            hour: stat.m_hour as _,
+            minute: stat.m_minute as _,
+            second: stat.m_second as _,
+        };
+        // `stat` does not report the weekday, round-trip through the epoch to fill it in
+        let epoch = PLAYDATE.system.convert_date_time_to_epoch(modified);
+        Self {
+            is_dir: stat.isdir != 0,
+            len: stat.size as _,
+            modified: PLAYDATE.system.convert_epoch_to_date_time(epoch),
+        }
+    }
+}
+
+/// An entry of a directory, returned by [`read_dir`](super::read_dir) and [`walk_dir`](super::walk_dir).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    path: PathBuf,
+    is_dir: bool,
+}
+
+impl DirEntry {
+    /// Returns the full path of the entry, without trailing slash.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the name of the entry, without trailing slash.
+    pub fn file_name(&self) -> &str {
+        self.path.file_name().unwrap_or_default()
+    }
+
+    /// Returns `true` if the entry is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Returns `true` if the entry is a regular file.
+    pub fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+
+    /// Queries the metadata of the entry.
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        super::metadata(&self.path)
+    }
+}
+
+/// Iterator over the entries of a directory, returned by [`read_dir`](super::read_dir).
+#[derive(Debug)]
+pub struct ReadDir {
+    entries: vec::IntoIter<DirEntry>,
+}
+
+impl ReadDir {
+    pub(super) fn new(path: &Path) -> io::Result<Self> {
+        let mut entries = Vec::new();
+        PLAYDATE.file.list_files(path, true, |name| {
+            // `listfiles` marks directories with a trailing slash
+            let name = Path::new(name);
+            entries.push(DirEntry {
+                path: path.join(name.trim_trailing_slash()),
+                is_dir: name.has_trailing_slash(),
+            });
+        })?;
+        Ok(Self {
+            entries: entries.into_iter(),
+        })
+    }
+}
+
+impl Iterator for ReadDir {
+    type Item = DirEntry;
+
+    fn next(&mut self) -> Option<DirEntry> {
+        self.entries.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+/// Recursive iterator over a directory tree, returned by [`walk_dir`](super::walk_dir).
+///
+/// Entries are yielded depth-first: each directory is yielded before its content. Subdirectories are only listed when the iterator reaches them.
+#[derive(Debug)]
+pub struct WalkDir {
+    stack: Vec<ReadDir>,
+}
+
+impl WalkDir {
+    pub(super) fn new(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            stack: vec![ReadDir::new(path)?],
+        })
+    }
+}
+
+impl Iterator for WalkDir {
+    type Item = io::Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let dir = self.stack.last_mut()?;
+            let Some(entry) = dir.next() else {
+                self.stack.pop();
+                continue;
+            };
+            if entry.is_dir() {
+                match ReadDir::new(entry.path()) {
+                    Ok(dir) => self.stack.push(dir),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            return Some(Ok(entry));
+        }
+    }
+}
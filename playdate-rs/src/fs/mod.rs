@@ -1,12 +1,24 @@
+mod asset;
+mod buffered;
+#[cfg(feature = "compress")]
+pub mod compress;
+mod dir;
+mod path;
+
 use alloc::ffi::CString;
 use alloc::string::String;
 use alloc::vec::Vec;
 
 pub use sys::{FileOptions, FileStat, SEEK_CUR, SEEK_END, SEEK_SET};
 
+pub use asset::{AssetReader, ChunkedReader, DEFAULT_CHUNK_SIZE};
+pub use buffered::{BufReader, BufWriter, LineReader, DEFAULT_BUF_SIZE};
+pub use dir::{DirEntry, Metadata, ReadDir, WalkDir};
+pub use path::{Path, PathBuf, SEPARATOR};
+
 use no_std_io::io::{self};
 
-pub use no_std_io::io::{Read, Seek, Write};
+pub use no_std_io::io::{BufRead, Read, Seek, SeekFrom, Write};
 
 pub struct PlaydateFileSystem {
     handle: *const sys::playdate_file,
@@ -39,7 +51,7 @@ impl PlaydateFileSystem {
         mut callback: impl FnMut(&str),
     ) -> Result<(), io::Error> {
         let c_string = CString::new(path.as_ref()).unwrap();
-        extern "C" fn callback_wrapper(filename: *const i8, callback: *mut c_void) {
+        extern "C" fn callback_wrapper(filename: *const c_char, callback: *mut c_void) {
             let callback = callback as *mut *mut dyn FnMut(&str);
             let callback = unsafe { &mut **callback };
             let filename = unsafe { ::core::ffi::CStr::from_ptr(filename) };
@@ -55,7 +67,7 @@ impl PlaydateFileSystem {
                 show_hidden as i32,
             )
         };
-        if result != 0 {
+        if result == 0 {
             Ok(())
         } else {
             Err(self.get_error().unwrap())
@@ -67,7 +79,7 @@ impl PlaydateFileSystem {
         let c_string = CString::new(path.as_ref()).unwrap();
         let mut stat = FileStat::default();
         let result = unsafe { (*self.handle).stat.unwrap()(c_string.as_ptr(), &mut stat) };
-        if result != 0 {
+        if result == 0 {
             Ok(stat)
         } else {
             Err(self.get_error().unwrap())
@@ -78,7 +90,7 @@ impl PlaydateFileSystem {
     pub fn mkdir(&self, path: impl AsRef<str>) -> io::Result<()> {
         let c_string = CString::new(path.as_ref()).unwrap();
         let result = unsafe { (*self.handle).mkdir.unwrap()(c_string.as_ptr()) };
-        if result != 0 {
+        if result == 0 {
             Ok(())
         } else {
             Err(self.get_error().unwrap())
@@ -89,7 +101,7 @@ impl PlaydateFileSystem {
     pub fn unlink(&self, name: impl AsRef<str>, recursive: bool) -> io::Result<()> {
         let c_string = CString::new(name.as_ref()).unwrap();
         let result = unsafe { (*self.handle).unlink.unwrap()(c_string.as_ptr(), recursive as i32) };
-        if result != 0 {
+        if result == 0 {
             Ok(())
         } else {
             Err(self.get_error().unwrap())
@@ -102,7 +114,7 @@ impl PlaydateFileSystem {
         let to_c_string = CString::new(to.as_ref()).unwrap();
         let result =
             unsafe { (*self.handle).rename.unwrap()(from_c_string.as_ptr(), to_c_string.as_ptr()) };
-        if result != 0 {
+        if result == 0 {
             Ok(())
         } else {
             Err(self.get_error().unwrap())
@@ -157,7 +169,7 @@ impl PlaydateFileSystem {
     /// Flushes the output buffer of file immediately. Returns the number of bytes written, or -1 in case of error.
     pub(crate) fn flush(&self, file: *mut sys::SDFile) -> io::Result<()> {
         let result = unsafe { (*self.handle).flush.unwrap()(file) };
-        if result != 0 {
+        if result >= 0 {
             Ok(())
         } else {
             Err(self.get_error().unwrap())
@@ -175,9 +187,9 @@ impl PlaydateFileSystem {
     }
 
     /// Sets the read/write offset in the given file handle to pos, relative to the whence macro. SEEK_SET is relative to the beginning of the file, SEEK_CUR is relative to the current position of the file pointer, and SEEK_END is relative to the end of the file. Returns 0 on success, -1 on error.
-    pub(crate) fn seek(&self, file: *mut sys::SDFile, pos: usize, whence: i32) -> io::Result<()> {
-        let result = unsafe { (*self.handle).seek.unwrap()(file, pos as i32, whence) };
-        if result != 0 {
+    pub(crate) fn seek(&self, file: *mut sys::SDFile, pos: i32, whence: i32) -> io::Result<()> {
+        let result = unsafe { (*self.handle).seek.unwrap()(file, pos, whence) };
+        if result == 0 {
             Ok(())
         } else {
             Err(self.get_error().unwrap())
@@ -185,7 +197,7 @@ impl PlaydateFileSystem {
     }
 }
 
-use core::ffi::c_void;
+use core::ffi::{c_char, c_void};
 
 use crate::PLAYDATE;
 
@@ -208,6 +220,12 @@ impl File {
         PLAYDATE.file.open(name, mode)
     }
 
+    /// Reads exactly `buf.len()` bytes starting at `offset` from the beginning of the file. The read/write offset is left after the bytes read.
+    pub fn read_exact_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        self.seek(io::SeekFrom::Start(offset))?;
+        self.read_exact(buf)
+    }
+
     /// Read the entire content to a string
     pub fn read_to_string(&mut self) -> io::Result<String> {
         let mut buf = Vec::new();
@@ -249,15 +267,26 @@ impl Seek for File {
             io::SeekFrom::End(_) => SEEK_END,
             io::SeekFrom::Current(_) => SEEK_CUR,
         };
-        let pos = match pos {
-            io::SeekFrom::Start(pos) => pos as usize,
-            io::SeekFrom::End(pos) => pos as usize,
-            io::SeekFrom::Current(pos) => pos as usize,
+        let offset = match pos {
+            io::SeekFrom::Start(pos) => i32::try_from(pos).ok(),
+            io::SeekFrom::End(pos) => i32::try_from(pos).ok(),
+            io::SeekFrom::Current(pos) => i32::try_from(pos).ok(),
         };
-        if PLAYDATE.file.seek(self.handle, pos, whence as _).is_err() {
+        let Some(offset) = offset else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek offset out of range",
+            ));
+        };
+        if PLAYDATE
+            .file
+            .seek(self.handle, offset, whence as _)
+            .is_err()
+        {
             Err(io::Error::new(io::ErrorKind::Other, "file seek error"))
         } else {
-            Ok(pos as u64)
+            // Report the resulting position, not the requested offset
+            Ok(self.tell()? as u64)
         }
     }
 }
@@ -267,3 +296,112 @@ impl Drop for File {
         PLAYDATE.file.close(self.handle).unwrap();
     }
 }
+
+/// Reads the entire content of a file into a byte vector. The game's data folder is searched first, then the pdx.
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let mut file = File::open(
+        path.as_ref(),
+        FileOptions::kFileRead | FileOptions::kFileReadData,
+    )?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Reads the entire content of a file into a string. The game's data folder is searched first, then the pdx.
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    String::from_utf8(read(path)?)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "file is not valid UTF-8"))
+}
+
+/// Writes a slice as the entire content of a file in the game's data folder, replacing the file if it exists.
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut file = File::open(path.as_ref(), FileOptions::kFileWrite)?;
+    file.write_all(contents.as_ref())?;
+    file.flush()
+}
+
+/// Returns `true` if a file or directory exists at the given path.
+pub fn exists(path: impl AsRef<Path>) -> bool {
+    PLAYDATE.file.stat(path.as_ref()).is_ok()
+}
+
+/// Queries the metadata of a file or directory.
+pub fn metadata(path: impl AsRef<Path>) -> io::Result<Metadata> {
+    PLAYDATE.file.stat(path.as_ref()).map(Metadata::from)
+}
+
+/// Creates a directory in the game's data folder. The parent directory must exist.
+pub fn create_dir(path: impl AsRef<Path>) -> io::Result<()> {
+    PLAYDATE.file.mkdir(path.as_ref())
+}
+
+/// Creates a directory in the game's data folder, together with all its missing parent directories.
+pub fn create_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    let mut current = PathBuf::new();
+    for component in path.as_ref().components() {
+        current.push(component);
+        match metadata(&current) {
+            Ok(m) if m.is_dir() => {}
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "a file exists with the same name as the directory",
+                ))
+            }
+            Err(_) => create_dir(&current)?,
+        }
+    }
+    Ok(())
+}
+
+/// Returns an iterator over the entries of a directory. Hidden files are included.
+pub fn read_dir(path: impl AsRef<Path>) -> io::Result<ReadDir> {
+    ReadDir::new(path.as_ref())
+}
+
+/// Returns a recursive iterator over all files and directories below the given directory.
+pub fn walk_dir(path: impl AsRef<Path>) -> io::Result<WalkDir> {
+    WalkDir::new(path.as_ref())
+}
+
+/// Copies the content of a file to a file in the game's data folder, replacing it if it exists. Returns the number of bytes copied.
+pub fn copy(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<u64> {
+    let mut src = File::open(
+        from.as_ref(),
+        FileOptions::kFileRead | FileOptions::kFileReadData,
+    )?;
+    let mut dst = File::open(to.as_ref(), FileOptions::kFileWrite)?;
+    let mut buf = [0u8; 1024];
+    let mut copied = 0;
+    loop {
+        let len = src.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        dst.write_all(&buf[..len])?;
+        copied += len as u64;
+    }
+    dst.flush()?;
+    Ok(copied)
+}
+
+/// Renames a file or directory, replacing the destination if it exists.
+pub fn rename(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
+    PLAYDATE.file.rename(from.as_ref(), to.as_ref())
+}
+
+/// Deletes a file.
+pub fn remove_file(path: impl AsRef<Path>) -> io::Result<()> {
+    PLAYDATE.file.unlink(path.as_ref(), false)
+}
+
+/// Deletes an empty directory.
+pub fn remove_dir(path: impl AsRef<Path>) -> io::Result<()> {
+    PLAYDATE.file.unlink(path.as_ref(), false)
+}
+
+/// Deletes a directory and all its content.
+pub fn remove_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    PLAYDATE.file.unlink(path.as_ref(), true)
+}
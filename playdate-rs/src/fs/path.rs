@@ -0,0 +1,335 @@
+use alloc::{
+    borrow::{Borrow, ToOwned},
+    string::String,
+};
+use core::{fmt, ops::Deref};
+
+/// Path separator used by the Playdate filesystem.
+pub const SEPARATOR: char = '/';
+
+/// A slice of a path, similar to [`std::path::Path`](https://doc.rust-lang.org/std/path/struct.Path.html).
+///
+/// Playdate paths are relative to the game's data folder (and the pdx, for reading), use `/` as separator, and mark directories returned by [`PlaydateFileSystem::list_files`](super::PlaydateFileSystem::list_files) with a trailing slash. The methods of `Path` ignore the trailing slash, so `"images/"` and `"images"` have the same file name and parent.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Path {
+    inner: str,
+}
+
+impl Path {
+    /// Wraps a string slice as a path slice.
+    pub fn new<S: AsRef<str> + ?Sized>(s: &S) -> &Path {
+        // SAFETY: `Path` is a transparent wrapper around `str`
+        unsafe { &*(s.as_ref() as *const str as *const Path) }
+    }
+
+    /// Returns the path as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.inner
+    }
+
+    /// Converts the path to an owned [`PathBuf`].
+    pub fn to_path_buf(&self) -> PathBuf {
+        PathBuf {
+            inner: self.inner.to_owned(),
+        }
+    }
+
+    /// Returns `true` if the path is empty, i.e. the root of the data folder.
+    pub fn is_empty(&self) -> bool {
+        self.trimmed().is_empty()
+    }
+
+    /// Returns `true` if the path ends with a slash, the convention used by `listfiles` for directories.
+    pub fn has_trailing_slash(&self) -> bool {
+        self.inner.ends_with(SEPARATOR)
+    }
+
+    /// Returns the path without its trailing slashes.
+    pub fn trim_trailing_slash(&self) -> &Path {
+        Path::new(self.trimmed())
+    }
+
+    /// Returns the path without its final component, or `None` if the path has a single component or is empty.
+    pub fn parent(&self) -> Option<&Path> {
+        let trimmed = self.trimmed();
+        let (parent, _) = trimmed.rsplit_once(SEPARATOR)?;
+        Some(Path::new(parent.trim_end_matches(SEPARATOR)))
+    }
+
+    /// Returns the final component of the path, or `None` if the path is empty.
+    pub fn file_name(&self) -> Option<&str> {
+        let trimmed = self.trimmed();
+        let name = match trimmed.rsplit_once(SEPARATOR) {
+            Some((_, name)) => name,
+            None => trimmed,
+        };
+        (!name.is_empty()).then_some(name)
+    }
+
+    /// Returns the file name without its extension.
+    pub fn file_stem(&self) -> Option<&str> {
+        let name = self.file_name()?;
+        match name.rsplit_once('.') {
+            Some((stem, _)) if !stem.is_empty() => Some(stem),
+            _ => Some(name),
+        }
+    }
+
+    /// Returns the extension of the file name, without the leading dot.
+    pub fn extension(&self) -> Option<&str> {
+        let name = self.file_name()?;
+        match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => Some(ext),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the non-empty components of the path.
+    pub fn components(&self) -> impl Iterator<Item = &str> {
+        self.inner.split(SEPARATOR).filter(|c| !c.is_empty())
+    }
+
+    /// Creates an owned path with `path` appended to this path, with a single slash between them.
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        let mut buf = self.to_path_buf();
+        buf.push(path);
+        buf
+    }
+
+    /// Creates an owned path with the extension replaced, or removed if `extension` is empty.
+    pub fn with_extension(&self, extension: impl AsRef<str>) -> PathBuf {
+        let mut buf = self.to_path_buf();
+        buf.set_extension(extension);
+        buf
+    }
+
+    /// Returns `true` if `base` is a prefix of this path, compared component by component.
+    pub fn starts_with(&self, base: impl AsRef<Path>) -> bool {
+        let mut components = self.components();
+        base.as_ref()
+            .components()
+            .all(|c| components.next() == Some(c))
+    }
+
+    fn trimmed(&self) -> &str {
+        self.inner.trim_end_matches(SEPARATOR)
+    }
+}
+
+/// An owned, mutable path, similar to [`std::path::PathBuf`](https://doc.rust-lang.org/std/path/struct.PathBuf.html). See [`Path`].
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PathBuf {
+    inner: String,
+}
+
+impl PathBuf {
+    /// Creates an empty path, i.e. the root of the data folder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the path as a path slice.
+    pub fn as_path(&self) -> &Path {
+        Path::new(&self.inner)
+    }
+
+    /// Appends `path` to this path, with a single slash between them. The trailing slash of this path, if any, is dropped.
+    pub fn push(&mut self, path: impl AsRef<Path>) {
+        let path = path.as_ref().as_str().trim_start_matches(SEPARATOR);
+        if path.is_empty() {
+            return;
+        }
+        let len = self.inner.trim_end_matches(SEPARATOR).len();
+        self.inner.truncate(len);
+        if !self.inner.is_empty() {
+            self.inner.push(SEPARATOR);
+        }
+        self.inner.push_str(path);
+    }
+
+    /// Removes the final component. Returns `false` if the path has no parent.
+    pub fn pop(&mut self) -> bool {
+        match self.as_path().parent().map(|p| p.as_str().len()) {
+            Some(len) => {
+                self.inner.truncate(len);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replaces the extension of the file name, or removes it if `extension` is empty. Returns `false` if the path has no file name.
+    pub fn set_extension(&mut self, extension: impl AsRef<str>) -> bool {
+        let Some(stem) = self.file_stem() else {
+            return false;
+        };
+        let trimmed = self.as_path().trimmed();
+        let end = trimmed.len() - trimmed.rsplit(SEPARATOR).next().unwrap().len() + stem.len();
+        self.inner.truncate(end);
+        let extension = extension.as_ref();
+        if !extension.is_empty() {
+            self.inner.push('.');
+            self.inner.push_str(extension);
+        }
+        true
+    }
+
+    /// Consumes the path and returns the underlying string.
+    pub fn into_string(self) -> String {
+        self.inner
+    }
+}
+
+impl Deref for PathBuf {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl Borrow<Path> for PathBuf {
+    fn borrow(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl ToOwned for Path {
+    type Owned = PathBuf;
+
+    fn to_owned(&self) -> PathBuf {
+        self.to_path_buf()
+    }
+}
+
+impl AsRef<Path> for Path {
+    fn as_ref(&self) -> &Path {
+        self
+    }
+}
+
+impl AsRef<Path> for PathBuf {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl AsRef<Path> for str {
+    fn as_ref(&self) -> &Path {
+        Path::new(self)
+    }
+}
+
+impl AsRef<Path> for String {
+    fn as_ref(&self) -> &Path {
+        Path::new(self)
+    }
+}
+
+impl AsRef<str> for Path {
+    fn as_ref(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl AsRef<str> for PathBuf {
+    fn as_ref(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl From<&str> for PathBuf {
+    fn from(s: &str) -> Self {
+        Self {
+            inner: s.to_owned(),
+        }
+    }
+}
+
+impl From<String> for PathBuf {
+    fn from(inner: String) -> Self {
+        Self { inner }
+    }
+}
+
+impl From<&Path> for PathBuf {
+    fn from(path: &Path) -> Self {
+        path.to_path_buf()
+    }
+}
+
+impl From<PathBuf> for String {
+    fn from(path: PathBuf) -> Self {
+        path.inner
+    }
+}
+
+impl fmt::Debug for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.inner, f)
+    }
+}
+
+impl fmt::Debug for PathBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl fmt::Display for PathBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.inner, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components_ignore_trailing_slash() {
+        let dir = Path::new("levels/world 1/");
+        assert!(dir.has_trailing_slash());
+        assert_eq!(dir.trim_trailing_slash().as_str(), "levels/world 1");
+        assert_eq!(dir.file_name(), Some("world 1"));
+        assert_eq!(dir.parent().map(Path::as_str), Some("levels"));
+        assert_eq!(dir.components().collect::<Vec<_>>(), ["levels", "world 1"]);
+        assert_eq!(Path::new("levels").parent(), None);
+        assert!(Path::new("/").is_empty());
+        assert_eq!(Path::new("").file_name(), None);
+    }
+
+    #[test]
+    fn stem_and_extension() {
+        let file = Path::new("saves/slot.1.json");
+        assert_eq!(file.file_stem(), Some("slot.1"));
+        assert_eq!(file.extension(), Some("json"));
+        let hidden = Path::new(".config");
+        assert_eq!(hidden.file_stem(), Some(".config"));
+        assert_eq!(hidden.extension(), None);
+        assert_eq!(file.with_extension("bak").as_str(), "saves/slot.1.bak");
+        assert_eq!(file.with_extension("").as_str(), "saves/slot.1");
+    }
+
+    #[test]
+    fn push_pop_and_starts_with() {
+        let mut path = PathBuf::from("levels/");
+        path.push("/world/1.json");
+        assert_eq!(path.as_str(), "levels/world/1.json");
+        assert_eq!(PathBuf::new().join("a").as_str(), "a");
+        assert!(path.starts_with("levels/world"));
+        assert!(!path.starts_with("level"));
+        assert!(path.pop());
+        assert_eq!(path.as_str(), "levels/world");
+        assert!(path.pop());
+        assert!(!path.pop());
+        assert_eq!(path.as_str(), "levels");
+    }
+}
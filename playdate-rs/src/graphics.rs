@@ -1,10 +1,13 @@
 use core::{
+    cell::OnceCell,
     ffi::{c_char, c_void},
     marker::PhantomData,
 };
 
 use crate::math::{Rect, Size, Vec2};
-use alloc::ffi::CString;
+use alloc::{
+    borrow::ToOwned, collections::BTreeMap, ffi::CString, format, string::String, vec::Vec,
+};
 
 use crate::{math::SideOffsets, util::Ref};
 
@@ -748,6 +751,307 @@ impl Drop for BitmapTable {
     }
 }
 
+/// How the edges and the center of a [`NineSlice`] fill their area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum NineSliceMode {
+    /// The piece is scaled to fill the area.
+    #[default]
+    Stretch,
+    /// The piece is repeated, starting from the top-left corner of the area.
+    Tile,
+}
+
+/// A stretchable image for panels, dialog boxes and button frames. This is the equivalent of the Lua `playdate.graphics.nineSlice`.
+///
+/// The image is split into nine pieces by the inner insets: the four corners are always drawn at their original size, the top and bottom edges fill the width, the left and right edges fill the height, and the center fills the rest.
+#[derive(Debug, Clone)]
+pub struct NineSlice {
+    /// Pieces in row-major order, `None` for empty pieces.
+    pieces: [Option<Bitmap>; 9],
+    insets: SideOffsets<i32>,
+    edge_mode: NineSliceMode,
+    center_mode: NineSliceMode,
+}
+
+impl NineSlice {
+    /// Create a nine-slice from the given bitmap. `insets` are the sizes of the corners, measured from each side of the bitmap.
+    pub fn new(bitmap: impl AsRef<Bitmap>, insets: SideOffsets<i32>) -> Self {
+        let bitmap = bitmap.as_ref();
+        let size = bitmap.get_bitmap_data().size;
+        debug_assert!(
+            insets.left + insets.right <= size.width && insets.top + insets.bottom <= size.height,
+            "nine-slice insets are larger than the bitmap"
+        );
+        let xs = [0, insets.left, size.width - insets.right, size.width];
+        let ys = [0, insets.top, size.height - insets.bottom, size.height];
+        let pieces = core::array::from_fn(|i| {
+            let (col, row) = (i % 3, i / 3);
+            let rect = Rect::new(
+                xs[col],
+                ys[row],
+                xs[col + 1] - xs[col],
+                ys[row + 1] - ys[row],
+            );
+            if rect.width <= 0 || rect.height <= 0 {
+                return None;
+            }
+            let piece = Bitmap::new(rect.size().cast(), Color::Clear);
+            PLAYDATE.graphics.push_context(&piece);
+            PLAYDATE.graphics.draw_bitmap(
+                bitmap,
+                Vec2::new(-rect.x, -rect.y),
+                BitmapFlip::Unflipped,
+            );
+            PLAYDATE.graphics.pop_context();
+            Some(piece)
+        });
+        Self {
+            pieces,
+            insets,
+            edge_mode: NineSliceMode::Stretch,
+            center_mode: NineSliceMode::Stretch,
+        }
+    }
+
+    /// Open an image as a nine-slice.
+    pub fn open(path: impl AsRef<str>, insets: SideOffsets<i32>) -> Result<Self, Error> {
+        Ok(Self::new(Bitmap::open(path)?, insets))
+    }
+
+    /// Returns the inner insets.
+    pub fn get_insets(&self) -> SideOffsets<i32> {
+        self.insets
+    }
+
+    /// Returns the smallest size the nine-slice can be drawn at without shrinking the corners.
+    pub fn get_minimum_size(&self) -> Size<i32> {
+        Size::new(
+            self.insets.left + self.insets.right,
+            self.insets.top + self.insets.bottom,
+        )
+    }
+
+    /// Sets how the four edges fill their area.
+    pub fn set_edge_mode(&mut self, mode: NineSliceMode) {
+        self.edge_mode = mode;
+    }
+
+    /// Returns how the four edges fill their area.
+    pub fn get_edge_mode(&self) -> NineSliceMode {
+        self.edge_mode
+    }
+
+    /// Sets how the center fills its area.
+    pub fn set_center_mode(&mut self, mode: NineSliceMode) {
+        self.center_mode = mode;
+    }
+
+    /// Returns how the center fills its area.
+    pub fn get_center_mode(&self) -> NineSliceMode {
+        self.center_mode
+    }
+
+    /// Draws the nine-slice to fill the given rect. If the rect is smaller than [`NineSlice::get_minimum_size`], the corners are scaled down to fit.
+    pub fn draw_in_rect(&self, rect: Rect<i32>) {
+        if rect.width <= 0 || rect.height <= 0 {
+            return;
+        }
+        let (left, right) = fit_insets(self.insets.left, self.insets.right, rect.width);
+        let (top, bottom) = fit_insets(self.insets.top, self.insets.bottom, rect.height);
+        let xs = [rect.x, rect.x + left, rect.x + rect.width - right];
+        let ws = [left, rect.width - left - right, right];
+        let ys = [rect.y, rect.y + top, rect.y + rect.height - bottom];
+        let hs = [top, rect.height - top - bottom, bottom];
+        for (i, piece) in self.pieces.iter().enumerate() {
+            let Some(piece) = piece else {
+                continue;
+            };
+            let (col, row) = (i % 3, i / 3);
+            if ws[col] <= 0 || hs[row] <= 0 {
+                continue;
+            }
+            let area = Rect::new(xs[col], ys[row], ws[col], hs[row]);
+            let mode = match (col, row) {
+                (1, 1) => self.center_mode,
+                (1, _) | (_, 1) => self.edge_mode,
+                _ => NineSliceMode::Stretch,
+            };
+            let size = piece.get_bitmap_data().size;
+            if size == area.size() {
+                PLAYDATE
+                    .graphics
+                    .draw_bitmap(piece, area.pos(), BitmapFlip::Unflipped);
+            } else if mode == NineSliceMode::Tile {
+                PLAYDATE
+                    .graphics
+                    .tile_bitmap(piece, area, BitmapFlip::Unflipped);
+            } else {
+                let scale = Vec2::new(
+                    area.width as f32 / size.width as f32,
+                    area.height as f32 / size.height as f32,
+                );
+                PLAYDATE
+                    .graphics
+                    .draw_scaled_bitmap(piece, area.pos(), scale);
+            }
+        }
+    }
+}
+
+/// Shrinks a pair of opposite insets proportionally so that they fit in `length`.
+fn fit_insets(a: i32, b: i32, length: i32) -> (i32, i32) {
+    if a + b <= length {
+        return (a, b);
+    }
+    let a = length * a / (a + b);
+    (a, length - a)
+}
+
+/// Where an image of an [`Atlas`] is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasImage {
+    /// Index of the sheet holding the image.
+    pub sheet: usize,
+    /// Area of the image in its sheet. Transparent borders trimmed at build time are not included.
+    pub rect: Rect<i32>,
+    /// Position of `rect` in the original image.
+    pub offset: Vec2<i32>,
+    /// Size of the original image.
+    pub size: Size<i32>,
+}
+
+/// Many small images packed into a few sheets by `cargo playdate build`, see `[[package.metadata.playdate.atlases]]`. The sheets are loaded once, and the images are drawn from them by name.
+///
+/// The images are named after their path in the asset folders, without extension.
+#[derive(Debug)]
+pub struct Atlas {
+    sheets: Vec<Bitmap>,
+    /// The images, with their area of the sheet once it was cut out by [`Atlas::draw`]
+    images: BTreeMap<String, (AtlasImage, OnceCell<Bitmap>)>,
+}
+
+impl Atlas {
+    /// Open the atlas at path, i.e. its index `<path>.atlas` and its sheets `<path>-<n>`.
+    pub fn open(path: impl AsRef<str>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let index_path = format!("{}.atlas", path);
+        let index = crate::fs::read_to_string(&index_path).map_err(Error::IO)?;
+        let invalid = || Error::InvalidAtlas(index_path.clone());
+        let mut lines = index.lines();
+        let count = lines
+            .next()
+            .and_then(|l| l.strip_prefix("sheets="))
+            .and_then(|n| n.parse::<usize>().ok())
+            .ok_or_else(invalid)?;
+        let mut images = BTreeMap::new();
+        for line in lines.filter(|l| !l.is_empty()) {
+            let (name, fields) = line.split_once('\t').ok_or_else(invalid)?;
+            let mut fields = fields.split('\t').map(|f| f.parse::<i32>().ok());
+            let mut next = || fields.next().flatten().ok_or_else(invalid);
+            let sheet = next()? as usize;
+            let rect = Rect::new(next()?, next()?, next()?, next()?);
+            let offset = Vec2::new(next()?, next()?);
+            let size = Size::new(next()?, next()?);
+            if sheet >= count {
+                return Err(invalid());
+            }
+            let image = AtlasImage {
+                sheet,
+                rect,
+                offset,
+                size,
+            };
+            images.insert(name.to_owned(), (image, OnceCell::new()));
+        }
+        let sheets = (0..count)
+            .map(|i| Bitmap::open(format!("{}-{}", path, i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { sheets, images })
+    }
+
+    /// Returns the image with the given name, or `None` if there is none.
+    pub fn get(&self, name: &str) -> Option<&AtlasImage> {
+        self.images.get(name).map(|(image, _)| image)
+    }
+
+    /// Returns the names of the images, in alphabetical order.
+    pub fn get_names(&self) -> impl Iterator<Item = &str> {
+        self.images.keys().map(|k| k.as_str())
+    }
+
+    /// Returns the sheets of the atlas.
+    pub fn get_sheets(&self) -> &[Bitmap] {
+        &self.sheets
+    }
+
+    fn find(&self, name: &str) -> Result<&AtlasImage, Error> {
+        self.get(name)
+            .ok_or_else(|| Error::AtlasImageNotFound(name.to_owned()))
+    }
+
+    /// Draws the named image with its upper-left corner at pos, like [`PlaydateGraphics::draw_bitmap`]. The first time an image is drawn, its area of the sheet is copied to a bitmap kept by the atlas.
+    pub fn draw(&self, name: &str, pos: Vec2<i32>, flip: BitmapFlip) -> Result<(), Error> {
+        let (image, piece) = self
+            .images
+            .get(name)
+            .ok_or_else(|| Error::AtlasImageNotFound(name.to_owned()))?;
+        let rect = image.rect;
+        let piece = piece.get_or_init(|| {
+            let piece = Bitmap::new(rect.size().cast(), Color::Clear);
+            PLAYDATE.graphics.push_context(&piece);
+            PLAYDATE.graphics.draw_bitmap(
+                &self.sheets[image.sheet],
+                Vec2::new(-rect.x, -rect.y),
+                BitmapFlip::Unflipped,
+            );
+            PLAYDATE.graphics.pop_context();
+            piece
+        });
+        // A flipped image is mirrored around its center, and so are the trimmed borders
+        let (flip_x, flip_y) = match flip {
+            BitmapFlip::Unflipped => (false, false),
+            BitmapFlip::FlippedX => (true, false),
+            BitmapFlip::FlippedY => (false, true),
+            BitmapFlip::FlippedXY => (true, true),
+        };
+        let x = if flip_x {
+            image.size.width - image.offset.x - rect.width
+        } else {
+            image.offset.x
+        };
+        let y = if flip_y {
+            image.size.height - image.offset.y - rect.height
+        } else {
+            image.offset.y
+        };
+        PLAYDATE
+            .graphics
+            .draw_bitmap(piece, pos + Vec2::new(x, y), flip);
+        Ok(())
+    }
+
+    /// Copies the named image out of its sheet, at its original size. This is useful for images drawn with [`Bitmap`]-only functions, e.g. as sprite images.
+    pub fn get_bitmap(&self, name: &str) -> Result<Bitmap, Error> {
+        let image = self.find(name)?;
+        let bitmap = Bitmap::new(image.size.cast(), Color::Clear);
+        PLAYDATE.graphics.push_context(&bitmap);
+        PLAYDATE.graphics.set_clip_rect(Rect::new(
+            image.offset.x,
+            image.offset.y,
+            image.rect.width,
+            image.rect.height,
+        ));
+        PLAYDATE.graphics.draw_bitmap(
+            &self.sheets[image.sheet],
+            Vec2::new(image.offset.x - image.rect.x, image.offset.y - image.rect.y),
+            BitmapFlip::Unflipped,
+        );
+        PLAYDATE.graphics.clear_clip_rect();
+        PLAYDATE.graphics.pop_context();
+        Ok(bitmap)
+    }
+}
+
 #[derive(PartialEq, Eq, Debug)]
 pub struct Font {
     handle: *mut sys::LCDFont,
@@ -0,0 +1,508 @@
+use std::{
+    alloc::Layout,
+    boxed::Box,
+    cell::RefCell,
+    ffi::{c_char, c_int, c_uint, c_void, CStr, CString},
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{Mutex, Once, OnceLock},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+    vec::Vec,
+};
+
+use crate::PlaydateAPI;
+
+/// Environment variable overriding the directory used as the game's data folder. Defaults to a new directory under the system temporary directory.
+pub const DATA_DIR_ENV: &str = "PLAYDATE_HEADLESS_DATA_DIR";
+/// Environment variable overriding the directory used as the game pdx, for files opened with `kFileRead`. Defaults to `assets`, relative to the package being tested.
+pub const PDX_DIR_ENV: &str = "PLAYDATE_HEADLESS_PDX_DIR";
+
+/// Seconds between the unix epoch and the playdate epoch, 2000-01-01 00:00:00 UTC.
+const PLAYDATE_EPOCH_OFFSET: u64 = 946_684_800;
+/// Alignment of the blocks returned by `realloc`, matching the device.
+const REALLOC_ALIGN: usize = 8;
+/// Every `realloc` block starts with its size, so it can be reallocated and freed.
+const REALLOC_HEADER: usize = 8;
+
+struct Headless {
+    data_dir: PathBuf,
+    pdx_dir: PathBuf,
+    start: Instant,
+    elapsed_start: Mutex<Instant>,
+}
+
+static HEADLESS: OnceLock<Headless> = OnceLock::new();
+static API: OnceLock<PlaydateAPI> = OnceLock::new();
+static LOGGER_INIT: Once = Once::new();
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn headless() -> &'static Headless {
+    HEADLESS.get_or_init(|| {
+        let data_dir = std::env::var_os(DATA_DIR_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                std::env::temp_dir().join(format!("playdate-headless-{}", std::process::id()))
+            });
+        fs::create_dir_all(&data_dir).unwrap();
+        let pdx_dir = std::env::var_os(PDX_DIR_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("assets"));
+        Headless {
+            data_dir,
+            pdx_dir,
+            start: Instant::now(),
+            elapsed_start: Mutex::new(Instant::now()),
+        }
+    })
+}
+
+/// Returns the headless API, used by `PLAYDATE` when the game was not started by the playdate runtime, e.g. in `#[test]`s.
+///
+/// Only the system and file APIs are emulated: logging goes to stdout, `system.error` panics, the clock is the host clock, no buttons are pressed and the crank is docked. Files are read from [`PDX_DIR_ENV`] and written to [`DATA_DIR_ENV`]. Every other function panics when called.
+///
+/// Tests run in parallel threads, so tests sharing files in the data folder should use different file names.
+pub(crate) fn api() -> &'static PlaydateAPI {
+    let api = API.get_or_init(|| {
+        let raw_api = sys::PlaydateAPI {
+            system: Box::leak(Box::new(system_api())),
+            file: Box::leak(Box::new(file_api())),
+            graphics: unavailable(),
+            sprite: unavailable(),
+            display: unavailable(),
+            sound: unavailable(),
+            lua: unavailable(),
+            json: unavailable(),
+            scoreboards: unavailable(),
+        };
+        PlaydateAPI::new(Box::leak(Box::new(raw_api)))
+    });
+    LOGGER_INIT.call_once(crate::logger::init);
+    api
+}
+
+/// Returns `true` if `PLAYDATE` is the headless API.
+pub(crate) fn is_active() -> bool {
+    API.get()
+        .is_some_and(|api| core::ptr::eq(api, &*crate::PLAYDATE))
+}
+
+/// A function table without any function, for the APIs the shim does not emulate. Nested tables are null pointers.
+fn unavailable<T>() -> &'static T {
+    // SAFETY: the tables only contain optional function pointers and raw pointers, for which all-zero is `None` and null
+    Box::leak(Box::new(unsafe { core::mem::zeroed() }))
+}
+
+fn set_error(error: impl ToString) {
+    let message = CString::new(error.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Converts a libc style result to 0 on success and -1 on error.
+fn to_status(result: io::Result<()>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+unsafe fn to_str<'a>(s: *const c_char) -> &'a str {
+    CStr::from_ptr(s).to_str().unwrap()
+}
+
+fn system_api() -> sys::playdate_sys {
+    sys::playdate_sys {
+        realloc: Some(realloc),
+        logToConsole: Some(log_to_console),
+        error: Some(error),
+        getCurrentTimeMilliseconds: Some(get_current_time_milliseconds),
+        getSecondsSinceEpoch: Some(get_seconds_since_epoch),
+        getElapsedTime: Some(get_elapsed_time),
+        resetElapsedTime: Some(reset_elapsed_time),
+        getButtonState: Some(get_button_state),
+        getCrankAngle: Some(get_crank_angle),
+        getCrankChange: Some(get_crank_change),
+        isCrankDocked: Some(is_crank_docked),
+        setUpdateCallback: Some(set_update_callback),
+        drawFPS: Some(draw_fps),
+        ..Default::default()
+    }
+}
+
+unsafe extern "C" fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+    let layout =
+        |size: usize| Layout::from_size_align(size + REALLOC_HEADER, REALLOC_ALIGN).unwrap();
+    let block = if ptr.is_null() {
+        if size == 0 {
+            return core::ptr::null_mut();
+        }
+        std::alloc::alloc(layout(size))
+    } else {
+        let block = (ptr as *mut u8).sub(REALLOC_HEADER);
+        let old_size = (block as *const usize).read();
+        if size == 0 {
+            std::alloc::dealloc(block, layout(old_size));
+            return core::ptr::null_mut();
+        }
+        std::alloc::realloc(block, layout(old_size), size + REALLOC_HEADER)
+    };
+    if block.is_null() {
+        return core::ptr::null_mut();
+    }
+    (block as *mut usize).write(size);
+    block.add(REALLOC_HEADER) as *mut c_void
+}
+
+// The bindings only pass preformatted messages, so the format string is the message
+unsafe extern "C" fn log_to_console(fmt: *const c_char, _args: ...) {
+    // `println!` is the crate macro writing to `logToConsole`
+    std::println!("{}", CStr::from_ptr(fmt).to_string_lossy());
+}
+
+// Unwinding through the C API is not allowed, so `PlaydateSystem::error` panics after this returns
+unsafe extern "C" fn error(fmt: *const c_char, _args: ...) {
+    eprintln!("error: {}", CStr::from_ptr(fmt).to_string_lossy());
+}
+
+extern "C" fn get_current_time_milliseconds() -> c_uint {
+    headless().start.elapsed().as_millis() as _
+}
+
+unsafe extern "C" fn get_seconds_since_epoch(milliseconds: *mut c_uint) -> c_uint {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    if !milliseconds.is_null() {
+        *milliseconds = now.subsec_millis();
+    }
+    now.as_secs().saturating_sub(PLAYDATE_EPOCH_OFFSET) as _
+}
+
+extern "C" fn get_elapsed_time() -> f32 {
+    headless()
+        .elapsed_start
+        .lock()
+        .unwrap()
+        .elapsed()
+        .as_secs_f32()
+}
+
+extern "C" fn reset_elapsed_time() {
+    *headless().elapsed_start.lock().unwrap() = Instant::now();
+}
+
+unsafe extern "C" fn get_button_state(
+    current: *mut sys::PDButtons,
+    pushed: *mut sys::PDButtons,
+    released: *mut sys::PDButtons,
+) {
+    for buttons in [current, pushed, released] {
+        if !buttons.is_null() {
+            *buttons = sys::PDButtons(0);
+        }
+    }
+}
+
+extern "C" fn get_crank_angle() -> f32 {
+    0.0
+}
+
+extern "C" fn get_crank_change() -> f32 {
+    0.0
+}
+
+extern "C" fn is_crank_docked() -> c_int {
+    1
+}
+
+extern "C" fn set_update_callback(_update: sys::PDCallbackFunction, _userdata: *mut c_void) {}
+
+extern "C" fn draw_fps(_x: c_int, _y: c_int) {}
+
+fn file_api() -> sys::playdate_file {
+    sys::playdate_file {
+        geterr: Some(geterr),
+        listfiles: Some(listfiles),
+        stat: Some(stat),
+        mkdir: Some(mkdir),
+        unlink: Some(unlink),
+        rename: Some(rename),
+        open: Some(open),
+        close: Some(close),
+        read: Some(read),
+        write: Some(write),
+        flush: Some(flush),
+        tell: Some(tell),
+        seek: Some(seek),
+    }
+}
+
+/// Returns the path of `name` in the data folder.
+fn data_path(name: &str) -> PathBuf {
+    headless().data_dir.join(name.trim_start_matches('/'))
+}
+
+/// Returns the path of `name` in the data folder if it exists there, else in the pdx.
+fn find_path(name: &str) -> PathBuf {
+    let path = data_path(name);
+    if path.exists() {
+        path
+    } else {
+        headless().pdx_dir.join(name.trim_start_matches('/'))
+    }
+}
+
+extern "C" fn geterr() -> *const c_char {
+    // The message stays valid until the next error on this thread
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map_or(core::ptr::null(), |e| e.as_ptr())
+    })
+}
+
+unsafe extern "C" fn listfiles(
+    path: *const c_char,
+    callback: Option<unsafe extern "C" fn(path: *const c_char, userdata: *mut c_void)>,
+    userdata: *mut c_void,
+    showhidden: c_int,
+) -> c_int {
+    let path = to_str(path).trim_start_matches('/');
+    // The data folder and the pdx are merged, like on the device
+    let mut names = Vec::new();
+    let mut found = false;
+    for dir in [&headless().data_dir, &headless().pdx_dir] {
+        let Ok(entries) = fs::read_dir(dir.join(path)) else {
+            continue;
+        };
+        found = true;
+        for entry in entries.flatten() {
+            let mut name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') && showhidden == 0 {
+                continue;
+            }
+            if entry.path().is_dir() {
+                name.push('/');
+            }
+            names.push(name);
+        }
+    }
+    if !found {
+        set_error(format!("{}: no such directory", path));
+        return -1;
+    }
+    names.sort();
+    names.dedup();
+    if let Some(callback) = callback {
+        for name in names {
+            let name = CString::new(name).unwrap();
+            callback(name.as_ptr(), userdata);
+        }
+    }
+    0
+}
+
+/// Converts days since the unix epoch to a (year, month, day) date, see <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+unsafe extern "C" fn stat(path: *const c_char, stat: *mut sys::FileStat) -> c_int {
+    let metadata = match fs::metadata(find_path(to_str(path))) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            set_error(e);
+            return -1;
+        }
+    };
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs() as i64);
+    let (year, month, day) = civil_from_days(modified.div_euclid(86_400));
+    let seconds = modified.rem_euclid(86_400);
+    *stat = sys::FileStat {
+        isdir: metadata.is_dir() as _,
+        size: metadata.len() as _,
+        m_year: year as _,
+        m_month: month as _,
+        m_day: day as _,
+        m_hour: (seconds / 3600) as _,
+        m_minute: (seconds / 60 % 60) as _,
+        m_second: (seconds % 60) as _,
+    };
+    0
+}
+
+unsafe extern "C" fn mkdir(path: *const c_char) -> c_int {
+    to_status(fs::create_dir(data_path(to_str(path))))
+}
+
+unsafe extern "C" fn unlink(name: *const c_char, recursive: c_int) -> c_int {
+    let path = data_path(to_str(name));
+    to_status(if !path.is_dir() {
+        fs::remove_file(path)
+    } else if recursive != 0 {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_dir(path)
+    })
+}
+
+unsafe extern "C" fn rename(from: *const c_char, to: *const c_char) -> c_int {
+    to_status(fs::rename(data_path(to_str(from)), data_path(to_str(to))))
+}
+
+unsafe extern "C" fn open(name: *const c_char, mode: sys::FileOptions) -> *mut sys::SDFile {
+    let name = to_str(name);
+    let has = |option: sys::FileOptions| mode.0 & option.0 != 0;
+    let file = if has(sys::FileOptions::kFileWrite) {
+        fs::File::create(data_path(name))
+    } else if has(sys::FileOptions::kFileAppend) {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(data_path(name))
+    } else {
+        // kFileReadData searches the data folder, kFileRead the pdx, and both search the data folder first
+        let mut candidates = Vec::new();
+        if has(sys::FileOptions::kFileReadData) {
+            candidates.push(data_path(name));
+        }
+        if has(sys::FileOptions::kFileRead) {
+            candidates.push(headless().pdx_dir.join(name.trim_start_matches('/')));
+        }
+        candidates
+            .into_iter()
+            .find(|path| path.is_file())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{}: not found", name)))
+            .and_then(fs::File::open)
+    };
+    match file {
+        Ok(file) => Box::into_raw(Box::new(file)) as *mut _,
+        Err(e) => {
+            set_error(e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+unsafe fn file<'a>(file: *mut sys::SDFile) -> &'a mut fs::File {
+    &mut *(file as *mut fs::File)
+}
+
+unsafe extern "C" fn close(handle: *mut sys::SDFile) -> c_int {
+    drop(Box::from_raw(handle as *mut fs::File));
+    0
+}
+
+unsafe extern "C" fn read(handle: *mut sys::SDFile, buf: *mut c_void, len: c_uint) -> c_int {
+    let buf = core::slice::from_raw_parts_mut(buf as *mut u8, len as usize);
+    match file(handle).read(buf) {
+        Ok(n) => n as _,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+unsafe extern "C" fn write(handle: *mut sys::SDFile, buf: *const c_void, len: c_uint) -> c_int {
+    let buf = core::slice::from_raw_parts(buf as *const u8, len as usize);
+    match file(handle).write(buf) {
+        Ok(n) => n as _,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+unsafe extern "C" fn flush(handle: *mut sys::SDFile) -> c_int {
+    to_status(file(handle).flush())
+}
+
+unsafe extern "C" fn tell(handle: *mut sys::SDFile) -> c_int {
+    match file(handle).stream_position() {
+        Ok(pos) => pos as _,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+unsafe extern "C" fn seek(handle: *mut sys::SDFile, pos: c_int, whence: c_int) -> c_int {
+    let pos = match whence as u32 {
+        sys::SEEK_SET => SeekFrom::Start(pos as _),
+        sys::SEEK_CUR => SeekFrom::Current(pos as _),
+        sys::SEEK_END => SeekFrom::End(pos as _),
+        _ => {
+            set_error("invalid whence");
+            return -1;
+        }
+    };
+    to_status(file(handle).seek(pos).map(|_| ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::headless;
+    use crate::{fs, storage};
+
+    #[test]
+    fn file_system_writes_to_data_dir() {
+        fs::create_dir_all("headless-fs/levels").unwrap();
+        fs::write("headless-fs/levels/1.txt", "level 1").unwrap();
+        fs::write("headless-fs/notes.txt", []).unwrap();
+        let data_dir = &headless().data_dir;
+        assert_eq!(
+            std::fs::read_to_string(data_dir.join("headless-fs/levels/1.txt")).unwrap(),
+            "level 1"
+        );
+        assert_eq!(
+            fs::read_to_string("headless-fs/levels/1.txt").unwrap(),
+            "level 1"
+        );
+        assert!(fs::read("headless-fs/notes.txt").unwrap().is_empty());
+        let mut names = fs::read_dir("headless-fs")
+            .unwrap()
+            .filter(|e| e.is_file())
+            .map(|e| e.file_name().to_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["notes.txt"]);
+        fs::remove_dir_all("headless-fs").unwrap();
+        assert!(!data_dir.join("headless-fs").exists());
+        assert!(fs::read("headless-fs/notes.txt").is_err());
+    }
+
+    #[test]
+    fn storage_round_trip() {
+        storage::save("headless-storage", &(3u32, "level 3".to_owned())).unwrap();
+        assert!(storage::exists("headless-storage"));
+        let (level, name): (u32, String) = storage::load("headless-storage").unwrap();
+        assert_eq!((level, name.as_str()), (3, "level 3"));
+        let mut migrated = storage::Storage::new();
+        migrated.set_schema_version(1);
+        migrated.add_migration(0, |(level, _): (u32, String)| level * 10);
+        assert_eq!(migrated.load::<u32>("headless-storage").unwrap(), 30);
+        storage::remove("headless-storage").unwrap();
+        assert!(!storage::exists("headless-storage"));
+        assert!(storage::load::<u32>("headless-storage").is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write as _;
+
+use crate::{
+    error::Error,
+    fs::{File, FileOptions, Write},
+    system::Buttons,
+};
+
+use super::BUTTONS;
+
+/// A game action that can be bound to buttons with an [`ActionMap`].
+///
+/// ```ignore
+/// #[derive(Clone, Copy, PartialEq, Eq)]
+/// enum GameAction {
+///     Jump,
+///     Fire,
+/// }
+///
+/// impl Action for GameAction {
+///     fn name(&self) -> &'static str {
+///         match self {
+///             GameAction::Jump => "jump",
+///             GameAction::Fire => "fire",
+///         }
+///     }
+/// }
+/// ```
+pub trait Action: Copy + Eq {
+    /// A stable, unique name for the action. This is used as the key when the bindings are saved.
+    fn name(&self) -> &'static str;
+}
+
+/// A rebindable mapping from game actions to buttons.
+///
+/// An action can be bound to several buttons, and is triggered by any of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionMap<A: Action> {
+    bindings: Vec<(A, Buttons)>,
+}
+
+impl<A: Action> Default for ActionMap<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Action> ActionMap<A> {
+    /// Create an empty action map.
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Binds the action to the given buttons, replacing any previous binding.
+    pub fn bind(&mut self, action: A, buttons: Buttons) {
+        if let Some(binding) = self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            binding.1 = buttons;
+        } else {
+            self.bindings.push((action, buttons));
+        }
+    }
+
+    /// Removes all buttons bound to the action.
+    pub fn unbind(&mut self, action: A) {
+        self.bind(action, Buttons::none());
+    }
+
+    /// Returns the buttons bound to the action.
+    pub fn get_binding(&self, action: A) -> Buttons {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, b)| *b)
+            .unwrap_or(Buttons::none())
+    }
+
+    /// Returns all actions and their bound buttons, in the order they were first bound.
+    pub fn get_bindings(&self) -> &[(A, Buttons)] {
+        &self.bindings
+    }
+
+    /// Returns `true` if any button bound to the action is currently down.
+    pub fn is_pressed(&self, action: A) -> bool {
+        super::is_pressed(self.get_binding(action))
+    }
+
+    /// Returns `true` if any button bound to the action was pushed during the last frame.
+    pub fn just_pressed(&self, action: A) -> bool {
+        super::just_pressed(self.get_binding(action))
+    }
+
+    /// Returns `true` if any button bound to the action was released during the last frame.
+    pub fn just_released(&self, action: A) -> bool {
+        super::just_released(self.get_binding(action))
+    }
+
+    /// Returns `true` if any button bound to the action was pushed or key-repeated during the last frame. See [`super::is_repeated`].
+    pub fn is_repeated(&self, action: A) -> bool {
+        super::is_repeated(self.get_binding(action))
+    }
+
+    /// Returns how long, in seconds, the action has been held down through any of its buttons.
+    pub fn get_hold_duration(&self, action: A) -> f32 {
+        let buttons = self.get_binding(action);
+        BUTTONS
+            .iter()
+            .filter(|(b, _)| buttons.contains(*b))
+            .map(|(b, _)| super::get_hold_duration(*b))
+            .fold(0.0, f32::max)
+    }
+
+    /// Returns `true` and consumes the press if the action was triggered within the last `window` seconds. See [`super::consume_buffered_press`].
+    pub fn consume_buffered_press(&self, action: A, window: f32) -> bool {
+        let buttons = self.get_binding(action);
+        !buttons.is_none() && super::consume_buffered_press(buttons, window)
+    }
+
+    /// Writes the bindings to the file at `path` in the game's data folder, one `name=Button|Button` line per action.
+    pub fn save(&self, path: impl AsRef<str>) -> Result<(), Error> {
+        let mut content = String::new();
+        for (action, buttons) in &self.bindings {
+            let names = BUTTONS
+                .iter()
+                .filter(|(b, _)| buttons.contains(*b))
+                .map(|(_, name)| *name)
+                .collect::<Vec<_>>();
+            writeln!(content, "{}={}", action.name(), names.join("|")).unwrap();
+        }
+        let mut file = File::open(path, FileOptions::kFileWrite).map_err(Error::IO)?;
+        file.write_all(content.as_bytes()).map_err(Error::IO)?;
+        Ok(())
+    }
+
+    /// Loads bindings previously written by [`ActionMap::save`] from the game's data folder.
+    ///
+    /// Only actions that are already bound in this map are updated, so bind the default buttons for every action first. Lines with unknown action or button names are ignored.
+    pub fn load(&mut self, path: impl AsRef<str>) -> Result<(), Error> {
+        let mut file = File::open(path, FileOptions::kFileReadData).map_err(Error::IO)?;
+        let content = file.read_to_string().map_err(Error::IO)?;
+        for line in content.lines() {
+            let Some((name, buttons)) = line.split_once('=') else {
+                continue;
+            };
+            let Some(buttons) = parse_buttons(buttons.trim()) else {
+                continue;
+            };
+            if let Some(binding) = self
+                .bindings
+                .iter_mut()
+                .find(|(a, _)| a.name() == name.trim())
+            {
+                binding.1 = buttons;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_buttons(s: &str) -> Option<Buttons> {
+    let mut buttons = Buttons::none();
+    for name in s.split('|').map(str::trim).filter(|n| !n.is_empty()) {
+        let (button, _) = BUTTONS.iter().find(|(_, n)| *n == name)?;
+        buttons |= *button;
+    }
+    Some(buttons)
+}
@@ -0,0 +1,243 @@
+mod action;
+
+use alloc::collections::VecDeque;
+use spin::Mutex;
+
+use crate::{system::Buttons, PLAYDATE};
+
+pub use action::{Action, ActionMap};
+
+/// Default delay before a held button starts repeating, in seconds. Same as the Lua `playdate.timer.keyRepeatTimer`.
+pub const DEFAULT_KEY_REPEAT_DELAY: f32 = 0.3;
+/// Default interval between two repeats of a held button, in seconds. Same as the Lua `playdate.timer.keyRepeatTimer`.
+pub const DEFAULT_KEY_REPEAT_INTERVAL: f32 = 0.1;
+
+/// Maximum number of button presses remembered for buffering and sequence detection.
+const HISTORY_CAPACITY: usize = 32;
+
+/// All buttons with their names, in bit order.
+pub(crate) const BUTTONS: [(Buttons, &str); 6] = [
+    (Buttons::Left, "Left"),
+    (Buttons::Right, "Right"),
+    (Buttons::Up, "Up"),
+    (Buttons::Down, "Down"),
+    (Buttons::B, "B"),
+    (Buttons::A, "A"),
+];
+
+static STATE: Mutex<InputState> = Mutex::new(InputState::new());
+
+#[derive(Debug, Clone, Copy)]
+struct ButtonPress {
+    button: Buttons,
+    time: f32,
+    consumed: bool,
+}
+
+struct InputState {
+    time: f32,
+    current: Buttons,
+    pushed: Buttons,
+    released: Buttons,
+    repeated: Buttons,
+    pressed_at: [Option<f32>; BUTTONS.len()],
+    next_repeat: [f32; BUTTONS.len()],
+    repeat_delay: f32,
+    repeat_interval: f32,
+    history: VecDeque<ButtonPress>,
+}
+
+impl InputState {
+    const fn new() -> Self {
+        Self {
+            time: 0.0,
+            current: Buttons::none(),
+            pushed: Buttons::none(),
+            released: Buttons::none(),
+            repeated: Buttons::none(),
+            pressed_at: [None; BUTTONS.len()],
+            next_repeat: [0.0; BUTTONS.len()],
+            repeat_delay: DEFAULT_KEY_REPEAT_DELAY,
+            repeat_interval: DEFAULT_KEY_REPEAT_INTERVAL,
+            history: VecDeque::new(),
+        }
+    }
+
+    fn update(&mut self, delta: f32) {
+        self.time += delta;
+        let state = PLAYDATE.system.get_button_state();
+        self.current = state.current;
+        self.pushed = state.pushed;
+        self.released = state.released;
+        self.repeated = Buttons::none();
+        for (i, (button, _)) in BUTTONS.iter().enumerate() {
+            if self.pushed.contains(*button) {
+                // A new press fires immediately, then repeats after the initial delay
+                self.pressed_at[i] = Some(self.time);
+                self.next_repeat[i] = self.time + self.repeat_delay;
+                self.repeated |= *button;
+                if self.history.len() == HISTORY_CAPACITY {
+                    self.history.pop_front();
+                }
+                self.history.push_back(ButtonPress {
+                    button: *button,
+                    time: self.time,
+                    consumed: false,
+                });
+            } else if self.current.contains(*button) {
+                if self.pressed_at[i].is_none() {
+                    // Held since before the first sampled frame
+                    self.pressed_at[i] = Some(self.time);
+                    self.next_repeat[i] = self.time + self.repeat_delay;
+                } else if self.time >= self.next_repeat[i] {
+                    self.repeated |= *button;
+                    // Don't try to catch up on missed repeats after a long frame
+                    self.next_repeat[i] =
+                        (self.next_repeat[i] + self.repeat_interval).max(self.time);
+                }
+            }
+            if !self.current.contains(*button) {
+                self.pressed_at[i] = None;
+            }
+        }
+    }
+
+    fn get_hold_duration(&self, buttons: Buttons) -> f32 {
+        let mut duration: Option<f32> = None;
+        for (i, (button, _)) in BUTTONS.iter().enumerate() {
+            if !buttons.contains(*button) {
+                continue;
+            }
+            let Some(pressed_at) = self.pressed_at[i] else {
+                return 0.0;
+            };
+            let d = self.time - pressed_at;
+            duration = Some(duration.map_or(d, |x| x.min(d)));
+        }
+        duration.unwrap_or(0.0)
+    }
+
+    fn consume_buffered_press(&mut self, buttons: Buttons, window: f32) -> bool {
+        let time = self.time;
+        let press = self
+            .history
+            .iter_mut()
+            .rev()
+            .take_while(|p| time - p.time <= window)
+            .find(|p| !p.consumed && buttons.contains(p.button));
+        match press {
+            Some(press) => {
+                press.consumed = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn combo_pressed(&self, buttons: Buttons, window: f32) -> bool {
+        if buttons.is_none() || !self.current.contains(buttons) || !self.pushed.intersects(buttons)
+        {
+            return false;
+        }
+        let mut first = f32::MAX;
+        let mut last = f32::MIN;
+        for (i, (button, _)) in BUTTONS.iter().enumerate() {
+            if buttons.contains(*button) {
+                let t = self.pressed_at[i].unwrap_or(self.time);
+                first = first.min(t);
+                last = last.max(t);
+            }
+        }
+        last - first <= window
+    }
+
+    fn sequence_entered(&self, sequence: &[Buttons], max_gap: f32) -> bool {
+        if sequence.is_empty() || sequence.len() > self.history.len() {
+            return false;
+        }
+        let presses = self.history.iter().rev().take(sequence.len());
+        let mut next_time = self.time;
+        for (i, (press, expected)) in presses.zip(sequence.iter().rev()).enumerate() {
+            // The last press of the sequence must happen in this frame
+            let gap = if i == 0 { 0.0 } else { max_gap };
+            if press.button != *expected || next_time - press.time > gap {
+                return false;
+            }
+            next_time = press.time;
+        }
+        true
+    }
+}
+
+/// Sample the buttons once per frame. Called by the runtime before `App::update`.
+pub(crate) fn update(delta: f32) {
+    STATE.lock().update(delta);
+}
+
+/// Returns `true` if any of the given buttons is currently down.
+pub fn is_pressed(buttons: Buttons) -> bool {
+    STATE.lock().current.intersects(buttons)
+}
+
+/// Returns `true` if any of the given buttons was pushed during the last frame.
+pub fn just_pressed(buttons: Buttons) -> bool {
+    STATE.lock().pushed.intersects(buttons)
+}
+
+/// Returns `true` if any of the given buttons was released during the last frame.
+pub fn just_released(buttons: Buttons) -> bool {
+    STATE.lock().released.intersects(buttons)
+}
+
+/// Returns how long, in seconds, all of the given buttons have been held down. Returns 0.0 if any of them is up.
+pub fn get_hold_duration(buttons: Buttons) -> f32 {
+    STATE.lock().get_hold_duration(buttons)
+}
+
+/// Sets the key repeat timing used by [`is_repeated`]: a held button fires once when pushed, once more after `delay` seconds, and then every `interval` seconds.
+pub fn set_key_repeat(delay: f32, interval: f32) {
+    debug_assert!(
+        delay >= 0.0 && interval > 0.0,
+        "invalid key repeat: {} {}",
+        delay,
+        interval
+    );
+    let mut state = STATE.lock();
+    state.repeat_delay = delay;
+    state.repeat_interval = interval;
+}
+
+/// Returns `true` if any of the given buttons was pushed during the last frame, or is held down and its key repeat fired during the last frame. Useful for scrolling through menus.
+pub fn is_repeated(buttons: Buttons) -> bool {
+    STATE.lock().repeated.intersects(buttons)
+}
+
+/// Returns `true` and consumes the press if any of the given buttons was pushed within the last `window` seconds and the press was not consumed before.
+///
+/// This gives players some leniency for inputs that arrive slightly early, e.g. pressing jump just before landing.
+pub fn consume_buffered_press(buttons: Buttons, window: f32) -> bool {
+    STATE.lock().consume_buffered_press(buttons, window)
+}
+
+/// Forgets all buffered button presses.
+pub fn clear_buffer() {
+    STATE.lock().history.clear();
+}
+
+/// Returns `true` during the frame where all of the given buttons become held down together, with all of them pushed within `window` seconds of each other.
+pub fn combo_pressed(buttons: Buttons, window: f32) -> bool {
+    STATE.lock().combo_pressed(buttons, window)
+}
+
+/// Returns `true` during the frame where the last button of `sequence` is pushed, if the buttons pushed before it match the rest of the sequence and no more than `max_gap` seconds passed between two consecutive presses.
+///
+/// ```ignore
+/// use playdate_rs::system::Buttons;
+/// let konami = [Buttons::Up, Buttons::Up, Buttons::Down, Buttons::Down, Buttons::B, Buttons::A];
+/// if playdate_rs::input::sequence_entered(&konami, 0.5) {
+///     // ...
+/// }
+/// ```
+pub fn sequence_entered(sequence: &[Buttons], max_gap: f32) -> bool {
+    STATE.lock().sequence_entered(sequence, max_gap)
+}
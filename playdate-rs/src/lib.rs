@@ -1,6 +1,7 @@
 #![cfg_attr(all(target_arch = "arm", target_os = "none"), no_std)]
 
 extern crate alloc;
+pub extern crate log;
 pub extern crate num_traits;
 #[doc(hidden)]
 pub extern crate playdate_rs_sys as sys;
@@ -13,24 +14,37 @@ pub mod print;
 #[macro_use]
 pub mod math;
 
+pub mod assets;
+pub mod crank;
+pub mod crash;
 pub mod display;
 pub mod error;
 pub mod fs;
 pub mod graphics;
+#[cfg(all(
+    feature = "headless",
+    not(all(target_arch = "arm", target_os = "none"))
+))]
+mod headless;
+pub mod input;
+pub mod logger;
 pub mod lua;
-mod memory;
+pub mod memory;
+pub mod profiler;
 pub mod scoreboards;
 pub mod sound;
 pub mod sprite;
+pub mod storage;
 pub mod system;
+pub mod ui;
 pub mod util;
 pub mod video;
 
 use core::{cell::UnsafeCell, ops::Deref};
 
-use alloc::{boxed::Box, format};
+use alloc::boxed::Box;
 pub use no_std_io::io;
-pub use playdate_rs_macros::app;
+pub use playdate_rs_macros::{app, include_assets};
 
 pub struct PlaydateAPI {
     raw_api: *mut sys::PlaydateAPI,
@@ -94,7 +108,14 @@ impl Deref for Playdate {
     type Target = PlaydateAPI;
 
     fn deref(&self) -> &Self::Target {
-        unsafe { (*self._p.get()).as_ref().unwrap() }
+        let api = unsafe { (*self._p.get()).as_ref() };
+        // Tests are not started by the playdate runtime, use the headless shim instead
+        #[cfg(all(
+            feature = "headless",
+            not(all(target_arch = "arm", target_os = "none"))
+        ))]
+        let api = api.or_else(|| Some(headless::api()));
+        api.unwrap()
     }
 }
 
@@ -122,8 +143,38 @@ pub trait App: Sized + 'static {
     /// `delta` is the time in seconds since the last frame.
     fn update(&mut self, _delta: f32) {}
 
-    /// Called when a system event occurs.
+    /// Called when a system event occurs, before the matching lifecycle method below.
     fn handle_event(&mut self, _event: system::SystemEvent, _arg: u32) {}
+
+    /// Called when the system menu is opened and the game is paused.
+    fn on_pause(&mut self) {}
+
+    /// Called when the system menu is closed and the game resumes.
+    fn on_resume(&mut self) {}
+
+    /// Called when the device is locked. [`App::save_state`] is called right after this.
+    fn on_lock(&mut self) {}
+
+    /// Called when the device is unlocked.
+    fn on_unlock(&mut self) {}
+
+    /// Called when the game is about to terminate, either because the player quit it or because the device is shutting down. [`App::save_state`] is called right after this.
+    fn on_terminate(&mut self) {}
+
+    /// Called when the battery is running low.
+    fn on_low_power(&mut self) {}
+
+    /// Persists the game state, e.g. with [`storage::save`]. Called automatically when the game terminates and when the device is locked.
+    fn save_state(&self) -> Result<(), error::Error> {
+        Ok(())
+    }
+
+    /// Restores the state persisted by [`App::save_state`], e.g. with [`storage::load`]. Called automatically at startup, after [`App::new`] and before [`App::init`].
+    ///
+    /// Return [`error::Error::FileNotExists`] if there is no saved state yet, e.g. on first launch. Other errors are logged to the console.
+    fn restore_state(&mut self) -> Result<(), error::Error> {
+        Ok(())
+    }
 }
 
 static mut APP: Option<*mut ()> = None;
@@ -142,8 +193,16 @@ unsafe extern "C" fn update<T: App>(_: *mut core::ffi::c_void) -> i32 {
         LAST_FRAME_TIME = Some(current_time);
         delta
     };
+    system::FRAME_NUMBER.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+    memory::begin_frame();
+    profiler::begin_frame();
+    // sample input helpers
+    crank::update(delta_time);
+    input::update(delta_time);
     // update frame
     app.update(delta_time);
+    profiler::end_frame();
+    memory::reset_frame_arena();
     1
 }
 
@@ -152,12 +211,19 @@ fn start_playdate_app<T: App>(pd: *mut sys::PlaydateAPI) {
     unsafe {
         *PLAYDATE._p.get() = Some(PlaydateAPI::new(pd));
     }
+    logger::init();
+    #[cfg(not(all(target_arch = "arm", target_os = "none")))]
+    crash::install_std_hook();
     // Create app instance
     let app = Box::leak(Box::new(T::new()));
     unsafe {
         APP = Some(app as *mut T as *mut ());
     }
-    // Initialize app
+    // Restore the persisted state and initialize app
+    match app.restore_state() {
+        Ok(()) | Err(error::Error::FileNotExists(_)) => {}
+        Err(e) => println!("Failed to restore app state: {:?}", e),
+    }
     app.init();
     PLAYDATE.system.set_update_callback(Some(update::<T>));
 }
@@ -172,13 +238,51 @@ pub fn __playdate_handle_event<T: App>(
     if event == system::SystemEvent::Init {
         start_playdate_app::<T>(pd);
     }
-    unsafe { T::get_mut().handle_event(event, arg) };
+    let app = unsafe { T::get_mut() };
+    app.handle_event(event, arg);
+    // Dispatch to the lifecycle methods
+    let save = match event {
+        system::SystemEvent::Pause => {
+            app.on_pause();
+            false
+        }
+        system::SystemEvent::Resume => {
+            app.on_resume();
+            false
+        }
+        system::SystemEvent::Lock => {
+            app.on_lock();
+            true
+        }
+        system::SystemEvent::Unlock => {
+            app.on_unlock();
+            false
+        }
+        system::SystemEvent::Terminate => {
+            app.on_terminate();
+            true
+        }
+        system::SystemEvent::LowPower => {
+            app.on_low_power();
+            false
+        }
+        _ => false,
+    };
+    if save {
+        if let Err(e) = app.save_state() {
+            println!("Failed to save app state: {:?}", e);
+        }
+    }
 }
 
 #[doc(hidden)]
 pub fn __playdate_handle_panic(info: &core::panic::PanicInfo) -> ! {
-    PLAYDATE.system.error(format!("{}", info));
-    unreachable!()
+    // Nothing can be reported before the API is available
+    if unsafe { (*PLAYDATE._p.get()).is_some() } {
+        crash::handle_panic(info.message(), info.location());
+    }
+    #[allow(clippy::empty_loop)]
+    loop {}
 }
 
 #[macro_export]
@@ -218,16 +322,15 @@ macro_rules! register_playdate_app {
         #[no_mangle]
         extern "C" fn _getpid() {}
 
+        // Empty exception index table, referenced by the unwinding code of libgcc
         #[cfg(all(target_arch = "arm", target_os = "none"))]
-        #[no_mangle]
-        extern "C" fn __exidx_start() {
-            unimplemented!();
-        }
-
-        #[cfg(all(target_arch = "arm", target_os = "none"))]
-        #[no_mangle]
-        extern "C" fn __exidx_end() {
-            unimplemented!();
-        }
+        ::core::arch::global_asm!(
+            ".section .rodata.__exidx,\"a\"",
+            ".globl __exidx_start",
+            ".globl __exidx_end",
+            "__exidx_start:",
+            "__exidx_end:",
+            ".previous",
+        );
     };
 }
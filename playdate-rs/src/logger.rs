@@ -0,0 +1,286 @@
+use alloc::{
+    collections::VecDeque,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Write;
+
+use log::{LevelFilter, Log, Metadata, Record};
+use no_std_io::io;
+use spin::Mutex;
+
+use crate::{
+    fs::Path,
+    graphics::{Color, Font},
+    math::{Rect, Vec2},
+    PLAYDATE,
+};
+
+pub use log::{debug, error, info, trace, warn};
+
+/// Default number of lines kept in the history.
+pub const DEFAULT_HISTORY_SIZE: usize = 64;
+/// Line height used by [`draw_history`] when no font is given, matching the system font.
+const SYSTEM_FONT_LINE_HEIGHT: i32 = 18;
+
+/// Default level: everything in debug builds, `info` and above in release builds. Games can also remove `debug!` and `trace!` from release builds at compile time by enabling the `strip_debug_logs` feature.
+const DEFAULT_LEVEL: LevelFilter = if cfg!(debug_assertions) {
+    LevelFilter::Trace
+} else {
+    LevelFilter::Info
+};
+
+struct LoggerState {
+    level: LevelFilter,
+    module_levels: Vec<(String, LevelFilter)>,
+    show_frame_number: bool,
+    show_timestamp: bool,
+    history: VecDeque<String>,
+    history_size: usize,
+}
+
+impl LoggerState {
+    const fn new() -> Self {
+        Self {
+            level: DEFAULT_LEVEL,
+            module_levels: Vec::new(),
+            show_frame_number: true,
+            show_timestamp: false,
+            history: VecDeque::new(),
+            history_size: DEFAULT_HISTORY_SIZE,
+        }
+    }
+
+    /// Returns the level of the longest module prefix matching `target`, or the global level.
+    fn get_level_for(&self, target: &str) -> LevelFilter {
+        self.module_levels
+            .iter()
+            .filter(|(module, _)| is_module_prefix(module, target))
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.level)
+    }
+
+    /// The `log` macros skip records above the max level without calling the logger, so it must be the most verbose of all levels.
+    fn update_max_level(&self) {
+        let max = self
+            .module_levels
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, Ord::max);
+        log::set_max_level(max);
+    }
+
+    /// Returns the frame number and timestamp prefixes of a line
+    fn format_prefix(&self) -> String {
+        let mut prefix = String::new();
+        if self.show_frame_number {
+            let _ = write!(prefix, "[{}] ", PLAYDATE.system.get_frame_number());
+        }
+        if self.show_timestamp {
+            let ms = PLAYDATE.system.get_current_time_milliseconds();
+            let _ = write!(prefix, "[{}.{:03}] ", ms / 1000, ms % 1000);
+        }
+        prefix
+    }
+
+    fn push_history(&mut self, line: String) {
+        if self.history_size == 0 {
+            return;
+        }
+        while self.history.len() >= self.history_size {
+            self.history.pop_front();
+        }
+        self.history.push_back(line);
+    }
+}
+
+static STATE: Mutex<LoggerState> = Mutex::new(LoggerState::new());
+
+/// `log::Log` implementation writing to the console with `logToConsole`.
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= STATE.lock().get_level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        let mut line = {
+            let state = STATE.lock();
+            if record.level() > state.get_level_for(record.target()) {
+                return;
+            }
+            state.format_prefix()
+        };
+        // The arguments may log or panic, so they are formatted without holding the lock
+        let _ = write!(
+            line,
+            "{:<5} {}: {}",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        PLAYDATE.system.log_to_console(&line);
+        STATE.lock().push_history(line);
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger. Called once at startup, before `App::new`.
+pub(crate) fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        STATE.lock().update_max_level();
+    }
+}
+
+/// Returns `true` if `module` is `target` or one of its parent modules.
+fn is_module_prefix(module: &str, target: &str) -> bool {
+    match target.strip_prefix(module) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+/// Sets the level of messages logged by modules that have no specific level set with [`set_module_level`].
+pub fn set_level(level: LevelFilter) {
+    let mut state = STATE.lock();
+    state.level = level;
+    state.update_max_level();
+}
+
+/// Returns the global log level.
+pub fn get_level() -> LevelFilter {
+    STATE.lock().level
+}
+
+/// Sets the log level of a module and its submodules, e.g. `set_module_level("my_game::physics", LevelFilter::Warn)`. The most specific module wins.
+pub fn set_module_level(module: impl AsRef<str>, level: LevelFilter) {
+    let module = module.as_ref();
+    let mut state = STATE.lock();
+    match state.module_levels.iter_mut().find(|(m, _)| m == module) {
+        Some((_, l)) => *l = level,
+        None => state.module_levels.push((module.to_string(), level)),
+    }
+    state.update_max_level();
+}
+
+/// Removes the log level of a module set with [`set_module_level`], so it uses the global level again.
+pub fn reset_module_level(module: impl AsRef<str>) {
+    let mut state = STATE.lock();
+    state.module_levels.retain(|(m, _)| m != module.as_ref());
+    state.update_max_level();
+}
+
+/// Sets whether log lines are prefixed with the frame number. Enabled by default.
+pub fn set_show_frame_number(show: bool) {
+    STATE.lock().show_frame_number = show;
+}
+
+/// Sets whether log lines are prefixed with the time in seconds since the game started. Disabled by default.
+pub fn set_show_timestamp(show: bool) {
+    STATE.lock().show_timestamp = show;
+}
+
+/// Sets how many of the most recent log lines are kept in memory. Defaults to [`DEFAULT_HISTORY_SIZE`], 0 disables the history.
+pub fn set_history_size(size: usize) {
+    let mut state = STATE.lock();
+    state.history_size = size;
+    while state.history.len() > size {
+        state.history.pop_front();
+    }
+}
+
+/// Returns the most recent log lines, oldest first.
+pub fn get_history() -> Vec<String> {
+    STATE.lock().history.iter().cloned().collect()
+}
+
+/// Returns the history without waiting for the lock, e.g. when panicking while logging.
+pub(crate) fn try_get_history() -> Option<Vec<String>> {
+    Some(STATE.try_lock()?.history.iter().cloned().collect())
+}
+
+/// Clears the history of recent log lines.
+pub fn clear_history() {
+    STATE.lock().history.clear();
+}
+
+/// Writes the most recent log lines to a file in the game's data folder, e.g. to attach it to a bug report.
+pub fn dump_history(path: impl AsRef<Path>) -> io::Result<()> {
+    let mut contents = String::new();
+    for line in STATE.lock().history.iter() {
+        contents.push_str(line);
+        contents.push('\n');
+    }
+    crate::fs::write(path, contents)
+}
+
+/// Draws the most recent log lines that fit in `rect` on a white background, newest at the bottom. Uses the current font if `font` is `None`.
+pub fn draw_history(rect: Rect<i32>, font: Option<&Font>) {
+    let line_height = match font {
+        Some(font) => {
+            PLAYDATE.graphics.set_font(font);
+            font.get_height() as i32
+        }
+        None => SYSTEM_FONT_LINE_HEIGHT,
+    };
+    let lines = get_history();
+    PLAYDATE.graphics.fill_rect(rect, Color::White);
+    PLAYDATE.graphics.set_clip_rect(rect);
+    let mut y = rect.y + rect.height - line_height;
+    for line in lines.iter().rev() {
+        if y + line_height <= rect.y {
+            break;
+        }
+        PLAYDATE.graphics.draw_text(line, Vec2::new(rect.x, y));
+        y -= line_height;
+    }
+    PLAYDATE.graphics.clear_clip_rect();
+}
+
+/// Draws the most recent log lines over the whole screen into the simulator's debug bitmap, which is shown on top of the game. Does nothing on device.
+pub fn draw_history_to_debug_bitmap(font: Option<&Font>) {
+    // `getDebugBitmap` is NULL on device
+    if cfg!(all(target_arch = "arm", target_os = "none")) {
+        return;
+    }
+    let Some(bitmap) = PLAYDATE.graphics.get_debug_bitmap() else {
+        return;
+    };
+    let size = bitmap.get_bitmap_data().size;
+    bitmap.clear(Color::Clear);
+    PLAYDATE.graphics.push_context(&*bitmap);
+    draw_history(Rect::new(0, 0, size.width, size.height), font);
+    PLAYDATE.graphics.pop_context();
+}
+
+#[cfg(all(test, feature = "headless"))]
+mod tests {
+    use core::fmt;
+
+    use crate::PLAYDATE;
+
+    struct LogsWhenFormatted;
+
+    impl fmt::Display for LogsWhenFormatted {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            log::warn!("inner");
+            f.write_str("outer")
+        }
+    }
+
+    #[test]
+    fn log_while_formatting() {
+        // Installs the logger
+        PLAYDATE.system.get_frame_number();
+        log::warn!("{}", LogsWhenFormatted);
+        let history = super::get_history();
+        let inner = history.iter().position(|l| l.ends_with(": inner"));
+        let outer = history.iter().position(|l| l.ends_with(": outer"));
+        assert!(inner.is_some() && inner < outer, "{:?}", history);
+    }
+}
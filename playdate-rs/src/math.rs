@@ -778,6 +778,7 @@ impl From<sys::CollisionVector> for Vec2<i32> {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct SideOffsets<T> {
     pub left: T,
     pub right: T,
@@ -0,0 +1,249 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    alloc::Layout,
+    cell::{Cell, RefCell, UnsafeCell},
+    fmt,
+    mem::MaybeUninit,
+    ptr::{self, NonNull},
+    str,
+};
+
+/// Default capacity of the frame arena, in bytes. The arena grows if a frame needs more.
+pub const DEFAULT_FRAME_ARENA_CAPACITY: usize = 16 * 1024;
+
+/// A bump allocator: allocations are a pointer increment, and everything is freed at once by [`Arena::reset`].
+///
+/// Values allocated in the arena are never dropped, so types owning heap memory (`Vec`, `String`, `Box`...) leak it. Allocate plain data, slices and strings instead.
+///
+/// When a chunk is full, a new one twice as large is allocated. [`Arena::reset`] merges the chunks into a single one, so a game with a steady memory usage per frame stops allocating after a few frames.
+///
+/// ```ignore
+/// let arena = Arena::with_capacity(4096);
+/// let points = arena.alloc_slice_fill_with(16, |i| vec2!(i as f32, 0.0));
+/// let label = arena.alloc_fmt(format_args!("score: {}", score));
+/// ```
+pub struct Arena {
+    chunks: RefCell<Vec<Box<[MaybeUninit<u8>]>>>,
+    /// Start and end of the free space of the current chunk
+    ptr: Cell<*mut u8>,
+    end: Cell<*mut u8>,
+    allocated: Cell<usize>,
+}
+
+impl Arena {
+    /// Creates an arena with a first chunk of `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let arena = Self {
+            chunks: RefCell::new(Vec::new()),
+            ptr: Cell::new(ptr::null_mut()),
+            end: Cell::new(ptr::null_mut()),
+            allocated: Cell::new(0),
+        };
+        arena.add_chunk(capacity.max(64));
+        arena
+    }
+
+    /// Returns the number of bytes allocated since the last reset, excluding alignment padding.
+    pub fn get_allocated_bytes(&self) -> usize {
+        self.allocated.get()
+    }
+
+    /// Returns the total size of the chunks, in bytes.
+    pub fn get_capacity(&self) -> usize {
+        self.chunks.borrow().iter().map(|c| c.len()).sum()
+    }
+
+    /// Frees everything allocated in the arena. If the arena grew, its chunks are replaced by a single chunk of the total size.
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+        if chunks.len() > 1 {
+            let capacity = chunks.iter().map(|c| c.len()).sum();
+            chunks.clear();
+            self.add_chunk(capacity);
+        } else if let Some(chunk) = chunks.last_mut() {
+            let range = chunk.as_mut_ptr_range();
+            self.ptr.set(range.start as *mut u8);
+            self.end.set(range.end as *mut u8);
+        }
+        self.allocated.set(0);
+    }
+
+    fn add_chunk(&self, capacity: usize) {
+        let mut chunk = Box::new_uninit_slice(capacity);
+        let range = chunk.as_mut_ptr_range();
+        self.ptr.set(range.start as *mut u8);
+        self.end.set(range.end as *mut u8);
+        self.chunks.borrow_mut().push(chunk);
+    }
+
+    /// Allocates memory for `layout`. The memory is uninitialized.
+    pub fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
+        if let Some(ptr) = self.try_bump(layout) {
+            return ptr;
+        }
+        let last = self.chunks.borrow().last().map_or(0, |c| c.len());
+        self.add_chunk((last * 2).max(layout.size() + layout.align()));
+        self.try_bump(layout).unwrap()
+    }
+
+    fn try_bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let ptr = self.ptr.get();
+        let padding = ptr.align_offset(layout.align());
+        let available = self.end.get() as usize - ptr as usize;
+        if padding.checked_add(layout.size())? > available {
+            return None;
+        }
+        // SAFETY: the allocation is within the current chunk
+        unsafe {
+            let start = ptr.add(padding);
+            self.ptr.set(start.add(layout.size()));
+            self.allocated.set(self.allocated.get() + layout.size());
+            Some(NonNull::new_unchecked(start))
+        }
+    }
+
+    /// Moves `value` into the arena. It is never dropped.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, value: T) -> &mut T {
+        let ptr = self.alloc_layout(Layout::new::<T>()).cast::<T>().as_ptr();
+        // SAFETY: the memory is allocated for a `T` and not handed out elsewhere
+        unsafe {
+            ptr.write(value);
+            &mut *ptr
+        }
+    }
+
+    /// Allocates a slice of `len` elements created by `f`, called with the index of each element.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_fill_with<T>(&self, len: usize, mut f: impl FnMut(usize) -> T) -> &mut [T] {
+        let layout = Layout::array::<T>(len).unwrap();
+        let ptr = self.alloc_layout(layout).cast::<T>().as_ptr();
+        // SAFETY: every element is initialized before the slice is created
+        unsafe {
+            for i in 0..len {
+                ptr.add(i).write(f(i));
+            }
+            core::slice::from_raw_parts_mut(ptr, len)
+        }
+    }
+
+    /// Copies a slice into the arena.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy>(&self, src: &[T]) -> &mut [T] {
+        self.alloc_slice_fill_with(src.len(), |i| src[i])
+    }
+
+    /// Copies a string into the arena.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_str(&self, s: &str) -> &mut str {
+        let bytes = self.alloc_slice_copy(s.as_bytes());
+        // SAFETY: the bytes are copied from a valid string
+        unsafe { str::from_utf8_unchecked_mut(bytes) }
+    }
+
+    /// Formats a string into the arena, like `format!` but without using the heap.
+    pub fn alloc_fmt(&self, args: fmt::Arguments) -> &str {
+        if let Some(s) = args.as_str() {
+            return self.alloc_str(s);
+        }
+        // Measure first, so the string is contiguous
+        struct Counter(usize);
+        impl fmt::Write for Counter {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0 += s.len();
+                Ok(())
+            }
+        }
+        struct Writer<'a>(&'a mut [u8], usize);
+        impl fmt::Write for Writer<'_> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                let end = self.1 + s.len();
+                let dst = self.0.get_mut(self.1..end).ok_or(fmt::Error)?;
+                dst.copy_from_slice(s.as_bytes());
+                self.1 = end;
+                Ok(())
+            }
+        }
+        let mut counter = Counter(0);
+        let _ = fmt::write(&mut counter, args);
+        let buf = self.alloc_slice_fill_with(counter.0, |_| 0u8);
+        let mut writer = Writer(&mut *buf, 0);
+        let _ = fmt::write(&mut writer, args);
+        let len = writer.1;
+        // SAFETY: only complete `str`s were copied into the buffer
+        unsafe { str::from_utf8_unchecked(&buf[..len]) }
+    }
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_FRAME_ARENA_CAPACITY)
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena")
+            .field("allocated", &self.get_allocated_bytes())
+            .field("capacity", &self.get_capacity())
+            .finish()
+    }
+}
+
+/// Allocates collections in the arena, e.g. `Vec::new_in(&arena)`. Deallocation is a no-op, the memory is freed by [`Arena::reset`].
+#[cfg(feature = "allocator_api")]
+unsafe impl core::alloc::Allocator for &Arena {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        let ptr = self.alloc_layout(layout);
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+/// The frame arena. The game is single-threaded, and it is only reset by the run loop, outside of [`with_frame_arena`].
+struct FrameArena {
+    arena: UnsafeCell<Option<Arena>>,
+    capacity: Cell<usize>,
+}
+
+unsafe impl Sync for FrameArena {}
+
+static FRAME_ARENA: FrameArena = FrameArena {
+    arena: UnsafeCell::new(None),
+    capacity: Cell::new(DEFAULT_FRAME_ARENA_CAPACITY),
+};
+
+/// Runs `f` with the frame arena, which is reset automatically after each `App::update`.
+///
+/// Allocations in the frame arena cannot escape the closure, but they stay valid until the end of the frame, so `with_frame_arena` can be called many times per frame without losing memory.
+///
+/// ```ignore
+/// with_frame_arena(|arena| {
+///     let text = arena.alloc_fmt(format_args!("{} fps", fps));
+///     PLAYDATE.graphics.draw_text(text, vec2!(0, 0));
+/// });
+/// ```
+pub fn with_frame_arena<R>(f: impl FnOnce(&Arena) -> R) -> R {
+    let cell = FRAME_ARENA.arena.get();
+    // SAFETY: the arena is only mutated when it is created, which cannot happen while it is borrowed, and by `reset_frame_arena`, never while `f` runs
+    unsafe {
+        if (*cell).is_none() {
+            *cell = Some(Arena::with_capacity(FRAME_ARENA.capacity.get()));
+        }
+        f((*cell).as_ref().unwrap())
+    }
+}
+
+/// Sets the initial capacity of the frame arena, in bytes. Only has an effect before the frame arena is first used. Defaults to [`DEFAULT_FRAME_ARENA_CAPACITY`].
+pub fn set_frame_arena_capacity(capacity: usize) {
+    FRAME_ARENA.capacity.set(capacity);
+}
+
+/// Frees the allocations of the frame arena. Called after each `App::update`.
+pub(crate) fn reset_frame_arena() {
+    // SAFETY: called by the run loop, outside of `with_frame_arena`
+    if let Some(arena) = unsafe { &mut *FRAME_ARENA.arena.get() } {
+        arena.reset();
+    }
+}
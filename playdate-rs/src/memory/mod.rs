@@ -0,0 +1,269 @@
+mod arena;
+mod pool;
+
+use alloc::alloc::GlobalAlloc;
+use core::{
+    alloc::Layout,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::PLAYDATE;
+
+pub(crate) use arena::reset_frame_arena;
+pub use arena::{set_frame_arena_capacity, with_frame_arena, Arena, DEFAULT_FRAME_ARENA_CAPACITY};
+pub use pool::{Pool, Pooled};
+
+/// Alignment guaranteed by the system `realloc`. Larger alignments are handled by over-allocating.
+const MIN_ALIGN: usize = 8;
+/// Number of size classes recorded in tracking mode. Class `i` holds allocations of up to `16 << i` bytes, the last class holds all larger allocations.
+pub const SIZE_CLASS_COUNT: usize = 12;
+
+/// Heap statistics of the global allocator, returned by [`stats`]. Only collected on device, where the Playdate heap is the global allocator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Bytes currently allocated.
+    pub live_bytes: usize,
+    /// Highest number of bytes allocated at once since the game started.
+    pub peak_bytes: usize,
+    /// Number of allocations currently alive.
+    pub live_allocations: usize,
+    /// Number of allocations, including reallocations, since the game started.
+    pub total_allocations: usize,
+    /// Number of allocations the system could not satisfy.
+    pub failed_allocations: usize,
+    /// Number of allocations, including reallocations, during the last complete frame.
+    pub last_frame_allocations: usize,
+}
+
+/// Allocation counts of a size class, returned by [`size_classes`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeClassStats {
+    /// Largest allocation size of this class, or `None` for the last class.
+    pub max_size: Option<usize>,
+    /// Number of allocations of this class currently alive.
+    pub live: usize,
+    /// Number of allocations of this class since tracking was enabled.
+    pub total: usize,
+}
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+static LIVE_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static TOTAL_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static FAILED_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static FRAME_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static LAST_FRAME_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static FRAME_ALLOCATION_WARNING: AtomicUsize = AtomicUsize::new(0);
+
+static TRACKING: AtomicBool = AtomicBool::new(false);
+static SIZE_CLASS_LIVE: [AtomicUsize; SIZE_CLASS_COUNT] =
+    [const { AtomicUsize::new(0) }; SIZE_CLASS_COUNT];
+static SIZE_CLASS_TOTAL: [AtomicUsize; SIZE_CLASS_COUNT] =
+    [const { AtomicUsize::new(0) }; SIZE_CLASS_COUNT];
+
+/// Returns the heap statistics of the global allocator.
+pub fn stats() -> MemoryStats {
+    MemoryStats {
+        live_bytes: LIVE_BYTES.load(Ordering::Relaxed),
+        peak_bytes: PEAK_BYTES.load(Ordering::Relaxed),
+        live_allocations: LIVE_ALLOCATIONS.load(Ordering::Relaxed),
+        total_allocations: TOTAL_ALLOCATIONS.load(Ordering::Relaxed),
+        failed_allocations: FAILED_ALLOCATIONS.load(Ordering::Relaxed),
+        last_frame_allocations: LAST_FRAME_ALLOCATIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// Returns the number of allocations made so far in the current frame.
+pub fn get_frame_allocations() -> usize {
+    FRAME_ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+/// Logs a warning for every frame making more than `max` allocations, to find allocations in hot loops. `None` disables the warning, which is the default.
+pub fn set_frame_allocation_warning(max: Option<usize>) {
+    FRAME_ALLOCATION_WARNING.store(max.map_or(0, |max| max + 1), Ordering::Relaxed);
+}
+
+/// Enables or disables the tracking mode, which records allocations by size class. Disabled by default, as it makes every allocation slightly slower.
+///
+/// Enabling tracking resets the size class statistics.
+pub fn set_tracking(enabled: bool) {
+    if enabled && !TRACKING.load(Ordering::Relaxed) {
+        for i in 0..SIZE_CLASS_COUNT {
+            SIZE_CLASS_LIVE[i].store(0, Ordering::Relaxed);
+            SIZE_CLASS_TOTAL[i].store(0, Ordering::Relaxed);
+        }
+    }
+    TRACKING.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns `true` if the tracking mode is enabled.
+pub fn is_tracking() -> bool {
+    TRACKING.load(Ordering::Relaxed)
+}
+
+/// Returns the allocation counts per size class recorded in tracking mode, smallest class first.
+///
+/// Only allocations made while tracking is enabled are counted, so live counts are only meaningful if tracking was enabled at startup.
+pub fn size_classes() -> [SizeClassStats; SIZE_CLASS_COUNT] {
+    core::array::from_fn(|i| SizeClassStats {
+        max_size: (i + 1 < SIZE_CLASS_COUNT).then_some(16 << i),
+        live: SIZE_CLASS_LIVE[i].load(Ordering::Relaxed),
+        total: SIZE_CLASS_TOTAL[i].load(Ordering::Relaxed),
+    })
+}
+
+/// Logs the heap statistics, and the allocations still alive per size class if tracking is enabled. Calling it before and after a level or a scene helps finding leaks.
+pub fn log_report() {
+    let stats = stats();
+    log::info!(
+        "heap: {} bytes in {} allocations (peak {} bytes), {} allocations in total, {} failed, {} last frame",
+        stats.live_bytes,
+        stats.live_allocations,
+        stats.peak_bytes,
+        stats.total_allocations,
+        stats.failed_allocations,
+        stats.last_frame_allocations,
+    );
+    if !is_tracking() {
+        return;
+    }
+    for class in size_classes().iter().filter(|c| c.live > 0 || c.total > 0) {
+        match class.max_size {
+            Some(max_size) => log::info!(
+                "  <= {} bytes: {} live, {} total",
+                max_size,
+                class.live,
+                class.total
+            ),
+            None => log::info!(
+                "  > {} bytes: {} live, {} total",
+                16 << (SIZE_CLASS_COUNT - 2),
+                class.live,
+                class.total
+            ),
+        }
+    }
+}
+
+/// Starts counting the allocations of a new frame. Called before each `App::update`.
+pub(crate) fn begin_frame() {
+    let count = FRAME_ALLOCATIONS.swap(0, Ordering::Relaxed);
+    LAST_FRAME_ALLOCATIONS.store(count, Ordering::Relaxed);
+    let warning = FRAME_ALLOCATION_WARNING.load(Ordering::Relaxed);
+    if warning != 0 && count >= warning {
+        log::warn!(
+            "frame {} made {} allocations",
+            PLAYDATE.system.get_frame_number(),
+            count
+        );
+    }
+}
+
+fn get_size_class(size: usize) -> usize {
+    let class = (usize::BITS - (size.max(1) - 1).leading_zeros()).saturating_sub(4);
+    (class as usize).min(SIZE_CLASS_COUNT - 1)
+}
+
+fn record_alloc(size: usize) {
+    let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+    LIVE_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    TOTAL_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    FRAME_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    if TRACKING.load(Ordering::Relaxed) {
+        let class = get_size_class(size);
+        SIZE_CLASS_LIVE[class].fetch_add(1, Ordering::Relaxed);
+        SIZE_CLASS_TOTAL[class].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn record_dealloc(size: usize) {
+    LIVE_BYTES.fetch_sub(size, Ordering::Relaxed);
+    LIVE_ALLOCATIONS.fetch_sub(1, Ordering::Relaxed);
+    if TRACKING.load(Ordering::Relaxed) {
+        // Allocations made before tracking was enabled are not counted
+        let live = &SIZE_CLASS_LIVE[get_size_class(size)];
+        if live.load(Ordering::Relaxed) > 0 {
+            live.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn record_failure() {
+    FAILED_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+struct PlaydateHeapAllocator;
+
+impl PlaydateHeapAllocator {
+    /// Allocates `size + align` bytes and stores the pointer returned by the system right before the aligned pointer.
+    unsafe fn alloc_aligned(&self, layout: Layout) -> *mut u8 {
+        let raw = PLAYDATE
+            .system
+            .realloc(ptr::null_mut(), layout.size() + layout.align()) as *mut u8;
+        if raw.is_null() {
+            return raw;
+        }
+        // `raw` is aligned to `MIN_ALIGN`, so there are at least `MIN_ALIGN` bytes before the aligned pointer
+        let offset = layout.align() - (raw as usize & (layout.align() - 1));
+        let aligned = raw.add(offset);
+        (aligned as *mut *mut u8).sub(1).write(raw);
+        aligned
+    }
+
+    unsafe fn dealloc_aligned(&self, ptr: *mut u8) {
+        let raw = (ptr as *mut *mut u8).sub(1).read();
+        PLAYDATE.system.realloc(raw as *mut _, 0);
+    }
+}
+
+unsafe impl GlobalAlloc for PlaydateHeapAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = if layout.align() <= MIN_ALIGN {
+            PLAYDATE.system.realloc(ptr::null_mut(), layout.size()) as *mut u8
+        } else {
+            self.alloc_aligned(layout)
+        };
+        if ptr.is_null() {
+            record_failure();
+        } else {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if layout.align() <= MIN_ALIGN {
+            PLAYDATE.system.realloc(ptr as *mut _, 0);
+        } else {
+            self.dealloc_aligned(ptr);
+        }
+        record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if layout.align() > MIN_ALIGN {
+            // Allocate, copy and free, keeping the alignment
+            let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+            let new_ptr = self.alloc(new_layout);
+            if !new_ptr.is_null() {
+                ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+                self.dealloc(ptr, layout);
+            }
+            return new_ptr;
+        }
+        let new_ptr = PLAYDATE.system.realloc(ptr as *mut _, new_size) as *mut u8;
+        if new_ptr.is_null() {
+            record_failure();
+        } else {
+            record_dealloc(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+#[cfg(target_os = "none")]
+#[global_allocator]
+static GLOBAL: PlaydateHeapAllocator = PlaydateHeapAllocator;
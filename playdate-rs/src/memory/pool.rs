@@ -0,0 +1,136 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    cell::RefCell,
+    fmt,
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+};
+
+type ResetFn<T> = Box<dyn Fn(&mut T)>;
+
+/// A pool of reusable objects, to avoid allocating and freeing objects such as bullets or particles every frame.
+///
+/// [`Pool::get`] returns an object from the pool, or creates one if the pool is empty. The object goes back to the pool when the returned [`Pooled`] guard is dropped, after being cleaned by the reset function.
+///
+/// ```ignore
+/// let pool = Pool::with_capacity(32, || Vec::<Vec2<f32>>::with_capacity(64));
+/// pool.set_reset(|points| points.clear());
+/// let mut points = pool.get();
+/// points.push(vec2!(1.0, 2.0));
+/// ```
+pub struct Pool<T> {
+    free: RefCell<Vec<T>>,
+    create: Box<dyn Fn() -> T>,
+    reset: RefCell<Option<ResetFn<T>>>,
+}
+
+impl<T> Pool<T> {
+    /// Creates an empty pool. Objects are created by `create` when the pool is empty.
+    pub fn new(create: impl Fn() -> T + 'static) -> Self {
+        Self {
+            free: RefCell::new(Vec::new()),
+            create: Box::new(create),
+            reset: RefCell::new(None),
+        }
+    }
+
+    /// Creates a pool filled with `capacity` objects.
+    pub fn with_capacity(capacity: usize, create: impl Fn() -> T + 'static) -> Self {
+        let pool = Self::new(create);
+        pool.reserve(capacity);
+        pool
+    }
+
+    /// Sets a function called on objects returned to the pool, e.g. to clear them.
+    pub fn set_reset(&self, reset: impl Fn(&mut T) + 'static) {
+        *self.reset.borrow_mut() = Some(Box::new(reset));
+    }
+
+    /// Creates objects until at least `count` objects are available in the pool.
+    pub fn reserve(&self, count: usize) {
+        let mut free = self.free.borrow_mut();
+        let missing = count.saturating_sub(free.len());
+        free.reserve(missing);
+        while free.len() < count {
+            free.push((self.create)());
+        }
+    }
+
+    /// Returns the number of objects available in the pool.
+    pub fn get_available(&self) -> usize {
+        self.free.borrow().len()
+    }
+
+    /// Takes an object from the pool, or creates one if the pool is empty.
+    pub fn get(&self) -> Pooled<'_, T> {
+        let value = self.free.borrow_mut().pop();
+        Pooled {
+            value: ManuallyDrop::new(value.unwrap_or_else(|| (self.create)())),
+            pool: self,
+        }
+    }
+
+    /// Returns an object to the pool, e.g. one detached with [`Pooled::detach`].
+    pub fn put(&self, mut value: T) {
+        if let Some(reset) = self.reset.borrow().as_ref() {
+            reset(&mut value);
+        }
+        self.free.borrow_mut().push(value);
+    }
+
+    /// Drops all the objects available in the pool.
+    pub fn clear(&self) {
+        self.free.borrow_mut().clear();
+    }
+}
+
+impl<T> fmt::Debug for Pool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("available", &self.get_available())
+            .finish()
+    }
+}
+
+/// An object borrowed from a [`Pool`]. It goes back to the pool when dropped.
+pub struct Pooled<'a, T> {
+    value: ManuallyDrop<T>,
+    pool: &'a Pool<T>,
+}
+
+impl<T> Pooled<'_, T> {
+    /// Takes the object out of the pool's control. Use [`Pool::put`] to return it later.
+    pub fn detach(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never dropped, so the value is only taken once
+        unsafe { ManuallyDrop::take(&mut this.value) }
+    }
+}
+
+impl<T> Deref for Pooled<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Pooled<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Pooled<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.value, f)
+    }
+}
+
+impl<T> Drop for Pooled<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: the value is not used after this
+        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        self.pool.put(value);
+    }
+}
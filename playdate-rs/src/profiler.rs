@@ -0,0 +1,428 @@
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt::Write as _,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use no_std_io::io;
+use spin::Mutex;
+
+use crate::{
+    fs::Path,
+    graphics::{BitmapDrawMode, Color},
+    math::{Rect, Vec2},
+    PLAYDATE,
+};
+
+/// Number of frames the rolling averages are computed over, roughly.
+pub const ROLLING_FRAMES: u32 = 30;
+/// Name of the scope covering the whole frame, parent of all other scopes.
+pub const FRAME_SCOPE: &str = "frame";
+const OVERLAY_LINE_HEIGHT: i32 = 16;
+const OVERLAY_WIDTH: i32 = 240;
+
+/// Measures the time spent until the end of the current block, when the profiler is enabled.
+///
+/// ```ignore
+/// fn update(&mut self, delta: f32) {
+///     profile!("physics");
+///     for body in &mut self.bodies {
+///         profile!("body");
+///         // ...
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! profile {
+    ($name: expr) => {
+        let _profile_scope = $crate::profiler::Scope::new($name);
+    };
+}
+
+/// Timing of a scope in the per-frame report, returned by [`get_report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileEntry {
+    /// Name given to [`profile!`].
+    pub name: &'static str,
+    /// Nesting level, 0 for [`FRAME_SCOPE`].
+    pub depth: usize,
+    /// Number of times the scope was entered in the last frame.
+    pub calls: u32,
+    /// Total time spent in the scope in the last frame, in milliseconds.
+    pub last_ms: f32,
+    /// Rolling average of the time spent in the scope per frame, in milliseconds.
+    pub average_ms: f32,
+    /// Maximum time spent in the scope in a frame since the profiler was enabled, in milliseconds.
+    pub max_ms: f32,
+}
+
+/// A timed scope of the current frame.
+#[derive(Debug, Clone, Copy)]
+struct Event {
+    name: &'static str,
+    node: usize,
+    start_us: u32,
+    duration_us: u32,
+}
+
+/// A scope in the tree of scopes seen so far, identified by its name and parent.
+#[derive(Debug, Clone)]
+struct Node {
+    name: &'static str,
+    parent: Option<usize>,
+    depth: usize,
+    calls: u32,
+    last_us: u32,
+    average_us: f32,
+    max_us: u32,
+}
+
+/// A frame recorded for the Chrome trace export.
+struct CapturedFrame {
+    start_us: u64,
+    events: Vec<Event>,
+}
+
+struct ProfilerState {
+    frame: u32,
+    events: Vec<Event>,
+    /// Indices in `events` of the open scopes
+    stack: Vec<usize>,
+    nodes: Vec<Node>,
+    /// Monotonic time of the start of the frame, in seconds
+    frame_start: f64,
+    capture: Vec<CapturedFrame>,
+    capture_frames: usize,
+    /// Monotonic time of the start of the capture, in seconds
+    capture_start: Option<f64>,
+    overlay: bool,
+}
+
+impl ProfilerState {
+    const fn new() -> Self {
+        Self {
+            frame: 0,
+            events: Vec::new(),
+            stack: Vec::new(),
+            nodes: Vec::new(),
+            frame_start: 0.0,
+            capture: Vec::new(),
+            capture_frames: 0,
+            capture_start: None,
+            overlay: false,
+        }
+    }
+
+    fn find_or_add_node(&mut self, name: &'static str, parent: Option<usize>) -> usize {
+        if let Some(i) = self
+            .nodes
+            .iter()
+            .position(|n| n.parent == parent && n.name == name)
+        {
+            return i;
+        }
+        let depth = parent.map_or(0, |p| self.nodes[p].depth + 1);
+        self.nodes.push(Node {
+            name,
+            parent,
+            depth,
+            calls: 0,
+            last_us: 0,
+            average_us: 0.0,
+            max_us: 0,
+        });
+        self.nodes.len() - 1
+    }
+
+    fn open(&mut self, name: &'static str) -> usize {
+        let parent = self.stack.last().map(|&e| self.events[e].node);
+        let node = self.find_or_add_node(name, parent);
+        self.events.push(Event {
+            name,
+            node,
+            start_us: self.now_us(),
+            duration_us: 0,
+        });
+        let index = self.events.len() - 1;
+        self.stack.push(index);
+        index
+    }
+
+    /// Microseconds since the start of the frame.
+    fn now_us(&self) -> u32 {
+        ((PLAYDATE.system.get_monotonic_time() - self.frame_start) * 1_000_000.0) as u32
+    }
+
+    fn close(&mut self, index: usize) {
+        let now = self.now_us();
+        // Scopes are closed in reverse order, unless a guard was moved
+        while let Some(open) = self.stack.pop() {
+            let event = &mut self.events[open];
+            event.duration_us = now.saturating_sub(event.start_us);
+            if open == index {
+                break;
+            }
+        }
+    }
+
+    /// Aggregates the events of the finished frame into the nodes.
+    fn finish_frame(&mut self) {
+        for node in &mut self.nodes {
+            node.calls = 0;
+            node.last_us = 0;
+        }
+        for event in &self.events {
+            let node = &mut self.nodes[event.node];
+            node.calls += 1;
+            node.last_us += event.duration_us;
+        }
+        for node in &mut self.nodes {
+            node.average_us += (node.last_us as f32 - node.average_us) / ROLLING_FRAMES as f32;
+            node.max_us = node.max_us.max(node.last_us);
+        }
+        if let Some(capture_start) = self.capture_start {
+            if self.capture.len() >= self.capture_frames {
+                return;
+            }
+            self.capture.push(CapturedFrame {
+                start_us: ((self.frame_start - capture_start) * 1_000_000.0) as u64,
+                events: self.events.clone(),
+            });
+        }
+    }
+
+    /// Returns the nodes in depth-first order.
+    fn get_report(&self) -> Vec<ProfileEntry> {
+        let mut report = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, n)| n.parent.is_none())
+            .map(|(i, _)| i)
+            .collect();
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            report.push(ProfileEntry {
+                name: node.name,
+                depth: node.depth,
+                calls: node.calls,
+                last_ms: node.last_us as f32 / 1000.0,
+                average_ms: node.average_us / 1000.0,
+                max_ms: node.max_us as f32 / 1000.0,
+            });
+            stack.extend(
+                self.nodes
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .filter(|(_, n)| n.parent == Some(i))
+                    .map(|(i, _)| i),
+            );
+        }
+        report
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STATE: Mutex<ProfilerState> = Mutex::new(ProfilerState::new());
+
+/// Guard created by [`profile!`], measuring the time until it is dropped.
+#[must_use = "the scope ends when the guard is dropped"]
+pub struct Scope {
+    index: Option<(u32, usize)>,
+}
+
+impl Scope {
+    /// Opens a scope. Does nothing if the profiler is disabled.
+    pub fn new(name: &'static str) -> Self {
+        if !ENABLED.load(Ordering::Relaxed) {
+            return Self { index: None };
+        }
+        let mut state = STATE.lock();
+        let index = state.open(name);
+        Self {
+            index: Some((state.frame, index)),
+        }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let Some((frame, index)) = self.index else {
+            return;
+        };
+        let mut state = STATE.lock();
+        // Ignore scopes kept across frames
+        if state.frame == frame && index < state.events.len() {
+            state.close(index);
+        }
+    }
+}
+
+/// Enables or disables the profiler. Disabled by default, in which case [`profile!`] costs a single check.
+pub fn set_enabled(enabled: bool) {
+    if !enabled {
+        let mut state = STATE.lock();
+        state.events.clear();
+        state.stack.clear();
+        state.nodes.clear();
+    }
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns `true` if the profiler is enabled.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Sets whether the report is drawn over the game after every frame: into the debug bitmap in the simulator, and into the frame buffer on device.
+pub fn set_overlay(overlay: bool) {
+    STATE.lock().overlay = overlay;
+}
+
+/// Returns the report of the last complete frame, in depth-first order: each scope is followed by its children.
+pub fn get_report() -> Vec<ProfileEntry> {
+    STATE.lock().get_report()
+}
+
+/// Logs the report of the last complete frame, indented by depth.
+pub fn log_report() {
+    for entry in get_report() {
+        log::info!(
+            "{:indent$}{}: {:.2} ms avg, {:.2} ms last, {:.2} ms max, {} calls",
+            "",
+            entry.name,
+            entry.average_ms,
+            entry.last_ms,
+            entry.max_ms,
+            entry.calls,
+            indent = entry.depth * 2
+        );
+    }
+}
+
+/// Starts recording the next `frames` frames for [`export_chrome_trace`]. Frames recorded before are discarded.
+pub fn start_capture(frames: usize) {
+    let mut state = STATE.lock();
+    state.capture.clear();
+    state.capture.reserve(frames);
+    state.capture_frames = frames;
+    state.capture_start = Some(PLAYDATE.system.get_monotonic_time());
+}
+
+/// Stops recording frames. The recorded frames are kept for [`export_chrome_trace`].
+pub fn stop_capture() {
+    let mut state = STATE.lock();
+    state.capture_frames = state.capture.len();
+}
+
+/// Returns the number of frames recorded since [`start_capture`].
+pub fn get_captured_frames() -> usize {
+    STATE.lock().capture.len()
+}
+
+/// Writes the recorded frames to a file of the data folder in the Chrome trace event format, which can be opened with `chrome://tracing` or [Perfetto](https://ui.perfetto.dev).
+pub fn export_chrome_trace(path: impl AsRef<Path>) -> io::Result<()> {
+    let state = STATE.lock();
+    let mut json = String::from("{\"traceEvents\":[");
+    let mut first = true;
+    for frame in &state.capture {
+        for event in &frame.events {
+            if !first {
+                json.push(',');
+            }
+            first = false;
+            json.push_str("{\"name\":\"");
+            for c in event.name.chars() {
+                match c {
+                    '"' => json.push_str("\\\""),
+                    '\\' => json.push_str("\\\\"),
+                    c if (c as u32) < 0x20 => {
+                        let _ = write!(json, "\\u{:04x}", c as u32);
+                    }
+                    c => json.push(c),
+                }
+            }
+            let _ = write!(
+                json,
+                "\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":0,\"tid\":0}}",
+                frame.start_us + event.start_us as u64,
+                event.duration_us
+            );
+        }
+    }
+    json.push_str("],\"displayTimeUnit\":\"ms\"}");
+    drop(state);
+    crate::fs::write(path, json)
+}
+
+/// Opens the frame scope. Called before each `App::update`.
+pub(crate) fn begin_frame() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut state = STATE.lock();
+    state.frame = state.frame.wrapping_add(1);
+    state.events.clear();
+    state.stack.clear();
+    // Restarting the timer keeps its precision, without changing `get_elapsed_time`
+    state.frame_start = PLAYDATE.system.restart_timer();
+    state.open(FRAME_SCOPE);
+}
+
+/// Closes the frame scope, updates the report and draws the overlay. Called after each `App::update`.
+pub(crate) fn end_frame() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut state = STATE.lock();
+    if state.events.is_empty() {
+        return;
+    }
+    state.close(0);
+    state.finish_frame();
+    if state.overlay {
+        let report = state.get_report();
+        drop(state);
+        draw_overlay(&report);
+    }
+}
+
+fn draw_overlay(report: &[ProfileEntry]) {
+    let graphics = &PLAYDATE.graphics;
+    let height = report.len() as i32 * OVERLAY_LINE_HEIGHT;
+    let debug_bitmap = if cfg!(all(target_arch = "arm", target_os = "none")) {
+        // `getDebugBitmap` is NULL on device
+        None
+    } else {
+        graphics.get_debug_bitmap()
+    };
+    if let Some(bitmap) = &debug_bitmap {
+        bitmap.clear(Color::Clear);
+        graphics.push_context(&**bitmap);
+    } else {
+        graphics.fill_rect(Rect::new(0, 0, OVERLAY_WIDTH, height), Color::White);
+    }
+    graphics.set_draw_mode(BitmapDrawMode::Copy);
+    let mut line = String::new();
+    for (i, entry) in report.iter().enumerate() {
+        line.clear();
+        let _ = write!(
+            line,
+            "{:indent$}{} {:.1}ms",
+            "",
+            entry.name,
+            entry.average_ms,
+            indent = entry.depth * 2
+        );
+        if entry.calls > 1 {
+            let _ = write!(line, " x{}", entry.calls);
+        }
+        graphics.draw_text(&line, Vec2::new(2, i as i32 * OVERLAY_LINE_HEIGHT));
+    }
+    if debug_bitmap.is_some() {
+        graphics.pop_context();
+    }
+}
@@ -106,19 +106,35 @@ impl PlaydateSprite {
     }
 
     pub fn query_sprites_at_point(&self, pos: Vec2<f32>) -> Vec<Ref<Sprite>> {
+        let mut result = Vec::new();
+        self.query_sprites_at_point_into(pos, &mut result);
+        result
+    }
+
+    /// Same as [`PlaydateSprite::query_sprites_at_point`], but fills `buf` instead of returning a new `Vec`. `buf` is cleared first, and keeps its capacity across calls.
+    pub fn query_sprites_at_point_into<'a>(
+        &'a self,
+        pos: Vec2<f32>,
+        buf: &mut Vec<Ref<'a, Sprite>>,
+    ) {
         let mut len = 0;
         let sprites =
             unsafe { (*self.handle).querySpritesAtPoint.unwrap()(pos.x, pos.y, &mut len) };
+        collect_into(sprites, len, buf, |s| Sprite::from_ref(*s));
+    }
+
+    pub fn query_sprites_in_rect(&self, rect: Rect<f32>) -> Vec<Ref<Sprite>> {
         let mut result = Vec::new();
-        for i in 0..len {
-            let sprite = unsafe { sprites.offset(i as isize).as_ref().unwrap() };
-            result.push(Sprite::from_ref(*sprite));
-        }
-        PLAYDATE.system.realloc(sprites as _, 0);
+        self.query_sprites_in_rect_into(rect, &mut result);
         result
     }
 
-    pub fn query_sprites_in_rect(&self, rect: Rect<f32>) -> Vec<Ref<Sprite>> {
+    /// Same as [`PlaydateSprite::query_sprites_in_rect`], but fills `buf` instead of returning a new `Vec`. `buf` is cleared first, and keeps its capacity across calls.
+    pub fn query_sprites_in_rect_into<'a>(
+        &'a self,
+        rect: Rect<f32>,
+        buf: &mut Vec<Ref<'a, Sprite>>,
+    ) {
         let mut len = 0;
         let sprites = unsafe {
             (*self.handle).querySpritesInRect.unwrap()(
@@ -129,26 +145,28 @@ impl PlaydateSprite {
                 &mut len,
             )
         };
+        collect_into(sprites, len, buf, |s| Sprite::from_ref(*s));
+    }
+
+    pub fn query_sprites_along_line(&self, x1: f32, y1: f32, x2: f32, y2: f32) -> Vec<Ref<Sprite>> {
         let mut result = Vec::new();
-        for i in 0..len {
-            let sprite = unsafe { sprites.offset(i as isize).as_ref().unwrap() };
-            result.push(Sprite::from_ref(*sprite));
-        }
-        PLAYDATE.system.realloc(sprites as _, 0);
+        self.query_sprites_along_line_into(x1, y1, x2, y2, &mut result);
         result
     }
 
-    pub fn query_sprites_along_line(&self, x1: f32, y1: f32, x2: f32, y2: f32) -> Vec<Ref<Sprite>> {
+    /// Same as [`PlaydateSprite::query_sprites_along_line`], but fills `buf` instead of returning a new `Vec`. `buf` is cleared first, and keeps its capacity across calls.
+    pub fn query_sprites_along_line_into<'a>(
+        &'a self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        buf: &mut Vec<Ref<'a, Sprite>>,
+    ) {
         let mut len = 0;
         let sprites =
             unsafe { (*self.handle).querySpritesAlongLine.unwrap()(x1, y1, x2, y2, &mut len) };
-        let mut result = Vec::new();
-        for i in 0..len {
-            let sprite = unsafe { sprites.offset(i as isize).as_ref().unwrap() };
-            result.push(Sprite::from_ref(*sprite));
-        }
-        PLAYDATE.system.realloc(sprites as _, 0);
-        result
+        collect_into(sprites, len, buf, |s| Sprite::from_ref(*s));
     }
 
     pub fn query_sprite_info_along_line(
@@ -158,30 +176,51 @@ impl PlaydateSprite {
         x2: f32,
         y2: f32,
     ) -> Vec<SpriteQueryInfo> {
+        let mut result = Vec::new();
+        self.query_sprite_info_along_line_into(x1, y1, x2, y2, &mut result);
+        result
+    }
+
+    /// Same as [`PlaydateSprite::query_sprite_info_along_line`], but fills `buf` instead of returning a new `Vec`. `buf` is cleared first, and keeps its capacity across calls.
+    pub fn query_sprite_info_along_line_into<'a>(
+        &'a self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        buf: &mut Vec<SpriteQueryInfo<'a>>,
+    ) {
         let mut len = 0;
         let info =
             unsafe { (*self.handle).querySpriteInfoAlongLine.unwrap()(x1, y1, x2, y2, &mut len) };
-        let mut result = Vec::new();
-        for i in 0..len {
-            let info = unsafe { info.offset(i as isize).as_ref().unwrap() };
-            result.push(SpriteQueryInfo::new(info));
-        }
-        PLAYDATE.system.realloc(info as _, 0);
-        result
+        collect_into(info, len, buf, SpriteQueryInfo::new);
     }
 
     /// Returns an array of all sprites that have collide rects that are currently overlapping. Each consecutive pair of sprites is overlapping (eg. 0 & 1 overlap, 2 & 3 overlap, etc).
     pub fn all_overlapping_sprites(&self) -> Vec<Ref<Sprite>> {
-        let mut len = 0;
-        let sprites = unsafe { (*self.handle).allOverlappingSprites.unwrap()(&mut len) };
         let mut result = Vec::new();
-        for i in 0..len {
-            let sprite = unsafe { sprites.offset(i as isize).as_ref().unwrap() };
-            result.push(Sprite::from_ref(*sprite));
-        }
-        PLAYDATE.system.realloc(sprites as _, 0);
+        self.all_overlapping_sprites_into(&mut result);
         result
     }
+
+    /// Same as [`PlaydateSprite::all_overlapping_sprites`], but fills `buf` instead of returning a new `Vec`. `buf` is cleared first, and keeps its capacity across calls.
+    pub fn all_overlapping_sprites_into<'a>(&'a self, buf: &mut Vec<Ref<'a, Sprite>>) {
+        let mut len = 0;
+        let sprites = unsafe { (*self.handle).allOverlappingSprites.unwrap()(&mut len) };
+        collect_into(sprites, len, buf, |s| Sprite::from_ref(*s));
+    }
+}
+
+/// Converts an array allocated by the system into `buf`, then frees the array.
+fn collect_into<T, U>(items: *mut T, len: i32, buf: &mut Vec<U>, f: impl Fn(&T) -> U) {
+    buf.clear();
+    if items.is_null() {
+        return;
+    }
+    for i in 0..len {
+        buf.push(f(unsafe { &*items.offset(i as isize) }));
+    }
+    PLAYDATE.system.realloc(items as _, 0);
 }
 
 #[derive(Debug)]
@@ -504,6 +543,17 @@ impl Sprite {
 
     /// Returns the same values as playdate->sprite->moveWithCollisions() but does not actually move the sprite.
     pub fn check_collisions(&self, move_goal: Vec2<f32>) -> Vec<SpriteCollisionInfo> {
+        let mut result = Vec::new();
+        self.check_collisions_into(move_goal, &mut result);
+        result
+    }
+
+    /// Same as [`Sprite::check_collisions`], but fills `buf` instead of returning a new `Vec`. `buf` is cleared first, and keeps its capacity across calls.
+    pub fn check_collisions_into<'a>(
+        &'a self,
+        move_goal: Vec2<f32>,
+        buf: &mut Vec<SpriteCollisionInfo<'a>>,
+    ) {
         let mut actual_x = 0.0;
         let mut actual_y = 0.0;
         let mut len = 0;
@@ -517,18 +567,23 @@ impl Sprite {
                 &mut len,
             )
         };
-        let mut result = Vec::new();
-        for i in 0..len {
-            let info = unsafe { info.offset(i as isize).as_ref().unwrap() };
-            result.push(SpriteCollisionInfo::new(info));
-        }
         // caller is responsible for freeing memory of array returned by moveWithCollisions()
-        PLAYDATE.system.realloc(info as _, 0);
-        result
+        collect_into(info, len, buf, SpriteCollisionInfo::new);
     }
 
     /// Moves the given sprite towards goalX, goalY taking collisions into account and returns an array of SpriteCollisionInfo. len is set to the size of the array and actualX, actualY are set to the sprite’s position after collisions. If no collisions occurred, this will be the same as goalX, goalY.
     pub fn move_with_collisions(&self, goal: Vec2<f32>) -> (Vec2<f32>, Vec<SpriteCollisionInfo>) {
+        let mut result = Vec::new();
+        let actual = self.move_with_collisions_into(goal, &mut result);
+        (actual, result)
+    }
+
+    /// Same as [`Sprite::move_with_collisions`], but fills `buf` instead of returning a new `Vec`, and returns the sprite's position after collisions. `buf` is cleared first, and keeps its capacity across calls.
+    pub fn move_with_collisions_into<'a>(
+        &'a self,
+        goal: Vec2<f32>,
+        buf: &mut Vec<SpriteCollisionInfo<'a>>,
+    ) -> Vec2<f32> {
         let mut actual_x = 0.0;
         let mut actual_y = 0.0;
         let mut len = 0;
@@ -542,28 +597,24 @@ impl Sprite {
                 &mut len,
             )
         };
-        let mut result = Vec::new();
-        for i in 0..len {
-            let info = unsafe { info.offset(i as isize).as_ref().unwrap() };
-            result.push(SpriteCollisionInfo::new(info));
-        }
         // caller is responsible for freeing memory of array returned by moveWithCollisions()
-        PLAYDATE.system.realloc(info as _, 0);
-        (vec2!(actual_x, actual_y), result)
+        collect_into(info, len, buf, SpriteCollisionInfo::new);
+        vec2!(actual_x, actual_y)
     }
 
     /// Returns an array of sprites that have collide rects that are currently overlapping the given sprite’s collide rect.
     pub fn overlapping_sprites(&self) -> Vec<Ref<Sprite>> {
+        let mut result = Vec::new();
+        self.overlapping_sprites_into(&mut result);
+        result
+    }
+
+    /// Same as [`Sprite::overlapping_sprites`], but fills `buf` instead of returning a new `Vec`. `buf` is cleared first, and keeps its capacity across calls.
+    pub fn overlapping_sprites_into<'a>(&'a self, buf: &mut Vec<Ref<'a, Sprite>>) {
         let mut len = 0;
         let sprites =
             unsafe { (*PLAYDATE.sprite.handle).overlappingSprites.unwrap()(self.handle, &mut len) };
-        let mut result = Vec::new();
-        for i in 0..len {
-            let sprite = unsafe { sprites.offset(i as isize).as_ref().unwrap() };
-            result.push(Sprite::from_ref(*sprite));
-        }
-        PLAYDATE.system.realloc(sprites as _, 0);
-        result
+        collect_into(sprites, len, buf, |s| Sprite::from_ref(*s));
     }
 }
 
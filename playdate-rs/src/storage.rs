@@ -0,0 +1,348 @@
+use alloc::{borrow::ToOwned, boxed::Box, format, string::String, vec::Vec};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error::Error,
+    fs::{File, FileOptions, Read, Write},
+    PLAYDATE,
+};
+
+/// Magic bytes at the beginning of every save file.
+const MAGIC: [u8; 4] = *b"PDSV";
+/// Version of the file layout itself, independent of the schema version of the stored value.
+const FORMAT_VERSION: u8 = 1;
+/// Magic (4), format version (1), reserved (3), schema version (4), payload length (4), payload CRC-32 (4).
+const HEADER_SIZE: usize = 20;
+
+/// Default number of previous saves kept as backups.
+pub const DEFAULT_BACKUP_COUNT: usize = 2;
+
+type MigrateFn = Box<dyn Fn(&[u8]) -> Result<Vec<u8>, Error>>;
+
+struct Migration {
+    from_version: u32,
+    migrate: MigrateFn,
+}
+
+/// Saves and loads serializable values in the game's data folder, with schema versioning and backups.
+///
+/// Values are serialized with serde into the compact postcard format, behind a small header with a schema version and a CRC-32 checksum. Writes go to a temporary file that is renamed over the previous save once it is complete, so a crash or a dead battery never leaves a half-written save behind. The previous saves are kept as rotating backups, and are used automatically if the latest save is missing or corrupted.
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct SaveGame {
+///     level: u32,
+///     score: u64,
+/// }
+///
+/// playdate_rs::storage::save("save", &SaveGame { level: 3, score: 1200 })?;
+/// let save: SaveGame = playdate_rs::storage::load("save")?;
+/// ```
+///
+/// The free functions [`save`] and [`load`] use a default `Storage` with schema version 0 and [`DEFAULT_BACKUP_COUNT`] backups. Create a `Storage` to change these, or to migrate saves written by older versions of the game.
+pub struct Storage {
+    schema_version: u32,
+    backup_count: usize,
+    migrations: Vec<Migration>,
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Storage {
+    /// Create a storage with schema version 0 and [`DEFAULT_BACKUP_COUNT`] backups.
+    pub fn new() -> Self {
+        Self {
+            schema_version: 0,
+            backup_count: DEFAULT_BACKUP_COUNT,
+            migrations: Vec::new(),
+        }
+    }
+
+    /// Sets the schema version written with every save. Bump it whenever the layout of the saved type changes, and register a migration from the previous version with [`Storage::add_migration`].
+    pub fn set_schema_version(&mut self, version: u32) {
+        self.schema_version = version;
+    }
+
+    /// Returns the schema version written with every save.
+    pub fn get_schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    /// Sets how many previous saves are kept as backups. 0 disables backups.
+    pub fn set_backup_count(&mut self, count: usize) {
+        self.backup_count = count;
+    }
+
+    /// Returns how many previous saves are kept as backups.
+    pub fn get_backup_count(&self) -> usize {
+        self.backup_count
+    }
+
+    /// Registers a migration from schema version `from_version` to `from_version + 1`.
+    ///
+    /// When a save with an older schema version is loaded, the migrations are applied one after the other until the current schema version is reached.
+    ///
+    /// ```ignore
+    /// storage.set_schema_version(1);
+    /// storage.add_migration(0, |old: SaveGameV0| SaveGame {
+    ///     level: old.level,
+    ///     score: 0,
+    /// });
+    /// ```
+    pub fn add_migration<Old: DeserializeOwned, New: Serialize>(
+        &mut self,
+        from_version: u32,
+        migrate: impl Fn(Old) -> New + 'static,
+    ) {
+        self.migrations.retain(|m| m.from_version != from_version);
+        self.migrations.push(Migration {
+            from_version,
+            migrate: Box::new(move |data| encode(&migrate(decode(data)?))),
+        });
+    }
+
+    /// Serializes the value and atomically writes it to the file `name` in the game's data folder, rotating the previous saves into backups.
+    pub fn save<T: Serialize>(&self, name: impl AsRef<str>, value: &T) -> Result<(), Error> {
+        let name = name.as_ref();
+        let payload = encode(value)?;
+        let mut data = Vec::with_capacity(HEADER_SIZE + payload.len());
+        data.extend_from_slice(&MAGIC);
+        data.extend_from_slice(&[FORMAT_VERSION, 0, 0, 0]);
+        data.extend_from_slice(&self.schema_version.to_le_bytes());
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(&crc32(&payload).to_le_bytes());
+        data.extend_from_slice(&payload);
+        // Write the whole save to a temporary file first, the file is closed when dropped
+        let tmp = tmp_path(name);
+        {
+            let mut file = File::open(&tmp, FileOptions::kFileWrite).map_err(Error::IO)?;
+            file.write_all(&data).map_err(Error::IO)?;
+            file.flush().map_err(Error::IO)?;
+        }
+        // Rotate the backups: name.bak1 is the most recent one
+        if self.backup_count > 0 && file_exists(name) {
+            for i in (1..self.backup_count).rev() {
+                let from = backup_path(name, i);
+                if file_exists(&from) {
+                    PLAYDATE
+                        .file
+                        .rename(&from, backup_path(name, i + 1))
+                        .map_err(Error::IO)?;
+                }
+            }
+            PLAYDATE
+                .file
+                .rename(name, backup_path(name, 1))
+                .map_err(Error::IO)?;
+        }
+        PLAYDATE.file.rename(&tmp, name).map_err(Error::IO)?;
+        Ok(())
+    }
+
+    /// Loads the value saved in the file `name` in the game's data folder, migrating it to the current schema version if needed.
+    ///
+    /// If the save is missing or corrupted, the most recent valid backup is loaded instead. The error of the latest save is returned if no valid backup exists either.
+    pub fn load<T: DeserializeOwned>(&self, name: impl AsRef<str>) -> Result<T, Error> {
+        let name = name.as_ref();
+        // A complete temporary file is more recent than the backups, in case the game was interrupted between the two renames of `save`
+        let candidates = [name.to_owned(), tmp_path(name)]
+            .into_iter()
+            .chain((1..=self.backup_count).map(|i| backup_path(name, i)));
+        let mut first_error = None;
+        for path in candidates {
+            if !file_exists(&path) {
+                continue;
+            }
+            match self.load_file(&path) {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        Err(first_error.unwrap_or_else(|| Error::FileNotExists(name.to_owned())))
+    }
+
+    fn load_file<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let mut file = File::open(path, FileOptions::kFileReadData).map_err(Error::IO)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).map_err(Error::IO)?;
+        let corrupted = |reason: &str| Error::CorruptedSaveFile(format!("{}: {}", path, reason));
+        if data.len() < HEADER_SIZE || data[0..4] != MAGIC {
+            return Err(corrupted("not a save file"));
+        }
+        if data[4] != FORMAT_VERSION {
+            return Err(corrupted("unknown format version"));
+        }
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ])
+        };
+        let mut version = read_u32(8);
+        let len = read_u32(12) as usize;
+        let checksum = read_u32(16);
+        let Some(payload) = data.get(HEADER_SIZE..HEADER_SIZE + len) else {
+            return Err(corrupted("truncated"));
+        };
+        if crc32(payload) != checksum {
+            return Err(corrupted("checksum mismatch"));
+        }
+        if version > self.schema_version {
+            return Err(Error::UnsupportedSchemaVersion(version));
+        }
+        let mut payload = payload.to_vec();
+        while version < self.schema_version {
+            let Some(migration) = self.migrations.iter().find(|m| m.from_version == version) else {
+                return Err(Error::UnsupportedSchemaVersion(version));
+            };
+            payload = (migration.migrate)(&payload)?;
+            version += 1;
+        }
+        decode(&payload)
+    }
+
+    /// Returns `true` if a save or one of its backups exists.
+    pub fn exists(&self, name: impl AsRef<str>) -> bool {
+        let name = name.as_ref();
+        file_exists(name)
+            || file_exists(&tmp_path(name))
+            || (1..=self.backup_count).any(|i| file_exists(&backup_path(name, i)))
+    }
+
+    /// Deletes a save, together with its backups.
+    pub fn remove(&self, name: impl AsRef<str>) -> Result<(), Error> {
+        let name = name.as_ref();
+        let paths = [name.to_owned(), tmp_path(name)]
+            .into_iter()
+            .chain((1..=self.backup_count).map(|i| backup_path(name, i)));
+        for path in paths {
+            if file_exists(&path) {
+                PLAYDATE.file.unlink(&path, false).map_err(Error::IO)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Saves the value to the file `name` in the game's data folder, using the default [`Storage`].
+pub fn save<T: Serialize>(name: impl AsRef<str>, value: &T) -> Result<(), Error> {
+    Storage::new().save(name, value)
+}
+
+/// Loads the value saved in the file `name` in the game's data folder, using the default [`Storage`].
+pub fn load<T: DeserializeOwned>(name: impl AsRef<str>) -> Result<T, Error> {
+    Storage::new().load(name)
+}
+
+/// Returns `true` if a save or one of its backups exists, using the default [`Storage`].
+pub fn exists(name: impl AsRef<str>) -> bool {
+    Storage::new().exists(name)
+}
+
+/// Deletes a save, together with its backups, using the default [`Storage`].
+pub fn remove(name: impl AsRef<str>) -> Result<(), Error> {
+    Storage::new().remove(name)
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+    postcard::to_allocvec(value).map_err(|e| Error::Serialization(format!("{}", e)))
+}
+
+fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, Error> {
+    postcard::from_bytes(data).map_err(|e| Error::Serialization(format!("{}", e)))
+}
+
+fn tmp_path(name: &str) -> String {
+    format!("{}.tmp", name)
+}
+
+fn backup_path(name: &str, index: usize) -> String {
+    format!("{}.bak{}", name, index)
+}
+
+fn file_exists(path: &str) -> bool {
+    PLAYDATE.file.stat(path).is_ok()
+}
+
+/// CRC-32 (IEEE) lookup table.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc = CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let value = (3u32, "level 3".to_owned());
+        let decoded: (u32, String) = decode(&encode(&value).unwrap()).unwrap();
+        assert_eq!(decoded, value);
+        assert!(decode::<(u32, String)>(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn migrations_replace_and_chain() {
+        let mut storage = Storage::new();
+        storage.set_schema_version(2);
+        storage.add_migration(0, |level: u32| level + 100);
+        storage.add_migration(0, |level: u32| (level, 0u32));
+        storage.add_migration(1, |(level, score): (u32, u32)| level * 10 + score);
+        assert_eq!(storage.migrations.len(), 2);
+        let mut payload = encode(&3u32).unwrap();
+        for version in 0..2 {
+            let migration = storage
+                .migrations
+                .iter()
+                .find(|m| m.from_version == version)
+                .unwrap();
+            payload = (migration.migrate)(&payload).unwrap();
+        }
+        assert_eq!(decode::<u32>(&payload).unwrap(), 30);
+    }
+
+    #[test]
+    fn file_names() {
+        assert_eq!(tmp_path("save"), "save.tmp");
+        assert_eq!(backup_path("save", 2), "save.bak2");
+    }
+}
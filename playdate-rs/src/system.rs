@@ -1,6 +1,10 @@
-use core::ffi::{c_char, c_void, CStr};
+use core::{
+    ffi::{c_char, c_void, CStr},
+    sync::atomic::{AtomicU32, Ordering},
+};
 
 use alloc::{ffi::CString, vec::Vec};
+use spin::Mutex;
 pub use sys::{
     LCDFontData as FontData, PDDateTime as DateTime, PDLanguage as Language,
     PDSystemEvent as SystemEvent,
@@ -9,6 +13,22 @@ use sys::{PDButtons, PDPeripherals};
 
 use crate::{graphics::Bitmap, math::Vec2, PLAYDATE};
 
+/// Number of frames updated since the game started, incremented before each `App::update`.
+pub(crate) static FRAME_NUMBER: AtomicU32 = AtomicU32::new(0);
+
+/// Bookkeeping of the high-resolution timer, so it can be restarted by the profiler without changing the time returned to the game.
+struct ElapsedTimer {
+    /// Seconds counted by the timer before its last restart
+    base: f64,
+    /// Value of [`PlaydateSystem::get_monotonic_time`] at the last [`PlaydateSystem::reset_elapsed_time`]
+    reset_at: f64,
+}
+
+static ELAPSED_TIMER: Mutex<ElapsedTimer> = Mutex::new(ElapsedTimer {
+    base: 0.0,
+    reset_at: 0.0,
+});
+
 pub struct PlaydateSystem {
     handle: *const sys::playdate_sys,
 }
@@ -37,6 +57,20 @@ impl PlaydateSystem {
             let c_string = CString::new(msg.as_ref()).unwrap();
             (*self.handle).error.unwrap()(c_string.as_ptr() as *mut c_char);
         }
+        #[cfg(all(
+            feature = "headless",
+            not(all(target_arch = "arm", target_os = "none"))
+        ))]
+        if crate::headless::is_active() {
+            panic!("{}", msg.as_ref());
+        }
+    }
+
+    /// Same as [`PlaydateSystem::error`], but without allocating, e.g. when the heap is exhausted.
+    pub(crate) fn error_static(&self, msg: &'static CStr) {
+        unsafe {
+            (*self.handle).error.unwrap()(msg.as_ptr() as *mut c_char);
+        }
     }
 
     /// Returns the current language of the system.
@@ -257,12 +291,36 @@ impl PlaydateSystem {
 
     /// Returns the number of seconds since playdate.resetElapsedTime() was called. The value is a floating-point number with microsecond accuracy.
     pub fn get_elapsed_time(&self) -> f32 {
+        let timer = ELAPSED_TIMER.lock();
+        (timer.base + self.get_timer_value() as f64 - timer.reset_at) as f32
+    }
+
+    fn get_timer_value(&self) -> f32 {
         unsafe { (*self.handle).getElapsedTime.unwrap()() }
     }
 
+    /// Returns the number of seconds counted by the high-resolution timer since the game started. Unlike [`get_elapsed_time`](Self::get_elapsed_time), it is not affected by [`reset_elapsed_time`](Self::reset_elapsed_time).
+    pub(crate) fn get_monotonic_time(&self) -> f64 {
+        ELAPSED_TIMER.lock().base + self.get_timer_value() as f64
+    }
+
+    /// Restarts the high-resolution timer, whose `f32` value loses precision as it grows, and returns the monotonic time.
+    pub(crate) fn restart_timer(&self) -> f64 {
+        let mut timer = ELAPSED_TIMER.lock();
+        timer.base += self.get_timer_value() as f64;
+        unsafe { (*self.handle).resetElapsedTime.unwrap()() }
+        timer.base
+    }
+
+    /// Returns the number of the current frame, starting at 1 for the first `App::update`. Returns 0 before the first update, e.g. in `App::init`.
+    pub fn get_frame_number(&self) -> u32 {
+        FRAME_NUMBER.load(Ordering::Relaxed)
+    }
+
     /// Resets the high-resolution timer.
     pub fn reset_elapsed_time(&self) {
-        unsafe { (*self.handle).resetElapsedTime.unwrap()() }
+        let now = self.restart_timer();
+        ELAPSED_TIMER.lock().reset_at = now;
     }
 
     /// Returns a value from 0-100 denoting the current level of battery charge. 0 = empty; 100 = full.
@@ -399,3 +457,22 @@ impl Peripherals {
     pub const NONE: Self = Peripherals::none();
     pub const ALL: Self = Peripherals::all();
 }
+
+#[cfg(all(test, feature = "headless"))]
+mod tests {
+    use crate::PLAYDATE;
+
+    #[test]
+    fn elapsed_time_survives_timer_restarts() {
+        let system = &PLAYDATE.system;
+        system.reset_elapsed_time();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let start = system.restart_timer();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        // The profiler restarts the timer every frame, which must not reset the game's timer
+        assert!(system.get_elapsed_time() >= 0.04);
+        assert!(system.get_monotonic_time() >= start + 0.02);
+        system.reset_elapsed_time();
+        assert!(system.get_elapsed_time() < 0.02);
+    }
+}
@@ -0,0 +1,586 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use crate::{
+    crank,
+    display::{DISPLAY_HEIGHT, DISPLAY_WIDTH},
+    graphics::{Bitmap, NineSlice},
+    input,
+    math::{Rect, SideOffsets, Size, Vec2},
+    system::Buttons,
+    PLAYDATE,
+};
+
+use super::inset_rect;
+
+/// Default duration of the scroll animation, in seconds.
+pub const DEFAULT_SCROLL_DURATION: f32 = 0.25;
+/// Default number of crank ticks per revolution used to move the selection.
+pub const DEFAULT_CRANK_TICKS_PER_REVOLUTION: u32 = 6;
+
+/// Position of a cell in a [`GridView`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct GridIndex {
+    pub section: usize,
+    pub row: usize,
+    pub column: usize,
+}
+
+impl GridIndex {
+    pub const fn new(section: usize, row: usize, column: usize) -> Self {
+        Self {
+            section,
+            row,
+            column,
+        }
+    }
+}
+
+type DrawCellFn = Box<dyn Fn(GridIndex, bool, Rect<i32>)>;
+type DrawSectionHeaderFn = Box<dyn Fn(usize, Rect<i32>)>;
+
+/// A scrollable grid of cells, split into sections with optional headers. This is the equivalent of the Lua `playdate.ui.gridview`.
+///
+/// Each section has its own number of rows, and all sections share the same number of columns. The grid does not own any content: cells and headers are drawn by the closures registered with [`GridView::set_draw_cell_function`] and [`GridView::set_draw_section_header_function`].
+///
+/// Call [`GridView::handle_input`] and [`GridView::update`] once per frame, then [`GridView::draw_in_rect`] to draw it.
+pub struct GridView {
+    cell_size: Size<i32>,
+    num_columns: usize,
+    rows_in_section: Vec<usize>,
+    section_header_height: i32,
+    cell_padding: SideOffsets<i32>,
+    content_inset: SideOffsets<i32>,
+    selection: Option<GridIndex>,
+    wrap_selection: bool,
+    scroll_cells_to_center: bool,
+    crank_ticks_per_revolution: u32,
+    scroll: Vec2<f32>,
+    scroll_from: Vec2<f32>,
+    scroll_to: Vec2<f32>,
+    scroll_elapsed: f32,
+    scroll_duration: f32,
+    view_size: Size<i32>,
+    needs_display: bool,
+    background: Option<NineSlice>,
+    draw_cell: Option<DrawCellFn>,
+    draw_section_header: Option<DrawSectionHeaderFn>,
+}
+
+impl GridView {
+    /// Create a new grid view with one section, no rows and one column. A `cell_size.width` of 0 makes the cells fill the available width, divided by the number of columns.
+    pub fn new(cell_size: Size<i32>) -> Self {
+        Self {
+            cell_size,
+            num_columns: 1,
+            rows_in_section: vec![0],
+            section_header_height: 0,
+            cell_padding: SideOffsets::ZERO,
+            content_inset: SideOffsets::ZERO,
+            selection: None,
+            wrap_selection: false,
+            scroll_cells_to_center: false,
+            crank_ticks_per_revolution: DEFAULT_CRANK_TICKS_PER_REVOLUTION,
+            scroll: Vec2::new(0.0, 0.0),
+            scroll_from: Vec2::new(0.0, 0.0),
+            scroll_to: Vec2::new(0.0, 0.0),
+            scroll_elapsed: 0.0,
+            scroll_duration: DEFAULT_SCROLL_DURATION,
+            view_size: Size::new(DISPLAY_WIDTH as _, DISPLAY_HEIGHT as _),
+            needs_display: true,
+            background: None,
+            draw_cell: None,
+            draw_section_header: None,
+        }
+    }
+
+    /// Sets the function that draws a cell. It receives the cell index, whether the cell is selected, and the rect to draw into (the cell rect minus the cell padding).
+    pub fn set_draw_cell_function(&mut self, func: impl Fn(GridIndex, bool, Rect<i32>) + 'static) {
+        self.draw_cell = Some(Box::new(func));
+        self.needs_display = true;
+    }
+
+    /// Sets the function that draws a section header. It receives the section index and the header rect. Headers are only drawn if the section header height is non-zero.
+    pub fn set_draw_section_header_function(&mut self, func: impl Fn(usize, Rect<i32>) + 'static) {
+        self.draw_section_header = Some(Box::new(func));
+        self.needs_display = true;
+    }
+
+    /// Sets a background image that fills the whole grid view, behind the cells.
+    pub fn set_background_image(&mut self, image: NineSlice) {
+        self.background = Some(image);
+        self.needs_display = true;
+    }
+
+    /// Removes the background image.
+    pub fn clear_background_image(&mut self) {
+        self.background = None;
+        self.needs_display = true;
+    }
+
+    /// Sets the size of each cell, including the cell padding.
+    pub fn set_cell_size(&mut self, cell_size: Size<i32>) {
+        self.cell_size = cell_size;
+        self.needs_display = true;
+    }
+
+    /// Returns the size of each cell. A width of 0 means the cells fill the available width.
+    pub fn get_cell_size(&self) -> Size<i32> {
+        self.cell_size
+    }
+
+    /// Sets the padding around the content drawn in each cell.
+    pub fn set_cell_padding(&mut self, padding: SideOffsets<i32>) {
+        self.cell_padding = padding;
+        self.needs_display = true;
+    }
+
+    /// Returns the padding around the content drawn in each cell.
+    pub fn get_cell_padding(&self) -> SideOffsets<i32> {
+        self.cell_padding
+    }
+
+    /// Sets the inset between the edges of the grid view and its content.
+    pub fn set_content_inset(&mut self, inset: SideOffsets<i32>) {
+        self.content_inset = inset;
+        self.needs_display = true;
+    }
+
+    /// Sets the height of the section headers. 0 hides the headers.
+    pub fn set_section_header_height(&mut self, height: i32) {
+        self.section_header_height = height;
+        self.needs_display = true;
+    }
+
+    /// Sets the number of sections. New sections have no rows.
+    pub fn set_number_of_sections(&mut self, sections: usize) {
+        self.rows_in_section.resize(sections, 0);
+        self.clamp_selection();
+    }
+
+    /// Returns the number of sections.
+    pub fn get_number_of_sections(&self) -> usize {
+        self.rows_in_section.len()
+    }
+
+    /// Sets the number of rows in the given section.
+    pub fn set_number_of_rows_in_section(&mut self, section: usize, rows: usize) {
+        if section >= self.rows_in_section.len() {
+            self.rows_in_section.resize(section + 1, 0);
+        }
+        self.rows_in_section[section] = rows;
+        self.clamp_selection();
+    }
+
+    /// Returns the number of rows in the given section.
+    pub fn get_number_of_rows_in_section(&self, section: usize) -> usize {
+        self.rows_in_section.get(section).copied().unwrap_or(0)
+    }
+
+    /// Sets the number of columns, shared by all sections.
+    pub fn set_number_of_columns(&mut self, columns: usize) {
+        debug_assert!(columns > 0, "a grid view needs at least one column");
+        self.num_columns = columns.max(1);
+        self.clamp_selection();
+    }
+
+    /// Returns the number of columns.
+    pub fn get_number_of_columns(&self) -> usize {
+        self.num_columns
+    }
+
+    /// If set, moving the selection past the last cell wraps around to the first one, and vice versa.
+    pub fn set_wrap_selection(&mut self, wrap: bool) {
+        self.wrap_selection = wrap;
+    }
+
+    /// If set, scrolling to a cell centers it in the view instead of scrolling just enough to make it visible.
+    pub fn set_scroll_cells_to_center(&mut self, center: bool) {
+        self.scroll_cells_to_center = center;
+    }
+
+    /// Sets the duration of the scroll animation, in seconds. 0 disables the animation.
+    pub fn set_scroll_duration(&mut self, duration: f32) {
+        self.scroll_duration = duration.max(0.0);
+    }
+
+    /// Sets how many crank ticks per revolution [`GridView::handle_input`] uses to move the selection by one row. 0 disables crank scrolling.
+    pub fn set_crank_ticks_per_revolution(&mut self, ticks: u32) {
+        self.crank_ticks_per_revolution = ticks;
+    }
+
+    /// Returns the selected cell, if any.
+    pub fn get_selection(&self) -> Option<GridIndex> {
+        self.selection
+    }
+
+    /// Selects the given cell and scrolls to it. Out of range indices clear the selection.
+    pub fn set_selection(&mut self, index: GridIndex) {
+        self.selection = if self.is_valid(index) {
+            Some(index)
+        } else {
+            None
+        };
+        if let Some(index) = self.selection {
+            self.scroll_to_cell(index, true);
+        }
+        self.needs_display = true;
+    }
+
+    /// Clears the selection.
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
+        self.needs_display = true;
+    }
+
+    /// Moves the selection to the next row, continuing into the next non-empty section.
+    pub fn select_next_row(&mut self) {
+        let next = match self.selection {
+            Some(index) => self.next_row(index),
+            None => self.first_cell(),
+        };
+        self.move_selection(next);
+    }
+
+    /// Moves the selection to the previous row, continuing into the previous non-empty section.
+    pub fn select_previous_row(&mut self) {
+        let prev = match self.selection {
+            Some(index) => self.previous_row(index),
+            None => self.first_cell(),
+        };
+        self.move_selection(prev);
+    }
+
+    /// Moves the selection to the next column. With wrapping enabled, moving past the last column continues on the next row.
+    pub fn select_next_column(&mut self) {
+        let next = match self.selection {
+            Some(index) if index.column + 1 < self.num_columns => {
+                Some(GridIndex::new(index.section, index.row, index.column + 1))
+            }
+            Some(index) if self.wrap_selection => self
+                .next_row(index)
+                .map(|i| GridIndex::new(i.section, i.row, 0)),
+            Some(_) => None,
+            None => self.first_cell(),
+        };
+        self.move_selection(next);
+    }
+
+    /// Moves the selection to the previous column. With wrapping enabled, moving past the first column continues on the previous row.
+    pub fn select_previous_column(&mut self) {
+        let prev = match self.selection {
+            Some(index) if index.column > 0 => {
+                Some(GridIndex::new(index.section, index.row, index.column - 1))
+            }
+            Some(index) if self.wrap_selection => self
+                .previous_row(index)
+                .map(|i| GridIndex::new(i.section, i.row, self.num_columns - 1)),
+            Some(_) => None,
+            None => self.first_cell(),
+        };
+        self.move_selection(prev);
+    }
+
+    /// Moves the selection with the d-pad (with key repeat) and the crank. Returns `true` if the selection changed.
+    pub fn handle_input(&mut self) -> bool {
+        let before = self.selection;
+        if input::is_repeated(Buttons::Up) {
+            self.select_previous_row();
+        }
+        if input::is_repeated(Buttons::Down) {
+            self.select_next_row();
+        }
+        if self.num_columns > 1 {
+            if input::is_repeated(Buttons::Left) {
+                self.select_previous_column();
+            }
+            if input::is_repeated(Buttons::Right) {
+                self.select_next_column();
+            }
+        }
+        if self.crank_ticks_per_revolution > 0 {
+            let ticks = crank::get_crank_ticks(self.crank_ticks_per_revolution);
+            for _ in 0..ticks.unsigned_abs() {
+                if ticks > 0 {
+                    self.select_next_row();
+                } else {
+                    self.select_previous_row();
+                }
+            }
+        }
+        self.selection != before
+    }
+
+    /// Scrolls so that the given cell is visible, or centered if [`GridView::set_scroll_cells_to_center`] is set.
+    pub fn scroll_to_cell(&mut self, index: GridIndex, animated: bool) {
+        let cell = self.get_cell_bounds(index);
+        let view = inset_rect(
+            Rect::new(0, 0, self.view_size.width, self.view_size.height),
+            self.content_inset,
+        );
+        let mut target = self.scroll_to;
+        if self.scroll_cells_to_center {
+            target.x = (cell.x + cell.width / 2 - view.width / 2) as f32;
+            target.y = (cell.y + cell.height / 2 - view.height / 2) as f32;
+        } else {
+            // Reveal the section header together with the first row
+            let top = if index.row == 0 {
+                cell.y - self.section_header_height
+            } else {
+                cell.y
+            };
+            if (top as f32) < target.y {
+                target.y = top as f32;
+            } else if ((cell.y + cell.height) as f32) > target.y + view.height as f32 {
+                target.y = (cell.y + cell.height - view.height) as f32;
+            }
+            if (cell.x as f32) < target.x {
+                target.x = cell.x as f32;
+            } else if ((cell.x + cell.width) as f32) > target.x + view.width as f32 {
+                target.x = (cell.x + cell.width - view.width) as f32;
+            }
+        }
+        self.scroll_to_position(target, animated);
+    }
+
+    /// Scrolls the content so that the given content position is at the top-left corner of the view.
+    pub fn scroll_to_position(&mut self, position: Vec2<f32>, animated: bool) {
+        let content = self.get_content_size();
+        let view = inset_rect(
+            Rect::new(0, 0, self.view_size.width, self.view_size.height),
+            self.content_inset,
+        );
+        let max = Vec2::new(
+            (content.width - view.width).max(0) as f32,
+            (content.height - view.height).max(0) as f32,
+        );
+        let target = Vec2::new(position.x.clamp(0.0, max.x), position.y.clamp(0.0, max.y));
+        if animated && self.scroll_duration > 0.0 {
+            self.scroll_from = self.scroll;
+            self.scroll_elapsed = 0.0;
+        } else {
+            self.scroll = target;
+            self.scroll_from = target;
+            self.scroll_elapsed = self.scroll_duration;
+        }
+        self.scroll_to = target;
+        self.needs_display = true;
+    }
+
+    /// Returns the current scroll position.
+    pub fn get_scroll_position(&self) -> Vec2<f32> {
+        self.scroll
+    }
+
+    /// Advances the scroll animation. `delta` is the time in seconds since the last frame.
+    pub fn update(&mut self, delta: f32) {
+        if self.scroll == self.scroll_to {
+            return;
+        }
+        self.scroll_elapsed += delta;
+        let t = if self.scroll_duration > 0.0 {
+            (self.scroll_elapsed / self.scroll_duration).min(1.0)
+        } else {
+            1.0
+        };
+        // Ease-out cubic
+        let p = 1.0 - (1.0 - t) * (1.0 - t) * (1.0 - t);
+        self.scroll = self.scroll_from + (self.scroll_to - self.scroll_from) * p;
+        if t >= 1.0 {
+            self.scroll = self.scroll_to;
+        }
+        self.needs_display = true;
+    }
+
+    /// Returns `true` if the grid view changed since it was last drawn, e.g. because the selection changed or it is scrolling.
+    pub fn needs_display(&self) -> bool {
+        self.needs_display
+    }
+
+    /// Forces the grid view to be redrawn, e.g. after the content of a cell changed.
+    pub fn set_needs_display(&mut self) {
+        self.needs_display = true;
+    }
+
+    /// Returns the bounds of the given cell, relative to the top-left corner of the content.
+    pub fn get_cell_bounds(&self, index: GridIndex) -> Rect<i32> {
+        let mut y = 0;
+        for rows in self.rows_in_section.iter().take(index.section) {
+            y += self.section_header_height + *rows as i32 * self.cell_size.height;
+        }
+        y += self.section_header_height + index.row as i32 * self.cell_size.height;
+        let width = self.get_cell_width();
+        Rect::new(index.column as i32 * width, y, width, self.cell_size.height)
+    }
+
+    /// Returns the size of the whole content, including all sections and headers.
+    pub fn get_content_size(&self) -> Size<i32> {
+        let height = self
+            .rows_in_section
+            .iter()
+            .map(|rows| self.section_header_height + *rows as i32 * self.cell_size.height)
+            .sum();
+        Size::new(self.get_cell_width() * self.num_columns as i32, height)
+    }
+
+    /// Draws the grid view in the given rect of the current drawing context.
+    ///
+    /// The cells are clipped to the rect minus the content inset with the clip rect, which is cleared afterwards: the clip rect of the caller is not kept, set it again after drawing if needed.
+    pub fn draw_in_rect(&mut self, rect: Rect<i32>) {
+        // Take the draw functions out so that they can be called while `self` is borrowed
+        let draw_cell = self.draw_cell.take();
+        let draw_section_header = self.draw_section_header.take();
+        self.draw_with(rect, draw_cell.as_deref(), draw_section_header.as_deref());
+        self.draw_cell = draw_cell;
+        self.draw_section_header = draw_section_header;
+    }
+
+    /// Draws the grid view into the given bitmap, filling the whole bitmap.
+    pub fn draw_into_bitmap(&mut self, bitmap: &Bitmap) {
+        let size = bitmap.get_bitmap_data().size;
+        PLAYDATE.graphics.push_context(bitmap);
+        self.draw_in_rect(Rect::new(0, 0, size.width, size.height));
+        PLAYDATE.graphics.pop_context();
+    }
+
+    /// Draws the grid view in the given rect with the given draw functions, instead of the registered ones.
+    pub(super) fn draw_with(
+        &mut self,
+        rect: Rect<i32>,
+        draw_cell: Option<&dyn Fn(GridIndex, bool, Rect<i32>)>,
+        draw_section_header: Option<&dyn Fn(usize, Rect<i32>)>,
+    ) {
+        if self.view_size != rect.size() {
+            self.view_size = rect.size();
+            // Keep the scroll position valid for the new size
+            self.scroll_to_position(self.scroll_to, false);
+        }
+        if let Some(background) = &self.background {
+            background.draw_in_rect(rect);
+        }
+        let view = inset_rect(rect, self.content_inset);
+        let scroll = self.scroll.round().cast::<i32>();
+        let cell_width = self.get_cell_width();
+        let cell_height = self.cell_size.height;
+        let is_visible = |r: &Rect<i32>| {
+            r.x < view.x + view.width
+                && r.x + r.width > view.x
+                && r.y < view.y + view.height
+                && r.y + r.height > view.y
+        };
+        PLAYDATE.graphics.set_clip_rect(view);
+        let mut y = view.y - scroll.y;
+        for (section, rows) in self.rows_in_section.iter().enumerate() {
+            if self.section_header_height > 0 {
+                let header = Rect::new(view.x, y, view.width, self.section_header_height);
+                if let Some(draw_header) = draw_section_header {
+                    if is_visible(&header) {
+                        draw_header(section, header);
+                    }
+                }
+                y += self.section_header_height;
+            }
+            for row in 0..*rows {
+                for column in 0..self.num_columns {
+                    let cell = Rect::new(
+                        view.x + column as i32 * cell_width - scroll.x,
+                        y,
+                        cell_width,
+                        cell_height,
+                    );
+                    if !is_visible(&cell) {
+                        continue;
+                    }
+                    if let Some(draw_cell) = draw_cell {
+                        let index = GridIndex::new(section, row, column);
+                        let selected = self.selection == Some(index);
+                        draw_cell(index, selected, inset_rect(cell, self.cell_padding));
+                    }
+                }
+                y += cell_height;
+            }
+        }
+        PLAYDATE.graphics.clear_clip_rect();
+        self.needs_display = self.scroll != self.scroll_to;
+    }
+
+    fn get_cell_width(&self) -> i32 {
+        if self.cell_size.width > 0 {
+            self.cell_size.width
+        } else {
+            let view = inset_rect(
+                Rect::new(0, 0, self.view_size.width, self.view_size.height),
+                self.content_inset,
+            );
+            view.width / self.num_columns as i32
+        }
+    }
+
+    fn is_valid(&self, index: GridIndex) -> bool {
+        index.section < self.rows_in_section.len()
+            && index.row < self.rows_in_section[index.section]
+            && index.column < self.num_columns
+    }
+
+    fn clamp_selection(&mut self) {
+        if let Some(index) = self.selection {
+            if !self.is_valid(index) {
+                self.selection = None;
+            }
+        }
+        self.needs_display = true;
+    }
+
+    fn move_selection(&mut self, index: Option<GridIndex>) {
+        if let Some(index) = index {
+            self.selection = Some(index);
+            self.scroll_to_cell(index, true);
+        }
+    }
+
+    fn first_cell(&self) -> Option<GridIndex> {
+        self.rows_in_section
+            .iter()
+            .position(|rows| *rows > 0)
+            .map(|section| GridIndex::new(section, 0, 0))
+    }
+
+    fn last_row(&self, column: usize) -> Option<GridIndex> {
+        self.rows_in_section
+            .iter()
+            .rposition(|rows| *rows > 0)
+            .map(|section| GridIndex::new(section, self.rows_in_section[section] - 1, column))
+    }
+
+    fn next_row(&self, index: GridIndex) -> Option<GridIndex> {
+        if index.row + 1 < self.rows_in_section[index.section] {
+            return Some(GridIndex::new(index.section, index.row + 1, index.column));
+        }
+        let next_section = (index.section + 1..self.rows_in_section.len())
+            .find(|section| self.rows_in_section[*section] > 0);
+        match next_section {
+            Some(section) => Some(GridIndex::new(section, 0, index.column)),
+            None if self.wrap_selection => self
+                .first_cell()
+                .map(|i| GridIndex::new(i.section, i.row, index.column)),
+            None => None,
+        }
+    }
+
+    fn previous_row(&self, index: GridIndex) -> Option<GridIndex> {
+        if index.row > 0 {
+            return Some(GridIndex::new(index.section, index.row - 1, index.column));
+        }
+        let prev_section = (0..index.section)
+            .rev()
+            .find(|section| self.rows_in_section[*section] > 0);
+        match prev_section {
+            Some(section) => Some(GridIndex::new(
+                section,
+                self.rows_in_section[section] - 1,
+                index.column,
+            )),
+            None if self.wrap_selection => self.last_row(index.column),
+            None => None,
+        }
+    }
+}
@@ -0,0 +1,475 @@
+use alloc::{boxed::Box, string::String};
+
+// required for thumbv7em builds
+#[allow(unused_imports)]
+use num_traits::Float;
+
+use crate::{
+    crank,
+    display::{DISPLAY_HEIGHT, DISPLAY_WIDTH},
+    graphics::{BitmapDrawMode, Color, Font},
+    input,
+    math::{Rect, Vec2},
+    system::Buttons,
+    PLAYDATE,
+};
+
+/// Width of the keyboard, in pixels.
+pub const KEYBOARD_WIDTH: i32 = 160;
+/// Default number of crank ticks per revolution used to move the selection.
+const DEFAULT_CRANK_TICKS_PER_REVOLUTION: u32 = 12;
+/// Width of the character column, in pixels. The menu column takes the rest of the keyboard.
+const CHARACTER_COLUMN_WIDTH: i32 = 72;
+/// Default row height, used when no font is set.
+const DEFAULT_ROW_HEIGHT: i32 = 24;
+/// Duration of the slide animation, in seconds.
+const SLIDE_DURATION: f32 = 0.2;
+/// How fast the character column scrolls towards the selection, in rows per second per row of distance.
+const SCROLL_SPEED: f32 = 16.0;
+
+const UPPERCASE: &[char] = &[
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
+    'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+];
+const LOWERCASE: &[char] = &[
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z',
+];
+const NUMBERS: &[char] = &[
+    '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', '.', ',', ':', ';', '!', '?', '\'', '"', '-',
+    '_', '+', '=', '*', '/', '\\', '&', '%', '$', '#', '@', '(', ')', '[', ']', '{', '}', '<', '>',
+    '^', '`', '|', '~',
+];
+
+/// The characters shown in the character column of a [`Keyboard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum KeyboardCharacterSet {
+    #[default]
+    Uppercase,
+    Lowercase,
+    /// Digits and punctuation.
+    Numbers,
+}
+
+impl KeyboardCharacterSet {
+    /// Returns the characters of the set, in display order.
+    pub fn get_characters(&self) -> &'static [char] {
+        match self {
+            KeyboardCharacterSet::Uppercase => UPPERCASE,
+            KeyboardCharacterSet::Lowercase => LOWERCASE,
+            KeyboardCharacterSet::Numbers => NUMBERS,
+        }
+    }
+}
+
+/// Keys of the menu column of a [`Keyboard`], from top to bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuKey {
+    Uppercase,
+    Lowercase,
+    Numbers,
+    Space,
+    Delete,
+    Ok,
+}
+
+const MENU: [(MenuKey, &str); 6] = [
+    (MenuKey::Uppercase, "ABC"),
+    (MenuKey::Lowercase, "abc"),
+    (MenuKey::Numbers, "123"),
+    (MenuKey::Space, "space"),
+    (MenuKey::Delete, "del"),
+    (MenuKey::Ok, "OK"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Characters,
+    Menu,
+}
+
+type TextChangedFn = Box<dyn FnMut(&str)>;
+type ConfirmFn = Box<dyn FnMut(&str)>;
+type CancelFn = Box<dyn FnMut()>;
+
+/// An on-screen keyboard for text entry, similar to the Lua `playdate.keyboard`.
+///
+/// The keyboard slides in from the right edge of the screen and has two columns: a scrolling column of characters, and a menu to switch character sets, insert a space, delete a character or confirm. Up/down and the crank move the selection, left/right switch between the columns, A activates the selected key, and B deletes the last character. Pressing B with an empty text cancels the entry.
+///
+/// The keyboard does not draw the text being edited. Use [`Keyboard::get_text`] or the text-changed callback to draw it where it belongs in the game, left of [`Keyboard::get_frame`].
+///
+/// Call [`Keyboard::handle_input`] and [`Keyboard::update`] once per frame, then [`Keyboard::draw`] after all other drawing is done.
+pub struct Keyboard {
+    text: String,
+    max_length: Option<usize>,
+    character_set: KeyboardCharacterSet,
+    column: Column,
+    selected_character: [usize; 3],
+    selected_menu_key: usize,
+    scroll: f32,
+    crank_ticks_per_revolution: u32,
+    visible: bool,
+    slide: f32,
+    font: Option<Font>,
+    on_text_changed: Option<TextChangedFn>,
+    on_confirm: Option<ConfirmFn>,
+    on_cancel: Option<CancelFn>,
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Keyboard {
+    /// Create a new, hidden keyboard.
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            max_length: None,
+            character_set: KeyboardCharacterSet::Uppercase,
+            column: Column::Characters,
+            selected_character: [0; 3],
+            selected_menu_key: 0,
+            scroll: 0.0,
+            crank_ticks_per_revolution: DEFAULT_CRANK_TICKS_PER_REVOLUTION,
+            visible: false,
+            slide: 0.0,
+            font: None,
+            on_text_changed: None,
+            on_confirm: None,
+            on_cancel: None,
+        }
+    }
+
+    /// Shows the keyboard, starting with the given text.
+    pub fn show(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.column = Column::Characters;
+        self.scroll = self.get_selected_character_index() as f32;
+        self.visible = true;
+    }
+
+    /// Hides the keyboard without calling the confirm or cancel callbacks.
+    pub fn hide(&mut self) {
+        self.visible = false;
+    }
+
+    /// Returns `true` if the keyboard is shown, including while it slides in or out.
+    pub fn is_visible(&self) -> bool {
+        self.visible || self.slide > 0.0
+    }
+
+    /// Returns the text entered so far.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text. The text-changed callback is not called.
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+    }
+
+    /// Sets the maximum number of characters of the text. `None` means no limit.
+    pub fn set_max_length(&mut self, max_length: Option<usize>) {
+        self.max_length = max_length;
+    }
+
+    /// Sets the font used to draw the keys. If no font is set, the current font of the graphics context is used.
+    pub fn set_font(&mut self, font: Font) {
+        self.font = Some(font);
+    }
+
+    /// Sets the character set shown in the character column.
+    pub fn set_character_set(&mut self, set: KeyboardCharacterSet) {
+        self.character_set = set;
+        self.scroll = self.get_selected_character_index() as f32;
+    }
+
+    /// Returns the character set shown in the character column.
+    pub fn get_character_set(&self) -> KeyboardCharacterSet {
+        self.character_set
+    }
+
+    /// Returns the selected character, or `None` if the selection is in the menu column.
+    pub fn get_selected_character(&self) -> Option<char> {
+        match self.column {
+            Column::Characters => {
+                Some(self.character_set.get_characters()[self.get_selected_character_index()])
+            }
+            Column::Menu => None,
+        }
+    }
+
+    /// Sets how many crank ticks per revolution move the selection by one key. 0 disables the crank.
+    pub fn set_crank_ticks_per_revolution(&mut self, ticks: u32) {
+        self.crank_ticks_per_revolution = ticks;
+    }
+
+    /// Sets the function called with the new text every time the text is changed by the player.
+    pub fn set_text_changed_callback(&mut self, callback: impl FnMut(&str) + 'static) {
+        self.on_text_changed = Some(Box::new(callback));
+    }
+
+    /// Sets the function called with the final text when the player selects OK. The keyboard hides itself afterwards.
+    pub fn set_confirm_callback(&mut self, callback: impl FnMut(&str) + 'static) {
+        self.on_confirm = Some(Box::new(callback));
+    }
+
+    /// Sets the function called when the player cancels the entry by pressing B with an empty text. The keyboard hides itself afterwards.
+    pub fn set_cancel_callback(&mut self, callback: impl FnMut() + 'static) {
+        self.on_cancel = Some(Box::new(callback));
+    }
+
+    /// Returns the area of the screen currently covered by the keyboard, taking the slide animation into account.
+    pub fn get_frame(&self) -> Rect<i32> {
+        let t = 1.0 - (1.0 - self.slide) * (1.0 - self.slide);
+        let width = (KEYBOARD_WIDTH as f32 * t).round() as i32;
+        Rect::new(
+            DISPLAY_WIDTH as i32 - width,
+            0,
+            KEYBOARD_WIDTH,
+            DISPLAY_HEIGHT as _,
+        )
+    }
+
+    /// Handles the d-pad, crank, A and B buttons while the keyboard is shown. Does nothing while the keyboard is hidden or sliding out.
+    pub fn handle_input(&mut self) {
+        if !self.visible {
+            return;
+        }
+        if input::just_pressed(Buttons::Left) {
+            self.column = Column::Characters;
+        }
+        if input::just_pressed(Buttons::Right) {
+            self.column = Column::Menu;
+        }
+        let mut steps = 0;
+        if input::is_repeated(Buttons::Up) {
+            steps -= 1;
+        }
+        if input::is_repeated(Buttons::Down) {
+            steps += 1;
+        }
+        if self.crank_ticks_per_revolution > 0 {
+            steps += crank::get_crank_ticks(self.crank_ticks_per_revolution);
+        }
+        if steps != 0 {
+            self.move_selection(steps);
+        }
+        if input::just_pressed(Buttons::A) {
+            self.activate();
+        }
+        if input::is_repeated(Buttons::B) {
+            if self.text.is_empty() {
+                if input::just_pressed(Buttons::B) {
+                    self.cancel();
+                }
+            } else {
+                self.delete();
+            }
+        }
+    }
+
+    /// Advances the animations. `delta` is the time in seconds since the last frame.
+    pub fn update(&mut self, delta: f32) {
+        let step = delta / SLIDE_DURATION;
+        self.slide = if self.visible {
+            (self.slide + step).min(1.0)
+        } else {
+            (self.slide - step).max(0.0)
+        };
+        let target = self.get_selected_character_index() as f32;
+        let t = (delta * SCROLL_SPEED).min(1.0);
+        self.scroll += (target - self.scroll) * t;
+        if (target - self.scroll).abs() < 0.01 {
+            self.scroll = target;
+        }
+    }
+
+    /// Draws the keyboard at the right edge of the screen.
+    pub fn draw(&self) {
+        if !self.is_visible() {
+            return;
+        }
+        let frame = self.get_frame();
+        let graphics = &PLAYDATE.graphics;
+        if let Some(font) = &self.font {
+            graphics.set_font(font);
+        }
+        graphics.fill_rect(frame, Color::White);
+        graphics.draw_line(
+            Vec2::new(frame.x, 0),
+            Vec2::new(frame.x, frame.height),
+            2,
+            Color::Black,
+        );
+        let row_height = self.get_row_height();
+
+        // Character column: the selected row stays in the middle and the other characters scroll past it
+        let column = Rect::new(frame.x + 2, 0, CHARACTER_COLUMN_WIDTH - 2, frame.height);
+        let center = frame.height / 2;
+        let selected = Rect::new(column.x, center - row_height / 2, column.width, row_height);
+        if self.column == Column::Characters {
+            graphics.fill_rect(selected, Color::Black);
+        } else {
+            graphics.draw_rect(selected, Color::Black);
+        }
+        let characters = self.character_set.get_characters();
+        let base = self.scroll.floor();
+        let frac = self.scroll - base;
+        let visible_rows = center / row_height + 1;
+        let mut buf = [0u8; 4];
+        for k in -visible_rows..=visible_rows + 1 {
+            let index = (base as i32 + k).rem_euclid(characters.len() as i32) as usize;
+            let y = selected.y + ((k as f32 - frac) * row_height as f32).round() as i32;
+            let rect = Rect::new(column.x, y, column.width, row_height);
+            let highlighted =
+                self.column == Column::Characters && (y - selected.y).abs() < row_height / 2;
+            self.draw_label(characters[index].encode_utf8(&mut buf), rect, highlighted);
+        }
+
+        // Menu column
+        let menu = Rect::new(
+            column.x + column.width,
+            0,
+            frame.width - column.width - 2,
+            frame.height,
+        );
+        graphics.draw_line(
+            Vec2::new(menu.x, 0),
+            Vec2::new(menu.x, menu.height),
+            1,
+            Color::Black,
+        );
+        let top = center - row_height * MENU.len() as i32 / 2;
+        for (i, (key, label)) in MENU.iter().enumerate() {
+            let rect = Rect::new(
+                menu.x + 4,
+                top + i as i32 * row_height,
+                menu.width - 8,
+                row_height,
+            );
+            let highlighted = self.column == Column::Menu && i == self.selected_menu_key;
+            if highlighted {
+                graphics.fill_rect(rect, Color::Black);
+            } else if self.get_menu_character_set(*key) == Some(self.character_set) {
+                graphics.draw_rect(rect, Color::Black);
+            }
+            self.draw_label(label, rect, highlighted);
+        }
+    }
+
+    fn draw_label(&self, label: &str, rect: Rect<i32>, highlighted: bool) {
+        let graphics = &PLAYDATE.graphics;
+        if highlighted {
+            graphics.set_draw_mode(BitmapDrawMode::FillWhite);
+        }
+        let pos = match &self.font {
+            Some(font) => Vec2::new(
+                rect.x + (rect.width - font.get_text_width(label, 0) as i32) / 2,
+                rect.y + (rect.height - font.get_height() as i32) / 2,
+            ),
+            None => Vec2::new(rect.x + 4, rect.y + 2),
+        };
+        graphics.draw_text(label, pos);
+        graphics.set_draw_mode(BitmapDrawMode::Copy);
+    }
+
+    fn get_row_height(&self) -> i32 {
+        match &self.font {
+            Some(font) => font.get_height() as i32 + 8,
+            None => DEFAULT_ROW_HEIGHT,
+        }
+    }
+
+    fn get_selected_character_index(&self) -> usize {
+        self.selected_character[self.character_set as usize]
+    }
+
+    fn get_menu_character_set(&self, key: MenuKey) -> Option<KeyboardCharacterSet> {
+        match key {
+            MenuKey::Uppercase => Some(KeyboardCharacterSet::Uppercase),
+            MenuKey::Lowercase => Some(KeyboardCharacterSet::Lowercase),
+            MenuKey::Numbers => Some(KeyboardCharacterSet::Numbers),
+            _ => None,
+        }
+    }
+
+    fn move_selection(&mut self, steps: i32) {
+        match self.column {
+            Column::Characters => {
+                // The character column wraps around
+                let len = self.character_set.get_characters().len() as i32;
+                let index = &mut self.selected_character[self.character_set as usize];
+                let next = *index as i32 + steps;
+                // Keep the scroll animation continuous across the wraparound
+                self.scroll -= (next.div_euclid(len) * len) as f32;
+                *index = next.rem_euclid(len) as usize;
+            }
+            Column::Menu => {
+                let next = self.selected_menu_key as i32 + steps;
+                self.selected_menu_key = next.clamp(0, MENU.len() as i32 - 1) as usize;
+            }
+        }
+    }
+
+    fn activate(&mut self) {
+        match self.column {
+            Column::Characters => {
+                let c = self.character_set.get_characters()[self.get_selected_character_index()];
+                self.insert(c);
+            }
+            Column::Menu => {
+                let key = MENU[self.selected_menu_key].0;
+                if let Some(set) = self.get_menu_character_set(key) {
+                    self.set_character_set(set);
+                    return;
+                }
+                match key {
+                    MenuKey::Space => self.insert(' '),
+                    MenuKey::Delete => self.delete(),
+                    MenuKey::Ok => self.confirm(),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, c: char) {
+        if self
+            .max_length
+            .is_some_and(|max| self.text.chars().count() >= max)
+        {
+            return;
+        }
+        self.text.push(c);
+        if let Some(callback) = self.on_text_changed.as_mut() {
+            callback(&self.text);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.text.pop().is_none() {
+            return;
+        }
+        if let Some(callback) = self.on_text_changed.as_mut() {
+            callback(&self.text);
+        }
+    }
+
+    fn confirm(&mut self) {
+        self.visible = false;
+        if let Some(callback) = self.on_confirm.as_mut() {
+            callback(&self.text);
+        }
+    }
+
+    fn cancel(&mut self) {
+        self.visible = false;
+        if let Some(callback) = self.on_cancel.as_mut() {
+            callback();
+        }
+    }
+}
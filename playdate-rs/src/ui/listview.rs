@@ -0,0 +1,185 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    graphics::{Bitmap, BitmapDrawMode, Color, Font, NineSlice},
+    math::{Rect, SideOffsets, Size, Vec2},
+    PLAYDATE,
+};
+
+use super::{GridIndex, GridView};
+
+/// Default height of a row in a [`ListView`].
+pub const DEFAULT_ROW_HEIGHT: i32 = 24;
+
+/// A vertical list of text items, with a highlighted selected row. This is a [`GridView`] with one section and one column, that draws its own cells.
+///
+/// The selected row is drawn white on black. Use a [`GridView`] directly for custom cell drawing.
+pub struct ListView {
+    grid: GridView,
+    items: Vec<String>,
+    font: Option<Font>,
+}
+
+impl Default for ListView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ListView {
+    /// Create an empty list view with rows of [`DEFAULT_ROW_HEIGHT`] pixels.
+    pub fn new() -> Self {
+        let mut grid = GridView::new(Size::new(0, DEFAULT_ROW_HEIGHT));
+        grid.set_cell_padding(SideOffsets::new(8, 8, 2, 2));
+        Self {
+            grid,
+            items: Vec::new(),
+            font: None,
+        }
+    }
+
+    /// Replaces the items of the list. The selection is cleared if it is out of range.
+    pub fn set_items(&mut self, items: impl IntoIterator<Item = impl Into<String>>) {
+        self.items = items.into_iter().map(Into::into).collect();
+        self.grid.set_number_of_rows_in_section(0, self.items.len());
+    }
+
+    /// Returns the items of the list.
+    pub fn get_items(&self) -> &[String] {
+        &self.items
+    }
+
+    /// Sets the font used to draw the items. If no font is set, the current font of the graphics context is used.
+    pub fn set_font(&mut self, font: Font) {
+        self.font = Some(font);
+        self.grid.set_needs_display();
+    }
+
+    /// Sets the height of each row.
+    pub fn set_row_height(&mut self, height: i32) {
+        self.grid.set_cell_size(Size::new(0, height));
+    }
+
+    /// Sets the padding around the text of each row.
+    pub fn set_cell_padding(&mut self, padding: SideOffsets<i32>) {
+        self.grid.set_cell_padding(padding);
+    }
+
+    /// Sets the inset between the edges of the list view and its rows.
+    pub fn set_content_inset(&mut self, inset: SideOffsets<i32>) {
+        self.grid.set_content_inset(inset);
+    }
+
+    /// Sets a background image, see [`GridView::set_background_image`].
+    pub fn set_background_image(&mut self, image: NineSlice) {
+        self.grid.set_background_image(image);
+    }
+
+    /// If set, moving the selection past the last row wraps around to the first one, and vice versa.
+    pub fn set_wrap_selection(&mut self, wrap: bool) {
+        self.grid.set_wrap_selection(wrap);
+    }
+
+    /// If set, scrolling to a row centers it in the view.
+    pub fn set_scroll_cells_to_center(&mut self, center: bool) {
+        self.grid.set_scroll_cells_to_center(center);
+    }
+
+    /// Sets the duration of the scroll animation, in seconds. 0 disables the animation.
+    pub fn set_scroll_duration(&mut self, duration: f32) {
+        self.grid.set_scroll_duration(duration);
+    }
+
+    /// Sets how many crank ticks per revolution move the selection by one row. 0 disables crank scrolling.
+    pub fn set_crank_ticks_per_revolution(&mut self, ticks: u32) {
+        self.grid.set_crank_ticks_per_revolution(ticks);
+    }
+
+    /// Returns the index of the selected row, if any.
+    pub fn get_selected_row(&self) -> Option<usize> {
+        self.grid.get_selection().map(|index| index.row)
+    }
+
+    /// Returns the selected item, if any.
+    pub fn get_selected_item(&self) -> Option<&str> {
+        self.get_selected_row()
+            .and_then(|row| self.items.get(row))
+            .map(String::as_str)
+    }
+
+    /// Selects the given row and scrolls to it. Out of range rows clear the selection.
+    pub fn set_selected_row(&mut self, row: usize) {
+        self.grid.set_selection(GridIndex::new(0, row, 0));
+    }
+
+    /// Moves the selection to the next row.
+    pub fn select_next_row(&mut self) {
+        self.grid.select_next_row();
+    }
+
+    /// Moves the selection to the previous row.
+    pub fn select_previous_row(&mut self) {
+        self.grid.select_previous_row();
+    }
+
+    /// Moves the selection with the d-pad (with key repeat) and the crank. Returns `true` if the selection changed.
+    pub fn handle_input(&mut self) -> bool {
+        self.grid.handle_input()
+    }
+
+    /// Advances the scroll animation. `delta` is the time in seconds since the last frame.
+    pub fn update(&mut self, delta: f32) {
+        self.grid.update(delta);
+    }
+
+    /// Returns `true` if the list view changed since it was last drawn.
+    pub fn needs_display(&self) -> bool {
+        self.grid.needs_display()
+    }
+
+    /// Returns the underlying grid view.
+    pub fn get_grid_view(&mut self) -> &mut GridView {
+        &mut self.grid
+    }
+
+    /// Draws the list view in the given rect of the current drawing context. Like [`GridView::draw_in_rect`], it clears the clip rect.
+    pub fn draw_in_rect(&mut self, rect: Rect<i32>) {
+        let items = &self.items;
+        let font = self.font.as_ref();
+        let padding = self.grid.get_cell_padding();
+        let draw_cell = |index: GridIndex, selected: bool, rect: Rect<i32>| {
+            let Some(item) = items.get(index.row) else {
+                return;
+            };
+            if let Some(font) = font {
+                PLAYDATE.graphics.set_font(font);
+            }
+            if selected {
+                // Highlight the whole row, not just the padded content
+                let cell = Rect::new(
+                    rect.x - padding.left,
+                    rect.y - padding.top,
+                    rect.width + padding.left + padding.right,
+                    rect.height + padding.top + padding.bottom,
+                );
+                PLAYDATE.graphics.fill_rect(cell, Color::Black);
+                PLAYDATE.graphics.set_draw_mode(BitmapDrawMode::FillWhite);
+            }
+            let y = match font {
+                Some(font) => rect.y + (rect.height - font.get_height() as i32) / 2,
+                None => rect.y,
+            };
+            PLAYDATE.graphics.draw_text(item, Vec2::new(rect.x, y));
+            PLAYDATE.graphics.set_draw_mode(BitmapDrawMode::Copy);
+        };
+        self.grid.draw_with(rect, Some(&draw_cell), None);
+    }
+
+    /// Draws the list view into the given bitmap, filling the whole bitmap.
+    pub fn draw_into_bitmap(&mut self, bitmap: &Bitmap) {
+        let size = bitmap.get_bitmap_data().size;
+        PLAYDATE.graphics.push_context(bitmap);
+        self.draw_in_rect(Rect::new(0, 0, size.width, size.height));
+        PLAYDATE.graphics.pop_context();
+    }
+}
@@ -0,0 +1,19 @@
+mod gridview;
+mod keyboard;
+mod listview;
+
+pub use gridview::{GridIndex, GridView};
+pub use keyboard::{Keyboard, KeyboardCharacterSet, KEYBOARD_WIDTH};
+pub use listview::ListView;
+
+use crate::math::{Rect, SideOffsets};
+
+/// Shrinks the rect by the given insets.
+pub(crate) fn inset_rect(rect: Rect<i32>, insets: SideOffsets<i32>) -> Rect<i32> {
+    Rect::new(
+        rect.x + insets.left,
+        rect.y + insets.top,
+        (rect.width - insets.left - insets.right).max(0),
+        (rect.height - insets.top - insets.bottom).max(0),
+    )
+}